@@ -0,0 +1,70 @@
+// JSON-RPC 引擎服务入口 - 常驻进程，从标准输入逐行读取请求，应答逐行写到标准输出
+// 对局状态保存在服务端，测试脚本和其它语言的机器人只需发送坐标
+//
+// 示例：
+//   {"jsonrpc":"2.0","id":1,"method":"new_game","params":{"rule":"renju","ai_player":-1}}
+//   {"jsonrpc":"2.0","id":2,"method":"play","params":{"row":7,"col":7}}
+//   {"jsonrpc":"2.0","id":3,"method":"ai_move","params":{"difficulty":"hard","time_ms":1000}}
+//   {"jsonrpc":"2.0","id":4,"method":"report","params":{"engine":"mcts","time_ms":1000}}
+//   {"jsonrpc":"2.0","id":5,"method":"set_weights","params":{"weights":{"live_three":2500}}}
+//   {"jsonrpc":"2.0","id":6,"method":"ai_choose_color","params":{"difficulty":"hard"}}
+//
+// 可选参数 --weights <文件>：启动时从 JSON/TOML 文件载入评估权重
+
+mod rpc;
+mod session;
+
+use std::io::{self, BufRead, Write};
+
+use session::Session;
+use shared_core::EvalWeights;
+
+fn main() -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout().lock();
+    let mut session = Session::new();
+    if let Some(weights) = weights_from_args(std::env::args().skip(1)).map_err(io::Error::other)? {
+        session.set_weights(weights);
+    }
+
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = rpc::handle_line(&mut session, &line) {
+            writeln!(stdout, "{}", response)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+// 从命令行参数中找 --weights <文件> 并载入；没有该参数时返回 None
+fn weights_from_args(args: impl IntoIterator<Item = String>) -> Result<Option<EvalWeights>, String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--weights" {
+            let path = args.next().ok_or("--weights 需要文件路径")?;
+            return EvalWeights::load(path).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn weights_argument() {
+        assert_eq!(weights_from_args(args(&[])), Ok(None));
+        assert_eq!(weights_from_args(args(&["--other"])), Ok(None));
+        assert_eq!(weights_from_args(args(&["--weights"])), Err("--weights 需要文件路径".to_string()));
+        assert!(weights_from_args(args(&["--weights", "no-such-weights.toml"])).is_err());
+    }
+}
@@ -0,0 +1,328 @@
+// JSON-RPC 2.0 消息 - 每行一条请求，每条带 id 的请求回一行应答（id 为 null 也要应答）；没有 id 字段的通知不应答
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use shared_core::{ColorChoice, EvalWeights, GameState};
+
+use crate::session::{default_difficulty, Difficulty, NewGameParams, SearchParams, Session};
+
+// JSON-RPC 规定的错误码
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+// 对局本身的错误（非法落子、无法悔棋等）
+const GAME_ERROR: i32 = -32000;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    jsonrpc: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+    // 没有 id 字段为 None（通知）；"id": null 为 Some(Value::Null)，仍要应答
+    #[serde(default, deserialize_with = "present")]
+    id: Option<Value>,
+}
+
+// 字段存在时（包括 null）为 Some
+fn present<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        RpcError { code, message: message.into() }
+    }
+}
+
+#[derive(Deserialize)]
+struct PlayParams {
+    row: usize,
+    col: usize,
+}
+
+#[derive(Deserialize)]
+struct UndoParams {
+    #[serde(default = "one")]
+    count: usize,
+}
+
+fn one() -> usize {
+    1
+}
+
+#[derive(Deserialize)]
+struct LoadParams {
+    state: GameState,
+    #[serde(default)]
+    winner: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct ChooseColorParams {
+    choice: ColorChoice,
+}
+
+#[derive(Deserialize)]
+struct AiColorParams {
+    #[serde(default = "default_difficulty")]
+    difficulty: Difficulty,
+}
+
+#[derive(Deserialize)]
+struct EvaluateParams {
+    player: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct AnalyzeParams {
+    #[serde(default = "default_analyze_difficulty")]
+    difficulty: Difficulty,
+    #[serde(default = "default_multi_pv")]
+    multi_pv: usize,
+}
+
+fn default_analyze_difficulty() -> Difficulty {
+    Difficulty::Hard
+}
+
+fn default_multi_pv() -> usize {
+    3
+}
+
+// 省略的字段取内置默认值
+#[derive(Deserialize)]
+struct WeightsParams {
+    #[serde(default)]
+    weights: EvalWeights,
+}
+
+#[derive(Deserialize)]
+struct CandidatesParams {
+    limit: Option<usize>,
+}
+
+// 处理一行输入，返回要写回的一行；通知返回 None
+pub fn handle_line(session: &mut Session, line: &str) -> Option<String> {
+    let request: Request = match serde_json::from_str::<Value>(line) {
+        Err(e) => return Some(error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string()))),
+        Ok(value) => match serde_json::from_value(value) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, RpcError::new(INVALID_REQUEST, e.to_string()))),
+        },
+    };
+
+    if request.jsonrpc != "2.0" {
+        let error = RpcError::new(INVALID_REQUEST, "jsonrpc 必须为 \"2.0\"");
+        return Some(error_response(request.id.unwrap_or(Value::Null), error));
+    }
+
+    let outcome = dispatch(session, &request.method, request.params);
+    let id = request.id?;
+    let response = match outcome {
+        Ok(result) => Response { jsonrpc: "2.0", id, result: Some(result), error: None },
+        Err(error) => Response { jsonrpc: "2.0", id, result: None, error: Some(error) },
+    };
+    Some(serde_json::to_string(&response).expect("应答必然可以序列化"))
+}
+
+fn error_response(id: Value, error: RpcError) -> String {
+    serde_json::to_string(&Response { jsonrpc: "2.0", id, result: None, error: Some(error) }).expect("应答必然可以序列化")
+}
+
+fn dispatch(session: &mut Session, method: &str, params: Value) -> Result<Value, RpcError> {
+    match method {
+        "new_game" => reply(session.new_game(parse_params::<NewGameParams>(params)?)),
+        "get_state" => reply(Ok(session.state())),
+        "load_state" => {
+            let LoadParams { state, winner } = parse_params(params)?;
+            let loaded = session.load(state, winner).map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+            reply(Ok(loaded))
+        }
+        "play" => {
+            let PlayParams { row, col } = parse_params(params)?;
+            reply(session.play(row, col))
+        }
+        "undo" => {
+            let UndoParams { count } = parse_params(params)?;
+            reply(session.undo(count))
+        }
+        "choose_color" => {
+            let ChooseColorParams { choice } = parse_params(params)?;
+            reply(session.choose_color(choice))
+        }
+        "ai_choose_color" => {
+            let AiColorParams { difficulty } = parse_params(params)?;
+            reply(session.ai_choose_color(difficulty))
+        }
+        "best_move" => reply(session.best_move(parse_params::<SearchParams>(params)?)),
+        "report" => reply(session.report(parse_params::<SearchParams>(params)?)),
+        "ai_move" => reply(session.ai_move(parse_params::<SearchParams>(params)?)),
+        "evaluate" => {
+            let EvaluateParams { player } = parse_params(params)?;
+            let score = session.evaluate(player).map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+            Ok(json!({ "score": score }))
+        }
+        "analyze" => {
+            let AnalyzeParams { difficulty, multi_pv } = parse_params(params)?;
+            reply(Ok(session.analyze(difficulty, multi_pv)))
+        }
+        "candidates" => {
+            let CandidatesParams { limit } = parse_params(params)?;
+            reply(Ok(session.candidates(limit)))
+        }
+        "get_weights" => reply(Ok(session.weights())),
+        "set_weights" => {
+            let WeightsParams { weights } = parse_params(params)?;
+            reply(Ok(session.set_weights(weights)))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("未知的方法: {}", method))),
+    }
+}
+
+// 省略 params 时按空对象处理，所有字段取默认值
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn reply<T: Serialize>(result: Result<T, String>) -> Result<Value, RpcError> {
+    let value = result.map_err(|e| RpcError::new(GAME_ERROR, e))?;
+    Ok(serde_json::to_value(value).expect("结果必然可以序列化"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 发送一条请求，返回解析后的应答
+    fn call(session: &mut Session, request: Value) -> Value {
+        let line = handle_line(session, &request.to_string()).expect("带 id 的请求必有应答");
+        serde_json::from_str(&line).unwrap()
+    }
+
+    fn request(method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params })
+    }
+
+    fn error_code(response: &Value) -> Option<i64> {
+        response["error"]["code"].as_i64()
+    }
+
+    #[test]
+    fn reports_protocol_errors() {
+        let mut session = Session::new();
+        let response: Value = serde_json::from_str(&handle_line(&mut session, "{not json").unwrap()).unwrap();
+        assert_eq!(error_code(&response), Some(PARSE_ERROR as i64));
+        let response: Value = serde_json::from_str(&handle_line(&mut session, "[1, 2]").unwrap()).unwrap();
+        assert_eq!(error_code(&response), Some(INVALID_REQUEST as i64));
+        assert_eq!(error_code(&call(&mut session, request("fly", json!({})))), Some(METHOD_NOT_FOUND as i64));
+        assert_eq!(error_code(&call(&mut session, request("play", json!({ "row": "a" })))), Some(INVALID_PARAMS as i64));
+        assert_eq!(error_code(&call(&mut session, request("undo", json!({})))), Some(GAME_ERROR as i64));
+        // 通知不应答，但照样执行
+        assert_eq!(handle_line(&mut session, r#"{"jsonrpc":"2.0","method":"play","params":{"row":7,"col":7}}"#), None);
+        assert_eq!(session.state().state.board.stone_count(), 1);
+    }
+
+    #[test]
+    fn null_id_is_answered() {
+        let mut session = Session::new();
+        let line = handle_line(&mut session, r#"{"jsonrpc":"2.0","id":null,"method":"get_weights"}"#).expect("id 为 null 也要应答");
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert!(response.as_object().unwrap().contains_key("id"));
+        assert_eq!(response["id"], Value::Null);
+        assert!(response["result"].is_object());
+    }
+
+    #[test]
+    fn rejects_other_protocol_versions() {
+        let mut session = Session::new();
+        for line in [
+            r#"{"jsonrpc":"1.0","id":7,"method":"play","params":{"row":7,"col":7}}"#,
+            r#"{"jsonrpc":2.0,"id":7,"method":"play","params":{"row":7,"col":7}}"#,
+            r#"{"id":7,"method":"play","params":{"row":7,"col":7}}"#,
+        ] {
+            let response: Value = serde_json::from_str(&handle_line(&mut session, line).unwrap()).unwrap();
+            assert_eq!(error_code(&response), Some(INVALID_REQUEST as i64), "{}", line);
+            assert_eq!(response["id"], 7);
+        }
+        // 版本不对的通知也不执行
+        let response: Value = serde_json::from_str(&handle_line(&mut session, r#"{"method":"play","params":{"row":7,"col":7}}"#).unwrap()).unwrap();
+        assert_eq!((error_code(&response), &response["id"]), (Some(INVALID_REQUEST as i64), &Value::Null));
+        assert_eq!(session.state().state.board.stone_count(), 0);
+    }
+
+    #[test]
+    fn plays_and_reports_state() {
+        let mut session = Session::new();
+        let response = call(&mut session, request("new_game", json!({ "rule": "renju", "ai_player": -1 })));
+        assert_eq!(response["result"]["state"]["rule"], "renju");
+        let response = call(&mut session, request("play", json!({ "row": 7, "col": 7 })));
+        assert_eq!(response["result"]["state"]["current_player"], -1);
+        let response = call(&mut session, request("ai_move", json!({ "difficulty": "easy", "time_ms": 50 })));
+        assert_eq!(response["result"]["state"]["current_player"], 1);
+        assert!(response["result"]["move"]["row"].is_u64());
+        let response = call(&mut session, request("candidates", json!({ "limit": 3 })));
+        assert_eq!(response["result"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn rejects_out_of_board_coordinates() {
+        let mut session = Session::new();
+        // 4294967303 截断成 i32 恰好是 7，必须按原值判断
+        for (row, col) in [(4_294_967_303u64, 7u64), (7, 4_294_967_303), (15, 0), (u64::MAX, u64::MAX)] {
+            let response = call(&mut session, request("play", json!({ "row": row, "col": col })));
+            assert_eq!(error_code(&response), Some(GAME_ERROR as i64));
+        }
+        assert_eq!(session.state().state.board.stone_count(), 0);
+        assert!(call(&mut session, request("play", json!({ "row": 14, "col": 14 })))["result"].is_object());
+    }
+
+    #[test]
+    fn rejects_invalid_players() {
+        let mut session = Session::new();
+        assert!(call(&mut session, request("evaluate", json!({ "player": 1 })))["result"]["score"].is_i64());
+        assert_eq!(error_code(&call(&mut session, request("evaluate", json!({ "player": 0 })))), Some(INVALID_PARAMS as i64));
+        assert_eq!(error_code(&call(&mut session, request("evaluate", json!({ "player": -3 })))), Some(INVALID_PARAMS as i64));
+
+        let mut state = serde_json::to_value(session.state().state).unwrap();
+        state["current_player"] = json!(0);
+        assert_eq!(error_code(&call(&mut session, request("load_state", json!({ "state": state.clone() })))), Some(INVALID_PARAMS as i64));
+        state["current_player"] = json!(-1);
+        let response = call(&mut session, request("load_state", json!({ "state": state })));
+        assert_eq!(response["result"]["state"]["current_player"], -1);
+    }
+
+    #[test]
+    fn ai_chooses_color_in_swap2() {
+        let mut session = Session::new();
+        call(&mut session, request("new_game", json!({ "opening": "swap2", "proposer": "human" })));
+        for (row, col) in [(7, 7), (7, 8), (9, 9)] {
+            call(&mut session, request("play", json!({ "row": row, "col": col })));
+        }
+        let response = call(&mut session, request("ai_choose_color", json!({ "difficulty": "easy" })));
+        let choice = response["result"]["choice"].as_str().unwrap();
+        assert!(["black", "white", "place_two"].contains(&choice));
+        assert_ne!(response["result"]["state"]["phase"]["phase"], "choose_color");
+        // 选色之后不再是选色阶段
+        assert_eq!(error_code(&call(&mut session, request("ai_choose_color", json!(null)))), Some(GAME_ERROR as i64));
+    }
+}
@@ -0,0 +1,393 @@
+// 服务端对局 - 保存当前 GameState 和悔棋用的历史，客户端只需发送坐标
+
+use serde::{Deserialize, Serialize};
+use shared_core::{
+    analyze_position_with_weights, choose_color, create_game_state_with_opening, evaluate_board_with_weights, get_ai_color_choice, get_ai_opening_move,
+    get_best_move_with_info, get_best_move_with_options, get_possible_moves_with_rule, make_move, AIDifficulty, ColorChoice, EngineKind, EvalWeights,
+    GamePhase, GameState, MoveOptions, MoveReport, MoveResult, OpeningRule, Participant, PvLine, RuleSet, SearchFeatures, TraceEvent, BLACK,
+    BOARD_SIZE, WHITE,
+};
+
+// 某一时刻的对局：状态与胜者（未分胜负时为 None）
+#[derive(Debug, Clone)]
+struct Snapshot {
+    state: GameState,
+    winner: Option<i32>,
+}
+
+// 落子后的应答
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayReply {
+    pub state: GameState,
+    // 1 黑胜，-1 白胜，null 未分胜负
+    pub winner: Option<i32>,
+}
+
+// AI 代走的应答：选出的着法和走完后的对局
+#[derive(Debug, Clone, Serialize)]
+pub struct AiMoveReply {
+    #[serde(rename = "move")]
+    pub mv: MoveResult,
+    pub state: GameState,
+    pub winner: Option<i32>,
+}
+
+// AI 选色的应答：AI 的决定和选色后的对局
+#[derive(Debug, Clone, Serialize)]
+pub struct AiColorReply {
+    pub choice: ColorChoice,
+    pub state: GameState,
+    pub winner: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewGameParams {
+    // AI 执子颜色，默认执白
+    #[serde(default = "default_ai_player")]
+    pub ai_player: i32,
+    #[serde(default)]
+    pub rule: RuleSet,
+    #[serde(default = "default_size")]
+    pub size: usize,
+    #[serde(default)]
+    pub opening: OpeningRule,
+    // 开局协议下摆开局棋子的一方，默认人类
+    pub proposer: Option<Participant>,
+}
+
+fn default_ai_player() -> i32 {
+    WHITE
+}
+
+fn default_size() -> usize {
+    BOARD_SIZE
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct SearchParams {
+    #[serde(default = "default_difficulty")]
+    pub difficulty: Difficulty,
+    // 覆盖难度自带的搜索时间（毫秒）
+    pub time_ms: Option<u64>,
+    // 覆盖难度自带的搜索线程数
+    pub threads: Option<usize>,
+    // 搜索增强开关，如 {"pvs":false,"lmr":false,"aspiration_window":0}；省略的项保持开启
+    pub features: Option<SearchFeatures>,
+    // 搜索后端，"alpha_beta"（默认）或 "mcts"
+    pub engine: Option<EngineKind>,
+}
+
+// 难度参数，JSON 中写作 "easy"/"medium"/"hard"
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl From<Difficulty> for AIDifficulty {
+    fn from(difficulty: Difficulty) -> Self {
+        match difficulty {
+            Difficulty::Easy => AIDifficulty::Easy,
+            Difficulty::Medium => AIDifficulty::Medium,
+            Difficulty::Hard => AIDifficulty::Hard,
+        }
+    }
+}
+
+pub fn default_difficulty() -> Difficulty {
+    Difficulty::Medium
+}
+
+// 执子颜色只能是黑（1）或白（-1）
+fn is_color(player: i32) -> bool {
+    player == BLACK || player == WHITE
+}
+
+pub struct Session {
+    current: Snapshot,
+    history: Vec<Snapshot>,
+    // 评估权重，换新对局时保留
+    weights: EvalWeights,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session::new()
+    }
+}
+
+impl Session {
+    // 默认是自由规则、15路棋盘、AI执白的新对局
+    pub fn new() -> Self {
+        let state = create_game_state_with_opening(RuleSet::Freestyle, OpeningRule::Free, Participant::Human, BOARD_SIZE)
+            .expect("默认棋盘尺寸必然合法");
+        Session {
+            current: Snapshot { state, winner: None },
+            history: Vec::new(),
+            weights: EvalWeights::default(),
+        }
+    }
+
+    pub fn weights(&self) -> EvalWeights {
+        self.weights
+    }
+
+    pub fn set_weights(&mut self, weights: EvalWeights) -> EvalWeights {
+        self.weights = weights;
+        self.weights
+    }
+
+    pub fn state(&self) -> PlayReply {
+        PlayReply {
+            state: self.current.state.clone(),
+            winner: self.current.winner,
+        }
+    }
+
+    pub fn new_game(&mut self, params: NewGameParams) -> Result<PlayReply, String> {
+        if !is_color(params.ai_player) {
+            return Err(format!("无效的执子颜色: {}", params.ai_player));
+        }
+        let proposer = params.proposer.unwrap_or(Participant::Human);
+        let mut state = create_game_state_with_opening(params.rule, params.opening, proposer, params.size)?;
+        // 开局协议下执子颜色由选色决定，这里只对无开局协议的对局生效
+        if params.opening == OpeningRule::Free {
+            state.ai_player = params.ai_player;
+            state.human_player = -params.ai_player;
+        }
+        self.current = Snapshot { state, winner: None };
+        self.history.clear();
+        Ok(self.state())
+    }
+
+    // 直接载入客户端给出的局面，清空悔棋历史；执子颜色和胜者必须是 1 或 -1
+    pub fn load(&mut self, state: GameState, winner: Option<i32>) -> Result<PlayReply, String> {
+        if !is_color(state.current_player) {
+            return Err(format!("无效的当前玩家: {}", state.current_player));
+        }
+        if !is_color(state.ai_player) || state.human_player != -state.ai_player {
+            return Err(format!("无效的执子颜色: ai_player {}，human_player {}", state.ai_player, state.human_player));
+        }
+        if let Some(winner) = winner.filter(|&w| !is_color(w)) {
+            return Err(format!("无效的胜者: {}", winner));
+        }
+        self.current = Snapshot { state, winner };
+        self.history.clear();
+        Ok(self.state())
+    }
+
+    // 轮到的一方在 (row, col) 落子
+    pub fn play(&mut self, row: usize, col: usize) -> Result<PlayReply, String> {
+        if self.current.winner.is_some() {
+            return Err("对局已结束".to_string());
+        }
+        let mut next = self.current.clone();
+        let player = next.state.current_player;
+        if make_move(&mut next.state, row, col)? {
+            next.winner = Some(player);
+        }
+        self.history.push(std::mem::replace(&mut self.current, next));
+        Ok(self.state())
+    }
+
+    pub fn undo(&mut self, count: usize) -> Result<PlayReply, String> {
+        if count == 0 || count > self.history.len() {
+            return Err(format!("无法悔棋 {} 步，历史中只有 {} 步", count, self.history.len()));
+        }
+        let keep = self.history.len() - count;
+        self.current = self.history.drain(keep..).next().expect("count 至少为1");
+        Ok(self.state())
+    }
+
+    // 开局协议的选色阶段
+    pub fn choose_color(&mut self, choice: ColorChoice) -> Result<PlayReply, String> {
+        let mut next = self.current.clone();
+        choose_color(&mut next.state, choice)?;
+        self.history.push(std::mem::replace(&mut self.current, next));
+        Ok(self.state())
+    }
+
+    // 选色阶段由 AI 决定执黑、执白或再摆两子，并按该决定推进
+    pub fn ai_choose_color(&mut self, difficulty: Difficulty) -> Result<AiColorReply, String> {
+        let choice = get_ai_color_choice(&self.current.state, difficulty.into()).ok_or("当前不是选色阶段")?;
+        let reply = self.choose_color(choice)?;
+        Ok(AiColorReply {
+            choice,
+            state: reply.state,
+            winner: reply.winner,
+        })
+    }
+
+    // 为轮到的一方选点，不落子
+    pub fn best_move(&self, params: SearchParams) -> Result<Option<MoveResult>, String> {
+        if self.current.winner.is_some() {
+            return Err("对局已结束".to_string());
+        }
+        let state = &self.current.state;
+        let difficulty = AIDifficulty::from(params.difficulty);
+        if state.phase != GamePhase::Playing {
+            return Ok(get_ai_opening_move(state, difficulty));
+        }
+        let side = state.current_player;
+        let options = MoveOptions {
+            time_limit_ms: params.time_ms,
+            threads: params.threads,
+            weights: Some(&self.weights),
+            features: params.features,
+            engine: params.engine,
+            ..Default::default()
+        };
+        Ok(get_best_move_with_options(&state.board, side, -side, difficulty, state.rule, options))
+    }
+
+    // 为轮到的一方选点，附带搜索统计和追踪事件，不落子
+    pub fn report(&self, params: SearchParams) -> Result<MoveReport, String> {
+        if self.current.winner.is_some() {
+            return Err("对局已结束".to_string());
+        }
+        let state = &self.current.state;
+        if state.phase != GamePhase::Playing {
+            return Err("开局阶段没有搜索统计".to_string());
+        }
+        let side = state.current_player;
+        let mut trace = Vec::new();
+        let mut record = |event: &TraceEvent| trace.push(event.clone());
+        let options = MoveOptions {
+            time_limit_ms: params.time_ms,
+            threads: params.threads,
+            weights: Some(&self.weights),
+            features: params.features,
+            engine: params.engine,
+            on_trace: Some(&mut record),
+            ..Default::default()
+        };
+        let (best, info) = get_best_move_with_info(&state.board, side, -side, params.difficulty.into(), state.rule, options);
+        Ok(MoveReport { best, info, trace })
+    }
+
+    // 为轮到的一方选点并落子
+    pub fn ai_move(&mut self, params: SearchParams) -> Result<AiMoveReply, String> {
+        let mv = self.best_move(params)?.ok_or("AI无法找到合适的移动")?;
+        let reply = self.play(mv.row, mv.col)?;
+        Ok(AiMoveReply {
+            mv,
+            state: reply.state,
+            winner: reply.winner,
+        })
+    }
+
+    // 轮到的一方的前 multi_pv 个候选着法，各带分数和主要变化
+    pub fn analyze(&self, difficulty: Difficulty, multi_pv: usize) -> Vec<PvLine> {
+        let state = &self.current.state;
+        analyze_position_with_weights(&state.board, state.current_player, difficulty.into(), state.rule, multi_pv, &self.weights)
+    }
+
+    // 以 player 视角的静态评估，默认取轮到的一方
+    pub fn evaluate(&self, player: Option<i32>) -> Result<i32, String> {
+        let state = &self.current.state;
+        let player = player.unwrap_or(state.current_player);
+        if !is_color(player) {
+            return Err(format!("无效的执子颜色: {}", player));
+        }
+        Ok(evaluate_board_with_weights(&state.board, player, state.rule, &self.weights))
+    }
+
+    // 轮到的一方的候选着法，按落子后的静态评估从高到低排序
+    pub fn candidates(&self, limit: Option<usize>) -> Vec<MoveResult> {
+        let state = &self.current.state;
+        let side = state.current_player;
+        let mut board = state.board;
+        let mut moves: Vec<MoveResult> = get_possible_moves_with_rule(&board, side, state.rule)
+            .into_iter()
+            .map(|(row, col)| {
+                board.make_move(row, col, side);
+                let score = evaluate_board_with_weights(&board, side, state.rule, &self.weights);
+                board.unmake_move(row, col);
+                MoveResult { row, col, score }
+            })
+            .collect();
+        moves.sort_by_key(|m| std::cmp::Reverse(m.score));
+        if let Some(limit) = limit {
+            moves.truncate(limit);
+        }
+        moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_game(session: &mut Session, opening: OpeningRule, proposer: Participant) {
+        let params = NewGameParams { ai_player: WHITE, rule: RuleSet::Freestyle, size: BOARD_SIZE, opening, proposer: Some(proposer) };
+        session.new_game(params).unwrap();
+    }
+
+    #[test]
+    fn play_undo_and_winner() {
+        let mut session = Session::new();
+        for col in 0..4 {
+            session.play(7, col).unwrap();
+            session.play(8, col).unwrap();
+        }
+        assert_eq!(session.undo(2).unwrap().state.board.stone_count(), 6);
+        assert!(session.undo(7).is_err());
+        session.play(7, 3).unwrap();
+        session.play(8, 3).unwrap();
+        assert!(session.play(7, 3).is_err());
+        let reply = session.play(7, 4).unwrap();
+        assert_eq!(reply.winner, Some(BLACK));
+        assert!(session.play(9, 9).is_err());
+        assert!(session.best_move(SearchParams { difficulty: Difficulty::Easy, time_ms: None, threads: None, features: None, engine: None }).is_err());
+        assert_eq!(session.undo(1).unwrap().winner, None);
+    }
+
+    #[test]
+    fn load_rejects_invalid_colors() {
+        let mut session = Session::new();
+        let valid = session.state().state;
+        let with = |edit: fn(&mut GameState)| {
+            let mut state = valid.clone();
+            edit(&mut state);
+            state
+        };
+        assert!(session.load(with(|s| s.current_player = 0), None).is_err());
+        assert!(session.load(with(|s| s.current_player = 2), None).is_err());
+        assert!(session.load(with(|s| s.ai_player = 3), None).is_err());
+        assert!(session.load(with(|s| s.human_player = s.ai_player), None).is_err());
+        assert!(session.load(valid.clone(), Some(0)).is_err());
+        assert_eq!(session.load(with(|s| s.current_player = WHITE), Some(BLACK)).unwrap().winner, Some(BLACK));
+    }
+
+    #[test]
+    fn evaluate_rejects_invalid_player() {
+        let mut session = Session::new();
+        session.play(7, 7).unwrap();
+        assert!(session.evaluate(Some(BLACK)).unwrap() > 0);
+        assert_eq!(session.evaluate(None), session.evaluate(Some(WHITE)));
+        assert!(session.evaluate(Some(0)).is_err());
+        assert!(session.evaluate(Some(5)).is_err());
+    }
+
+    #[test]
+    fn ai_color_choice_finishes_the_opening() {
+        let mut session = Session::new();
+        new_game(&mut session, OpeningRule::Swap, Participant::Human);
+        assert!(session.ai_choose_color(Difficulty::Easy).is_err());
+        for (row, col) in [(7, 7), (7, 8), (8, 8)] {
+            session.play(row, col).unwrap();
+        }
+        assert_eq!(session.state().state.phase, GamePhase::ChooseColor { by: Participant::Ai, can_place_two: false });
+
+        let reply = session.ai_choose_color(Difficulty::Easy).unwrap();
+        assert_ne!(reply.choice, ColorChoice::PlaceTwo);
+        let ai_color = if reply.choice == ColorChoice::Black { BLACK } else { WHITE };
+        assert_eq!(reply.state.phase, GamePhase::Playing);
+        assert_eq!(reply.state.ai_player, ai_color);
+        assert_eq!(reply.state.current_player, WHITE);
+        // 选色可以悔掉
+        assert!(matches!(session.undo(1).unwrap().state.phase, GamePhase::ChooseColor { .. }));
+    }
+}
@@ -0,0 +1,55 @@
+// Gomocup（Piskvork）协议引擎入口 - 从标准输入逐行读取命令，应答写到标准输出
+// 可直接放进 Piskvork 等对局管理器，与公开的 Gomocup 引擎对弈，也可以接入 Yixin-Board 做分析
+// 可选参数 --weights <文件>：从 JSON/TOML 文件载入评估权重
+
+mod protocol;
+
+use std::io::{self, BufRead};
+
+use protocol::Engine;
+use shared_core::EvalWeights;
+
+fn main() -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout().lock();
+    let mut engine = Engine::new();
+    if let Some(weights) = weights_from_args(std::env::args().skip(1)).map_err(io::Error::other)? {
+        engine.set_weights(weights);
+    }
+
+    for line in stdin.lock().lines() {
+        if !engine.handle_line(&line?, &mut stdout)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// 从命令行参数中找 --weights <文件> 并载入；没有该参数时返回 None
+fn weights_from_args(args: impl IntoIterator<Item = String>) -> Result<Option<EvalWeights>, String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--weights" {
+            let path = args.next().ok_or("--weights 需要文件路径")?;
+            return EvalWeights::load(path).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn weights_argument() {
+        assert_eq!(weights_from_args(args(&[])), Ok(None));
+        assert_eq!(weights_from_args(args(&["--other"])), Ok(None));
+        assert_eq!(weights_from_args(args(&["--weights"])), Err("--weights 需要文件路径".to_string()));
+        assert!(weights_from_args(args(&["--weights", "no-such-weights.toml"])).is_err());
+    }
+}
@@ -0,0 +1,737 @@
+// Gomocup（Piskvork）协议 - 解析管理器发来的命令，维护棋盘并调用共享核心选点
+// 协议坐标为 "x,y"，x 为列、y 为行；BOARD 命令中 1 表示己方棋子，2 表示对方棋子
+// 另外支持 Yixin-Board 等分析界面的扩展命令：INFO rule、INFO thread_num、YXNBEST、YXBLOCK、YXHASHCLEAR、YXSHOWINFO
+
+use std::io::{self, Write};
+
+use shared_core::tt::DEFAULT_TT_SIZE_MB;
+use shared_core::{
+    get_best_move_with_info, AIDifficulty, Board, EvalWeights, MoveOptions, MoveResult, RuleSet, SearchLimits, SearchResult, Searcher,
+    TranspositionTable, BLACK, MAX_BOARD_SIZE, MIN_BOARD_SIZE, WHITE,
+};
+
+// 管理器未给出 timeout_turn 时的默认每步时间（毫秒）
+const DEFAULT_TIMEOUT_TURN_MS: u64 = 5_000;
+
+// timeout_turn 为 0 表示要求尽快落子，此时每步只给这么多时间（毫秒）
+const MIN_TURN_BUDGET_MS: u64 = 100;
+
+// 协议对局始终按高级难度全力选点，时间紧时只缩短用时，不走低难度的故意失误
+const DIFFICULTY: AIDifficulty = AIDifficulty::Hard;
+
+// 对局时间有限时，每步最多使用剩余时间的比例（1/N）
+const MATCH_TIME_DIVISOR: u64 = 10;
+
+// 选点（VCF/VCT 求解 + 搜索）只用每步时间的 1/N，给超时和通信留出余量
+const SEARCH_TIME_DIVISOR: u64 = 3;
+
+// 置换表最多占用 max_memory 的 1/N
+const TT_MEMORY_DIVISOR: u64 = 2;
+
+// 管理器通过 INFO 下发的对局参数
+#[derive(Debug, Clone)]
+struct MatchInfo {
+    timeout_turn_ms: u64,
+    // 0 表示整局不限时
+    timeout_match_ms: u64,
+    time_left_ms: Option<u64>,
+    // 0 表示不限内存
+    max_memory: u64,
+    rule: RuleSet,
+    // INFO rule 的原始取值，切换到 Yixin-Board 解释时重新解码
+    rule_code: u64,
+    // 搜索线程数，None 时按难度决定
+    threads: Option<usize>,
+}
+
+impl Default for MatchInfo {
+    fn default() -> Self {
+        MatchInfo {
+            timeout_turn_ms: DEFAULT_TIMEOUT_TURN_MS,
+            timeout_match_ms: 0,
+            time_left_ms: None,
+            max_memory: 0,
+            rule: RuleSet::Freestyle,
+            rule_code: 0,
+            threads: None,
+        }
+    }
+}
+
+// BOARD / YXBLOCK 命令会连续读取多行直到 DONE
+enum Pending {
+    Board(Vec<(usize, usize, u8)>),
+    Block,
+}
+
+pub struct Engine {
+    board: Option<Board>,
+    info: MatchInfo,
+    pending: Option<Pending>,
+    // 分析界面禁止引擎走的点
+    blocked: Vec<(usize, usize)>,
+    // 跨步复用的置换表，YXHASHCLEAR 时清空
+    tt: TranspositionTable,
+    // YXSHOWINFO 之后每轮迭代输出 MESSAGE
+    show_info: bool,
+    // 收到过 YX 开头的扩展命令，说明对面是 Yixin-Board，INFO rule 按它的含义解释
+    yixin: bool,
+    weights: EvalWeights,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Engine {
+            board: None,
+            info: MatchInfo::default(),
+            pending: None,
+            blocked: Vec::new(),
+            tt: TranspositionTable::new(DEFAULT_TT_SIZE_MB),
+            show_info: false,
+            yixin: false,
+            weights: EvalWeights::default(),
+        }
+    }
+
+    pub fn set_weights(&mut self, weights: EvalWeights) {
+        self.weights = weights;
+    }
+
+    // 处理一行输入，应答直接写到 out；收到 END 时返回 false
+    pub fn handle_line(&mut self, line: &str, out: &mut dyn Write) -> io::Result<bool> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(true);
+        }
+
+        // BOARD / YXBLOCK 与 DONE 之间的行
+        if self.pending.is_some() {
+            self.handle_pending_line(line, out)?;
+            return Ok(true);
+        }
+
+        let (command, args) = match line.split_once(char::is_whitespace) {
+            Some((command, args)) => (command, args.trim()),
+            None => (line, ""),
+        };
+        let command = command.to_ascii_uppercase();
+        if command.starts_with("YX") && !self.yixin {
+            self.yixin = true;
+            self.update_rule();
+        }
+        match command.as_str() {
+            "START" => self.start(args, out)?,
+            "RESTART" => self.restart(out)?,
+            "BEGIN" => self.think(out)?,
+            "TURN" => self.turn(args, out)?,
+            "BOARD" => self.begin_pending(Pending::Board(Vec::new()), out)?,
+            "TAKEBACK" => self.takeback(args, out)?,
+            "INFO" => self.info(args),
+            "ABOUT" => respond(out, about())?,
+            "END" => return Ok(false),
+            "RECTSTART" => respond(out, "ERROR 不支持长方形棋盘")?,
+            "YXNBEST" => self.nbest(args, out)?,
+            "YXBLOCK" => self.begin_pending(Pending::Block, out)?,
+            "YXBLOCKRESET" => self.blocked.clear(),
+            "YXHASHCLEAR" => self.tt.clear(),
+            "YXSHOWINFO" => self.show_info = true,
+            _ => respond(out, format!("UNKNOWN {}", line))?,
+        }
+        Ok(true)
+    }
+
+    fn start(&mut self, args: &str, out: &mut dyn Write) -> io::Result<()> {
+        let Ok(size) = args.parse::<usize>() else {
+            return respond(out, format!("ERROR 无效的棋盘尺寸: {}", args));
+        };
+        match Board::with_size(size) {
+            Ok(board) => {
+                self.board = Some(board);
+                self.pending = None;
+                self.blocked.clear();
+                // 局面键不含棋盘尺寸和规则，换局时旧条目不能沿用
+                self.tt.clear();
+                respond(out, "OK")
+            }
+            Err(_) => respond(out, format!("ERROR 只支持 {} 到 {} 路棋盘", MIN_BOARD_SIZE, MAX_BOARD_SIZE)),
+        }
+    }
+
+    fn restart(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        match &mut self.board {
+            Some(board) => {
+                *board = Board::with_size(board.size()).expect("已有棋盘的尺寸必然合法");
+                self.blocked.clear();
+                self.tt.clear();
+                respond(out, "OK")
+            }
+            None => respond(out, "ERROR 尚未 START"),
+        }
+    }
+
+    fn turn(&mut self, args: &str, out: &mut dyn Write) -> io::Result<()> {
+        let Some(board) = &mut self.board else {
+            return respond(out, "ERROR 尚未 START");
+        };
+        let (row, col) = match parse_coords(args, board) {
+            Ok(pos) => pos,
+            Err(e) => return respond(out, format!("ERROR {}", e)),
+        };
+        if let Err(e) = board.try_make_move(row, col, color_to_move(board)) {
+            return respond(out, format!("ERROR {}", e));
+        }
+        self.think(out)
+    }
+
+    // BOARD 先只收集棋子，到 DONE 时才替换棋盘
+    fn begin_pending(&mut self, pending: Pending, out: &mut dyn Write) -> io::Result<()> {
+        if self.board.is_none() {
+            return respond(out, "ERROR 尚未 START");
+        }
+        if let Pending::Block = pending {
+            self.blocked.clear();
+        }
+        self.pending = Some(pending);
+        Ok(())
+    }
+
+    // BOARD 中每行为 "x,y,field"，YXBLOCK 中每行为 "x,y"
+    fn handle_pending_line(&mut self, line: &str, out: &mut dyn Write) -> io::Result<()> {
+        if line.eq_ignore_ascii_case("DONE") {
+            return match self.pending.take() {
+                Some(Pending::Board(stones)) => self.finish_board(&stones, out),
+                _ => Ok(()),
+            };
+        }
+
+        let board = self.board.as_ref().expect("BOARD/YXBLOCK 只在 START 之后开始");
+        let parts: Vec<&str> = line.split(',').map(str::trim).collect();
+        match (&mut self.pending, parts.as_slice()) {
+            (Some(Pending::Board(stones)), [x, y, field]) => match (parse_coords(&format!("{},{}", x, y), board), field.parse::<u8>()) {
+                (Ok((row, col)), Ok(field @ 1..=3)) => {
+                    stones.push((row, col, field));
+                    Ok(())
+                }
+                (Err(e), _) => respond(out, format!("ERROR {}", e)),
+                (_, _) => respond(out, format!("ERROR 无效的棋子类型: {}", field)),
+            },
+            (Some(Pending::Block), [_, _]) => match parse_coords(line, board) {
+                Ok(pos) => {
+                    self.blocked.push(pos);
+                    Ok(())
+                }
+                Err(e) => respond(out, format!("ERROR {}", e)),
+            },
+            _ => respond(out, format!("ERROR 无效的输入行: {}", line)),
+        }
+    }
+
+    // DONE：推断执子颜色后摆好棋盘，然后走棋；field 3 是连续对局的标记，不是棋子
+    // 先在新棋盘上摆完，整个列表合法后才替换当前棋盘，出错时保留原来的棋盘
+    fn finish_board(&mut self, stones: &[(usize, usize, u8)], out: &mut dyn Write) -> io::Result<()> {
+        let Some(board) = &mut self.board else {
+            return respond(out, "ERROR 尚未 START");
+        };
+        let stones: Vec<_> = stones.iter().filter(|s| s.2 != 3).collect();
+        // 轮到己方走，与 think 一样按总子数的奇偶决定己方颜色
+        let me = color_for_count(stones.len());
+        let mut next = Board::with_size(board.size()).expect("已有棋盘的尺寸必然合法");
+        for &&(row, col, field) in &stones {
+            if let Err(e) = next.try_make_move(row, col, if field == 1 { me } else { -me }) {
+                return respond(out, format!("ERROR {}", e));
+            }
+        }
+        *board = next;
+        self.think(out)
+    }
+
+    fn takeback(&mut self, args: &str, out: &mut dyn Write) -> io::Result<()> {
+        let Some(board) = &mut self.board else {
+            return respond(out, "ERROR 尚未 START");
+        };
+        match parse_coords(args, board) {
+            Ok((row, col)) if !board.is_empty_at(row, col) => {
+                board.unmake_move(row, col);
+                respond(out, "OK")
+            }
+            Ok(_) => respond(out, format!("ERROR 该位置没有棋子: {}", args)),
+            Err(e) => respond(out, format!("ERROR {}", e)),
+        }
+    }
+
+    // INFO 没有应答；不认识的键直接忽略
+    fn info(&mut self, args: &str) {
+        let (key, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        let value = value.trim().parse::<u64>().ok();
+        match (key.to_ascii_lowercase().as_str(), value) {
+            ("timeout_turn", Some(ms)) => self.info.timeout_turn_ms = ms,
+            ("timeout_match", Some(ms)) => self.info.timeout_match_ms = ms,
+            ("time_left", Some(ms)) => self.info.time_left_ms = Some(ms),
+            ("max_memory", Some(bytes)) => {
+                self.info.max_memory = bytes;
+                self.resize_tt();
+            }
+            ("rule", Some(code)) => {
+                self.info.rule_code = code;
+                self.update_rule();
+            }
+            ("thread_num", Some(threads)) => self.info.threads = Some(threads.max(1) as usize),
+            _ => {}
+        }
+    }
+
+    // 按 INFO rule 的取值和对面的界面确定规则；换规则后缓存的分数和着法（可能是新规则下的禁手）都不再可靠
+    fn update_rule(&mut self) {
+        let rule = rule_from_code(self.info.rule_code, self.yixin);
+        if rule != self.info.rule {
+            self.info.rule = rule;
+            self.tt.clear();
+        }
+    }
+
+    // 按内存限制重建置换表
+    fn resize_tt(&mut self) {
+        let mut size_mb = DEFAULT_TT_SIZE_MB;
+        if self.info.max_memory > 0 {
+            let budget_mb = self.info.max_memory / TT_MEMORY_DIVISOR / (1024 * 1024);
+            size_mb = size_mb.min(budget_mb as usize).max(1);
+        }
+        if size_mb != self.tt.size_mb() {
+            self.tt = TranspositionTable::new(size_mb);
+        }
+    }
+
+    // 本步可用的时间：每步限时与对局剩余时间的较小者
+    fn turn_budget_ms(&self) -> u64 {
+        let turn = if self.info.timeout_turn_ms == 0 { MIN_TURN_BUDGET_MS } else { self.info.timeout_turn_ms };
+        match (self.info.timeout_match_ms, self.info.time_left_ms) {
+            (0, _) | (_, None) => turn,
+            (_, Some(left)) => turn.min(left / MATCH_TIME_DIVISOR),
+        }
+    }
+
+    // 选点的总时间：每步时间的 1/N，至少1毫秒
+    fn time_limit_ms(&self) -> u64 {
+        (self.turn_budget_ms() / SEARCH_TIME_DIVISOR).max(1)
+    }
+
+    // 为轮到走棋的一方选点，不考虑分析界面禁止的点；show_info 时每轮迭代输出一行 MESSAGE，最后报告决定着法的阶段
+    fn search(&self, out: &mut dyn Write) -> Option<MoveResult> {
+        let board = self.board.as_ref()?;
+        let me = color_to_move(board);
+        let show_info = self.show_info;
+        let mut report = |result: &SearchResult| {
+            if show_info {
+                // 写失败时由最终应答报告，这里忽略
+                let _ = respond(out, search_message(result));
+            }
+        };
+        let options = MoveOptions {
+            excluded: &self.blocked,
+            tt: Some(&self.tt),
+            time_limit_ms: Some(self.time_limit_ms()),
+            threads: self.info.threads,
+            weights: Some(&self.weights),
+            on_iteration: Some(&mut report),
+            ..Default::default()
+        };
+        let (best, info) = get_best_move_with_info(board, me, -me, DIFFICULTY, self.info.rule, options);
+        if show_info {
+            if let Some(stage) = info.stage {
+                let _ = respond(out, format!("MESSAGE stage {:?} tm {}", stage, info.elapsed_ms));
+            }
+        }
+        best
+    }
+
+    // 选点、落子并回复坐标
+    fn think(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        let Some(board) = &self.board else {
+            return respond(out, "ERROR 尚未 START");
+        };
+        if board.is_full() {
+            return respond(out, "ERROR 棋盘已满");
+        }
+
+        let me = color_to_move(board);
+        let Some(best) = self.search(out) else {
+            return respond(out, "ERROR AI无法找到合适的移动");
+        };
+        if let Some(board) = &mut self.board {
+            board.make_move(best.row, best.col, me);
+        }
+        respond(out, format!("{},{}", best.col, best.row))
+    }
+
+    // YXNBEST n：一次多主变搜索给出前 n 个候选，共用一步的时间预算；只分析不落子，最后回复最佳点
+    fn nbest(&mut self, args: &str, out: &mut dyn Write) -> io::Result<()> {
+        let Ok(count) = args.parse::<usize>() else {
+            return respond(out, format!("ERROR 无效的候选数: {}", args));
+        };
+        let Some(board) = &self.board else {
+            return respond(out, "ERROR 尚未 START");
+        };
+
+        let limits = SearchLimits {
+            max_depth: DIFFICULTY.get_max_depth(),
+            time_limit_ms: self.time_limit_ms(),
+            max_nodes: 0,
+            width: DIFFICULTY.get_max_moves(),
+        };
+        let show_info = self.show_info;
+        let mut report = |result: &SearchResult| {
+            if show_info {
+                let _ = respond(out, search_message(result));
+            }
+        };
+        let result = Searcher::new(&self.tt, limits, self.info.rule)
+            .exclude_root_moves(&self.blocked)
+            .multi_pv(count.max(1))
+            .threads(self.info.threads.unwrap_or(DIFFICULTY.get_parallel_threads()))
+            .weights(self.weights)
+            .on_iteration(&mut report)
+            .iterative_deepening(board, color_to_move(board));
+
+        for (index, line) in result.lines.iter().enumerate() {
+            respond(out, format!("MESSAGE NBEST {} {},{} ev {}", index + 1, line.col, line.row, line.score))?;
+        }
+        match result.best_move {
+            Some((row, col)) => respond(out, format!("{},{}", col, row)),
+            None => respond(out, "ERROR AI无法找到合适的移动"),
+        }
+    }
+}
+
+// 写一行应答并立即刷新，管理器按行读取
+fn respond(out: &mut dyn Write, text: impl AsRef<str>) -> io::Result<()> {
+    writeln!(out, "{}", text.as_ref())?;
+    out.flush()
+}
+
+// 实时搜索信息：深度、分数、节点数、用时和主要变化
+fn search_message(result: &SearchResult) -> String {
+    let pv: Vec<String> = result.pv.iter().map(|&(row, col)| format!("{},{}", col, row)).collect();
+    format!(
+        "MESSAGE depth {} ev {} n {} tm {} pv {}",
+        result.depth,
+        result.score,
+        result.nodes,
+        result.elapsed_ms,
+        pv.join(" ")
+    )
+}
+
+// INFO rule 的取值：Gomocup 按位表示（1 恰好五连、2 连续对局、4 连珠、8 Caro），连续对局位不影响规则；
+// 只有确认对面是 Yixin-Board 时才按它的约定把 2 当作连珠
+fn rule_from_code(code: u64, yixin: bool) -> RuleSet {
+    if (yixin && code == 2) || code & 4 != 0 {
+        RuleSet::Renju
+    } else if code & 8 != 0 {
+        RuleSet::Caro
+    } else if code & 1 != 0 {
+        RuleSet::Standard
+    } else {
+        RuleSet::Freestyle
+    }
+}
+
+// 黑方先行，子数为偶数时轮到黑方
+fn color_to_move(board: &Board) -> i32 {
+    color_for_count(board.stone_count())
+}
+
+fn color_for_count(stone_count: usize) -> i32 {
+    if stone_count.is_multiple_of(2) {
+        BLACK
+    } else {
+        WHITE
+    }
+}
+
+// 解析 "x,y" 为 (row, col)
+fn parse_coords(text: &str, board: &Board) -> Result<(usize, usize), String> {
+    let (x, y) = text.split_once(',').ok_or_else(|| format!("无效的坐标: {}", text))?;
+    let (Ok(col), Ok(row)) = (x.trim().parse::<i32>(), y.trim().parse::<i32>()) else {
+        return Err(format!("无效的坐标: {}", text));
+    };
+    if !board.contains(row, col) {
+        return Err(format!("坐标超出棋盘: {}", text));
+    }
+    Ok((row as usize, col as usize))
+}
+
+fn about() -> String {
+    format!(
+        "name=\"gobang\", version=\"{}\", author=\"volunteercai-commits\", country=\"China\"",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_core::Bound;
+
+    // 依次送入命令，返回全部应答行
+    fn run(engine: &mut Engine, lines: &[&str]) -> Vec<String> {
+        let mut out = Vec::new();
+        for line in lines {
+            engine.handle_line(line, &mut out).unwrap();
+        }
+        String::from_utf8(out).unwrap().lines().map(str::to_string).collect()
+    }
+
+    fn parse_reply(reply: &str) -> (usize, usize) {
+        let (x, y) = reply.split_once(',').expect("应答应为坐标");
+        (y.parse().unwrap(), x.parse().unwrap())
+    }
+
+    #[test]
+    fn start_and_turn() {
+        let mut engine = Engine::new();
+        assert_eq!(run(&mut engine, &["TURN 7,7"]), ["ERROR 尚未 START"]);
+        assert!(run(&mut engine, &["START 3"])[0].starts_with("ERROR"));
+        assert_eq!(run(&mut engine, &["INFO timeout_turn 0", "START 15"]), ["OK"]);
+
+        let replies = run(&mut engine, &["TURN 7,7"]);
+        let (row, col) = parse_reply(&replies[0]);
+        let board = engine.board.as_ref().unwrap();
+        assert_eq!(board.get(7, 7), BLACK);
+        assert_eq!(board.get(row, col), WHITE);
+
+        assert!(run(&mut engine, &["TURN 7,7"])[0].starts_with("ERROR"));
+        assert!(run(&mut engine, &["TURN 15,0"])[0].starts_with("ERROR"));
+        assert!(run(&mut engine, &["TURN a,b"])[0].starts_with("ERROR"));
+        let key = 0x5eed;
+        engine.tt.store(key, 3, 100, Bound::Exact, None);
+        assert_eq!(run(&mut engine, &["RESTART"]), ["OK"]);
+        assert_eq!(engine.board.as_ref().unwrap().stone_count(), 0);
+        assert!(engine.tt.probe(key).is_none());
+
+        // 换尺寸重开时也不沿用旧的置换表
+        engine.tt.store(key, 3, 100, Bound::Exact, None);
+        assert_eq!(run(&mut engine, &["START 20"]), ["OK"]);
+        assert!(engine.tt.probe(key).is_none());
+    }
+
+    #[test]
+    fn board_infers_colour_and_completes_five() {
+        let mut engine = Engine::new();
+        let replies = run(
+            &mut engine,
+            &["INFO timeout_turn 0", "START 15", "BOARD", "3,7,1", "4,7,1", "5,7,1", "6,7,1", "0,0,2", "0,1,2", "0,2,2", "14,14,2", "DONE"],
+        );
+        assert_eq!(replies.len(), 2);
+        let (row, col) = parse_reply(&replies[1]);
+        assert!([(7, 2), (7, 7)].contains(&(row, col)));
+        // 己方与对方子数相同，己方执黑
+        assert_eq!(engine.board.as_ref().unwrap().get(row, col), BLACK);
+
+        let before = *engine.board.as_ref().unwrap();
+        assert!(run(&mut engine, &["BOARD", "3,7,4", "3,7"])
+            .iter()
+            .all(|reply| reply.starts_with("ERROR")));
+        // 列表里有重复位置时整盘都不摆，原来的棋盘保持不变
+        assert!(run(&mut engine, &["3,7,1", "4,7,2", "3,7,2", "DONE"])[0].starts_with("ERROR 位置已被占用"));
+        assert_eq!(*engine.board.as_ref().unwrap(), before);
+        assert_eq!(before.stone_count(), 9);
+    }
+
+    #[test]
+    fn board_and_think_agree_on_the_side_to_move() {
+        let mut engine = Engine::new();
+        // 己方比对方多一子：总子数为奇数，轮到白方，己方的子按白子摆
+        let replies = run(&mut engine, &["INFO timeout_turn 0", "START 15", "BOARD", "7,7,1", "8,8,1", "0,0,2", "DONE"]);
+        let (row, col) = parse_reply(&replies[1]);
+        let board = engine.board.as_ref().unwrap();
+        assert_eq!(board.get(7, 7), WHITE);
+        assert_eq!(board.get(0, 0), BLACK);
+        assert_eq!(board.get(row, col), WHITE);
+    }
+
+    #[test]
+    fn board_skips_continuous_game_markers() {
+        let mut engine = Engine::new();
+        // 双方各两子，标记为 3 的格子不算对方棋子，己方仍执黑
+        let replies = run(&mut engine, &["INFO timeout_turn 0", "START 15", "BOARD", "7,7,1", "8,8,2", "7,8,1", "9,9,2", "0,14,3", "14,0,3", "DONE"]);
+        let (row, col) = parse_reply(&replies[1]);
+        let board = engine.board.as_ref().unwrap();
+        assert_eq!(board.stone_count(), 5);
+        assert_eq!(board.get(row, col), BLACK);
+        assert_eq!(board.get(7, 7), BLACK);
+        assert_eq!(board.get(8, 8), WHITE);
+        assert!(board.is_empty_at(14, 0) || (row, col) == (14, 0));
+    }
+
+    #[test]
+    fn takeback_and_misc_commands() {
+        let mut engine = Engine::new();
+        run(&mut engine, &["INFO timeout_turn 0", "START 15", "BEGIN"]);
+        assert_eq!(engine.board.as_ref().unwrap().stone_count(), 1);
+        assert_eq!(run(&mut engine, &["TAKEBACK 7,7"]), ["OK"]);
+        assert_eq!(engine.board.as_ref().unwrap().stone_count(), 0);
+        assert!(run(&mut engine, &["TAKEBACK 7,7"])[0].starts_with("ERROR"));
+
+        assert!(run(&mut engine, &["ABOUT"])[0].starts_with("name=\"gobang\""));
+        assert_eq!(run(&mut engine, &["FOO bar"]), ["UNKNOWN FOO bar"]);
+        assert!(run(&mut engine, &["", "INFO unknown_key 5"]).is_empty());
+        assert!(!engine.handle_line("END", &mut Vec::new()).unwrap());
+    }
+
+    #[test]
+    fn info_sets_time_budget_and_rule() {
+        let mut engine = Engine::new();
+        assert_eq!(engine.turn_budget_ms(), DEFAULT_TIMEOUT_TURN_MS);
+        assert_eq!(engine.time_limit_ms(), DEFAULT_TIMEOUT_TURN_MS / SEARCH_TIME_DIVISOR);
+
+        // 0 表示尽快落子，不能当作不限时
+        run(&mut engine, &["INFO timeout_turn 0"]);
+        assert_eq!(engine.turn_budget_ms(), MIN_TURN_BUDGET_MS);
+        assert_eq!(engine.time_limit_ms(), MIN_TURN_BUDGET_MS / SEARCH_TIME_DIVISOR);
+
+        // 对局剩余时间更紧时按剩余时间分配
+        run(&mut engine, &["INFO timeout_turn 30000", "INFO timeout_match 180000", "INFO time_left 15000"]);
+        assert_eq!(engine.turn_budget_ms(), 15000 / MATCH_TIME_DIVISOR);
+        assert_eq!(engine.time_limit_ms(), 15000 / MATCH_TIME_DIVISOR / SEARCH_TIME_DIVISOR);
+        run(&mut engine, &["INFO time_left 0"]);
+        assert_eq!(engine.time_limit_ms(), 1);
+
+        for (code, rule) in [(0, RuleSet::Freestyle), (1, RuleSet::Standard), (4, RuleSet::Renju), (8, RuleSet::Caro)] {
+            run(&mut engine, &[&format!("INFO rule {}", code)]);
+            assert_eq!(engine.info.rule, rule);
+        }
+        run(&mut engine, &["INFO max_memory 16777216", "INFO thread_num 0"]);
+        assert_eq!(engine.tt.size_mb(), 8);
+        assert_eq!(engine.info.threads, Some(1));
+    }
+
+    #[test]
+    fn short_budget_keeps_full_strength() {
+        let mut engine = Engine::new();
+        // 时间很紧时只缩短用时，不会走低难度的故意失误
+        let replies = run(&mut engine, &["INFO timeout_turn 0", "START 15", "YXSHOWINFO", "BEGIN", "RESTART", "TURN 7,7", "RESTART", "TURN 0,0"]);
+        let stages: Vec<&str> = replies.iter().filter_map(|reply| reply.strip_prefix("MESSAGE stage ")).collect();
+        assert_eq!(stages.len(), 3);
+        assert!(stages.iter().all(|stage| !stage.starts_with("Handicap")));
+    }
+
+    #[test]
+    fn yixin_block_keeps_engine_off_points() {
+        let mut engine = Engine::new();
+        let replies = run(&mut engine, &["INFO timeout_turn 0", "START 15", "BOARD", "3,7,1", "4,7,1", "5,7,1", "6,7,1", "0,0,2", "0,1,2", "0,2,2", "14,14,2", "DONE"]);
+        // 撤掉引擎刚走的成五
+        assert_eq!(run(&mut engine, &[&format!("TAKEBACK {}", replies[1])]), ["OK"]);
+
+        // 两个成五点都被禁止
+        assert!(run(&mut engine, &["YXBLOCK", "2,7", "7,7", "DONE"]).is_empty());
+        assert_eq!(engine.blocked, [(7, 2), (7, 7)]);
+        let (row, col) = parse_reply(&run(&mut engine, &["BEGIN"])[0]);
+        assert!(![(7, 2), (7, 7)].contains(&(row, col)));
+
+        assert_eq!(run(&mut engine, &[&format!("TAKEBACK {},{}", col, row)]), ["OK"]);
+        run(&mut engine, &["YXBLOCKRESET"]);
+        assert!(engine.blocked.is_empty());
+        let (row, col) = parse_reply(&run(&mut engine, &["BEGIN"])[0]);
+        assert!([(7, 2), (7, 7)].contains(&(row, col)));
+    }
+
+    #[test]
+    fn yixin_nbest_lists_distinct_moves_without_playing() {
+        let mut engine = Engine::new();
+        run(&mut engine, &["INFO timeout_turn 0", "START 15", "BOARD", "7,7,2", "DONE"]);
+        let stones = engine.board.as_ref().unwrap().stone_count();
+
+        let replies = run(&mut engine, &["YXNBEST 3"]);
+        let moves: Vec<&str> = replies.iter().filter_map(|reply| reply.strip_prefix("MESSAGE NBEST ")).collect();
+        assert_eq!(moves.len(), 3);
+        let points: Vec<&str> = moves.iter().map(|m| m.split_whitespace().nth(1).unwrap()).collect();
+        assert!(points.iter().enumerate().all(|(i, p)| !points[..i].contains(p)));
+        assert_eq!(replies.last().unwrap(), points[0]);
+        assert_eq!(engine.board.as_ref().unwrap().stone_count(), stones);
+        assert!(run(&mut engine, &["YXNBEST x"])[0].starts_with("ERROR"));
+    }
+
+    #[test]
+    fn yixin_nbest_is_one_search_within_the_turn_budget() {
+        let mut engine = Engine::new();
+        run(&mut engine, &["INFO timeout_turn 1200", "START 15", "BOARD", "7,7,2", "8,8,1", "DONE", "YXSHOWINFO"]);
+        assert!(engine.time_limit_ms() < 1200);
+        let replies = run(&mut engine, &["YXNBEST 4"]);
+
+        // 迭代信息只来自一次迭代加深：深度单调上升，节点数一直累加
+        let iterations: Vec<(i32, u64)> = replies
+            .iter()
+            .filter_map(|reply| reply.strip_prefix("MESSAGE depth "))
+            .map(|rest| {
+                let fields: Vec<&str> = rest.split_whitespace().collect();
+                (fields[0].parse().unwrap(), fields[4].parse().unwrap())
+            })
+            .collect();
+        assert!(!iterations.is_empty());
+        assert!(iterations.windows(2).all(|pair| pair[0].0 < pair[1].0 && pair[0].1 <= pair[1].1));
+
+        let scores: Vec<i32> = replies
+            .iter()
+            .filter_map(|reply| reply.strip_prefix("MESSAGE NBEST "))
+            .map(|rest| rest.rsplit(' ').next().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(scores.len(), 4);
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    #[ignore = "按墙钟计时，机器繁忙时可能超出"]
+    fn yixin_nbest_returns_within_the_turn_budget() {
+        let mut engine = Engine::new();
+        run(&mut engine, &["INFO timeout_turn 1200", "START 15", "BOARD", "7,7,2", "8,8,1", "DONE"]);
+        let started = std::time::Instant::now();
+        run(&mut engine, &["YXNBEST 4"]);
+        assert!(started.elapsed().as_millis() < 1200);
+    }
+
+    #[test]
+    fn rule_change_clears_the_tt() {
+        let mut engine = Engine::new();
+        let key = 0x5eed;
+        engine.tt.store(key, 3, 100, Bound::Exact, None);
+        run(&mut engine, &["INFO rule 0"]);
+        assert!(engine.tt.probe(key).is_some());
+        run(&mut engine, &["INFO rule 4"]);
+        assert!(engine.tt.probe(key).is_none());
+        engine.tt.store(key, 3, 100, Bound::Exact, None);
+        run(&mut engine, &["INFO rule 6"]);
+        assert!(engine.tt.probe(key).is_some());
+    }
+
+    #[test]
+    fn continuous_game_bit_keeps_the_rule() {
+        let mut engine = Engine::new();
+        // Piskvork 的自由规则连续对局发送 rule 2，不能当作连珠
+        run(&mut engine, &["INFO rule 2"]);
+        assert_eq!(engine.info.rule, RuleSet::Freestyle);
+        run(&mut engine, &["INFO rule 3"]);
+        assert_eq!(engine.info.rule, RuleSet::Standard);
+        run(&mut engine, &["INFO rule 6"]);
+        assert_eq!(engine.info.rule, RuleSet::Renju);
+    }
+
+    #[test]
+    fn yixin_show_info_and_rule_codes() {
+        let mut engine = Engine::new();
+        // Yixin-Board 用 rule 2 表示连珠，收到 YX 扩展命令后按它的含义重新解释
+        run(&mut engine, &["INFO rule 2", "INFO timeout_turn 0", "START 15"]);
+        assert_eq!(engine.info.rule, RuleSet::Freestyle);
+        run(&mut engine, &["YXSHOWINFO", "YXHASHCLEAR"]);
+        assert_eq!(engine.info.rule, RuleSet::Renju);
+        let replies = run(&mut engine, &["TURN 7,7"]);
+        assert!(replies.iter().any(|reply| reply.starts_with("MESSAGE depth ")));
+        assert!(replies.iter().any(|reply| reply.starts_with("MESSAGE stage ")));
+        parse_reply(replies.last().unwrap());
+    }
+}
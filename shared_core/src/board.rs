@@ -0,0 +1,618 @@
+// 位棋盘 - 每方一组位集覆盖全部格子，可按值复制，替代 Vec<Vec<i32>>
+// 棋盘尺寸在运行时决定（15、19、20 等），格子编号固定为 row * MAX_BOARD_SIZE + col，
+// 与尺寸无关；棋子取值沿用原约定：1 / -1，0 表示空位
+
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::zobrist::piece_key;
+use crate::{BOARD_SIZE, DIRECTIONS, WIN_LENGTH};
+
+// 支持的最大棋盘尺寸（Gomocup 标准 20x20）
+pub const MAX_BOARD_SIZE: usize = 20;
+
+// 支持的最小棋盘尺寸
+pub const MIN_BOARD_SIZE: usize = WIN_LENGTH;
+
+// 位集能容纳的格子编号上限
+pub const MAX_CELL_COUNT: usize = MAX_BOARD_SIZE * MAX_BOARD_SIZE;
+
+// 位集需要的 u64 数量
+const WORDS: usize = MAX_CELL_COUNT.div_ceil(64);
+
+// 定长位集，每一位对应一个格子
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BitSet {
+    words: [u64; WORDS],
+}
+
+impl BitSet {
+    pub const fn new() -> Self {
+        BitSet { words: [0; WORDS] }
+    }
+
+    #[inline]
+    pub fn get(&self, idx: usize) -> bool {
+        self.words[idx / 64] >> (idx % 64) & 1 == 1
+    }
+
+    #[inline]
+    pub fn set(&mut self, idx: usize) {
+        self.words[idx / 64] |= 1 << (idx % 64);
+    }
+
+    #[inline]
+    pub fn clear(&mut self, idx: usize) {
+        self.words[idx / 64] &= !(1 << (idx % 64));
+    }
+
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    pub fn and(&self, other: &BitSet) -> BitSet {
+        let mut out = *self;
+        for (w, o) in out.words.iter_mut().zip(other.words.iter()) {
+            *w &= *o;
+        }
+        out
+    }
+
+    pub fn or(&self, other: &BitSet) -> BitSet {
+        let mut out = *self;
+        for (w, o) in out.words.iter_mut().zip(other.words.iter()) {
+            *w |= *o;
+        }
+        out
+    }
+
+    // self 中去掉 other 的部分
+    pub fn and_not(&self, other: &BitSet) -> BitSet {
+        let mut out = *self;
+        for (w, o) in out.words.iter_mut().zip(other.words.iter()) {
+            *w &= !*o;
+        }
+        out
+    }
+
+    // 整体左移 n 位（格子编号增大方向），n < 64
+    fn shl(&self, n: usize) -> BitSet {
+        let mut out = BitSet::new();
+        for i in (0..WORDS).rev() {
+            let mut w = self.words[i] << n;
+            if i > 0 && n > 0 {
+                w |= self.words[i - 1] >> (64 - n);
+            }
+            out.words[i] = w;
+        }
+        out.and(&board_tables().all)
+    }
+
+    // 整体右移 n 位（格子编号减小方向），n < 64
+    fn shr(&self, n: usize) -> BitSet {
+        let mut out = BitSet::new();
+        for i in 0..WORDS {
+            let mut w = self.words[i] >> n;
+            if i + 1 < WORDS && n > 0 {
+                w |= self.words[i + 1] << (64 - n);
+            }
+            out.words[i] = w;
+        }
+        out
+    }
+
+    // 在最大棋盘上向八个方向各扩张一格；结果可能超出实际棋盘，需要与 Board::area() 相交
+    pub fn dilate(&self) -> BitSet {
+        let tables = board_tables();
+        let horizontal = self
+            .or(&self.and_not(&tables.cols[MAX_BOARD_SIZE - 1]).shl(1))
+            .or(&self.and_not(&tables.cols[0]).shr(1));
+        horizontal
+            .or(&horizontal.shl(MAX_BOARD_SIZE))
+            .or(&horizontal.shr(MAX_BOARD_SIZE))
+    }
+
+    pub fn iter(&self) -> BitIter {
+        BitIter { words: self.words, word: 0 }
+    }
+}
+
+// 按格子编号从小到大遍历置位的格子
+pub struct BitIter {
+    words: [u64; WORDS],
+    word: usize,
+}
+
+impl Iterator for BitIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.word < WORDS {
+            let w = self.words[self.word];
+            if w != 0 {
+                self.words[self.word] = w & (w - 1);
+                return Some(self.word * 64 + w.trailing_zeros() as usize);
+            }
+            self.word += 1;
+        }
+        None
+    }
+}
+
+// 预计算的掩码：最大棋盘上每一行、每一列、每条主/副对角线上的格子，以及各尺寸棋盘的全部格子
+pub struct BoardTables {
+    pub all: BitSet,
+    // areas[size] 为 size x size 棋盘的全部格子
+    pub areas: Vec<BitSet>,
+    pub rows: Vec<BitSet>,
+    pub cols: Vec<BitSet>,
+    // 主对角线按 col - row + MAX_BOARD_SIZE - 1 编号
+    pub diags: Vec<BitSet>,
+    // 副对角线按 row + col 编号
+    pub anti_diags: Vec<BitSet>,
+}
+
+impl BoardTables {
+    // 经过 (row, col) 且沿 DIRECTIONS[dir] 方向的整条线
+    pub fn line(&self, row: usize, col: usize, dir: usize) -> &BitSet {
+        match dir {
+            0 => &self.rows[row],
+            1 => &self.cols[col],
+            2 => &self.diags[col + MAX_BOARD_SIZE - 1 - row],
+            _ => &self.anti_diags[row + col],
+        }
+    }
+}
+
+pub fn board_tables() -> &'static BoardTables {
+    static TABLES: OnceLock<BoardTables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let line_count = 2 * MAX_BOARD_SIZE - 1;
+        let mut tables = BoardTables {
+            all: BitSet::new(),
+            areas: vec![BitSet::new(); MAX_BOARD_SIZE + 1],
+            rows: vec![BitSet::new(); MAX_BOARD_SIZE],
+            cols: vec![BitSet::new(); MAX_BOARD_SIZE],
+            diags: vec![BitSet::new(); line_count],
+            anti_diags: vec![BitSet::new(); line_count],
+        };
+        for row in 0..MAX_BOARD_SIZE {
+            for col in 0..MAX_BOARD_SIZE {
+                let idx = Board::index(row, col);
+                tables.all.set(idx);
+                tables.rows[row].set(idx);
+                tables.cols[col].set(idx);
+                tables.diags[col + MAX_BOARD_SIZE - 1 - row].set(idx);
+                tables.anti_diags[row + col].set(idx);
+                // 该格属于所有边长大于 max(row, col) 的棋盘
+                for area in &mut tables.areas[row.max(col) + 1..] {
+                    area.set(idx);
+                }
+            }
+        }
+        tables
+    })
+}
+
+// 位棋盘：stones[0] 为棋子 1，stones[1] 为棋子 -1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Vec<i32>>", into = "Vec<Vec<i32>>")]
+pub struct Board {
+    stones: [BitSet; 2],
+    count: usize,
+    // 增量维护的Zobrist哈希
+    hash: u64,
+    // 棋盘边长
+    size: usize,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board {
+            stones: [BitSet::new(); 2],
+            count: 0,
+            hash: 0,
+            size: BOARD_SIZE,
+        }
+    }
+}
+
+#[inline]
+fn slot(player: i32) -> usize {
+    debug_assert!(player == 1 || player == -1, "无效的棋子: {}", player);
+    if player == 1 { 0 } else { 1 }
+}
+
+impl Board {
+    // 默认尺寸（15x15）的空棋盘
+    pub fn new() -> Self {
+        Board::default()
+    }
+
+    // 指定边长的空棋盘
+    pub fn with_size(size: usize) -> Result<Self, String> {
+        if !(MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&size) {
+            return Err(format!("棋盘尺寸必须在{}到{}之间", MIN_BOARD_SIZE, MAX_BOARD_SIZE));
+        }
+        Ok(Board { size, ..Board::default() })
+    }
+
+    // 棋盘边长
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // 格子总数
+    pub fn cell_count(&self) -> usize {
+        self.size * self.size
+    }
+
+    // 中心点坐标
+    pub fn center(&self) -> usize {
+        self.size / 2
+    }
+
+    // (row, col) 是否在棋盘内
+    #[inline]
+    pub fn contains(&self, row: i32, col: i32) -> bool {
+        row >= 0 && row < self.size as i32 && col >= 0 && col < self.size as i32
+    }
+
+    // 外部传入的 (row, col) 是否在棋盘内；直接比较 usize，不经过可能截断的转换
+    #[inline]
+    pub fn contains_cell(&self, row: usize, col: usize) -> bool {
+        row < self.size && col < self.size
+    }
+
+    // 棋盘上全部格子的位集
+    pub fn area(&self) -> &'static BitSet {
+        &board_tables().areas[self.size]
+    }
+
+    #[inline]
+    pub fn index(row: usize, col: usize) -> usize {
+        row * MAX_BOARD_SIZE + col
+    }
+
+    #[inline]
+    pub fn coords(idx: usize) -> (usize, usize) {
+        (idx / MAX_BOARD_SIZE, idx % MAX_BOARD_SIZE)
+    }
+
+    // 读取格子：1 / -1 / 0
+    #[inline]
+    pub fn get(&self, row: usize, col: usize) -> i32 {
+        self.get_index(Board::index(row, col))
+    }
+
+    #[inline]
+    pub fn get_index(&self, idx: usize) -> i32 {
+        if self.stones[0].get(idx) {
+            1
+        } else if self.stones[1].get(idx) {
+            -1
+        } else {
+            0
+        }
+    }
+
+    #[inline]
+    pub fn is_empty_at(&self, row: usize, col: usize) -> bool {
+        !self.occupied().get(Board::index(row, col))
+    }
+
+    // 检查后落子：坐标在棋盘内、棋子取值为 1 / -1、该位置为空；协议和接口收到的外部着法都经过这里
+    pub fn try_make_move(&mut self, row: usize, col: usize, player: i32) -> Result<(), String> {
+        if !self.contains_cell(row, col) {
+            return Err(format!("位置超出棋盘: ({}, {})", row, col));
+        }
+        if player != 1 && player != -1 {
+            return Err(format!("无效的棋子值 {} ({}, {})", player, row, col));
+        }
+        if !self.is_empty_at(row, col) {
+            return Err(format!("位置已被占用: ({}, {})", row, col));
+        }
+        self.make_move(row, col, player);
+        Ok(())
+    }
+
+    // 落子，调用方保证坐标合法且该位置为空（只做调试断言）；外部输入请用 try_make_move
+    #[inline]
+    pub fn make_move(&mut self, row: usize, col: usize, player: i32) {
+        let idx = Board::index(row, col);
+        debug_assert!(!self.occupied().get(idx), "位置已被占用");
+        self.stones[slot(player)].set(idx);
+        self.count += 1;
+        self.hash ^= piece_key(player, idx);
+    }
+
+    // 撤销 (row, col) 上的棋子
+    #[inline]
+    pub fn unmake_move(&mut self, row: usize, col: usize) {
+        let idx = Board::index(row, col);
+        let player = self.get_index(idx);
+        debug_assert!(player != 0, "撤销的位置为空");
+        self.stones[slot(player)].clear(idx);
+        self.count -= 1;
+        self.hash ^= piece_key(player, idx);
+    }
+
+    // 局面的Zobrist哈希（只含棋子，不含行棋方）
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn stone_count(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.count == self.cell_count()
+    }
+
+    pub fn stones(&self, player: i32) -> BitSet {
+        self.stones[slot(player)]
+    }
+
+    pub fn occupied(&self) -> BitSet {
+        self.stones[0].or(&self.stones[1])
+    }
+
+    pub fn empty_cells(&self) -> BitSet {
+        self.area().and_not(&self.occupied())
+    }
+
+    // 距离已有棋子 radius 格以内（切比雪夫距离）的空位
+    pub fn neighborhood(&self, radius: usize) -> BitSet {
+        let occupied = self.occupied();
+        let mut area = occupied;
+        for _ in 0..radius {
+            area = area.dilate();
+        }
+        area.and(self.area()).and_not(&occupied)
+    }
+
+    // 某方在经过 (row, col) 的 DIRECTIONS[dir] 方向整条线上的棋子
+    pub fn line_stones(&self, row: usize, col: usize, dir: usize, player: i32) -> BitSet {
+        self.stones(player).and(board_tables().line(row, col, dir))
+    }
+
+    // 从 (row, col) 沿 DIRECTIONS[dir] 正反两个方向数连续的同色棋子（含自身）
+    pub fn run_length(&self, row: usize, col: usize, dir: usize, player: i32) -> usize {
+        let (dx, dy) = DIRECTIONS[dir];
+        let own = self.stones(player);
+        let mut count = 1;
+        for sign in [1, -1] {
+            let mut r = row as i32 + dx * sign;
+            let mut c = col as i32 + dy * sign;
+            while self.contains(r, c) && own.get(Board::index(r as usize, c as usize)) {
+                count += 1;
+                r += dx * sign;
+                c += dy * sign;
+            }
+        }
+        count
+    }
+
+    // 从二维数组转换（Tauri 命令和 GameState 序列化使用），尺寸取自数组
+    pub fn from_nested(board: &[Vec<i32>]) -> Result<Board, String> {
+        let size = board.len();
+        if board.iter().any(|row| row.len() != size) {
+            return Err("棋盘必须是正方形".to_string());
+        }
+        let mut result = Board::with_size(size)?;
+        for (row, cells) in board.iter().enumerate() {
+            for (col, &cell) in cells.iter().enumerate() {
+                result.place_checked(row, col, cell)?;
+            }
+        }
+        Ok(result)
+    }
+
+    // 从按行展开的一维数组转换（WASM 接口使用），边长为数组长度的平方根
+    pub fn from_flat(board: &[i32]) -> Result<Board, String> {
+        let size = (MIN_BOARD_SIZE..=MAX_BOARD_SIZE)
+            .find(|size| size * size == board.len())
+            .ok_or_else(|| format!("棋盘数组长度 {} 不对应支持的棋盘尺寸", board.len()))?;
+        let mut result = Board::with_size(size)?;
+        for (i, &cell) in board.iter().enumerate() {
+            result.place_checked(i / size, i % size, cell)?;
+        }
+        Ok(result)
+    }
+
+    fn place_checked(&mut self, row: usize, col: usize, cell: i32) -> Result<(), String> {
+        match cell {
+            0 => Ok(()),
+            _ => self.try_make_move(row, col, cell),
+        }
+    }
+
+    pub fn to_nested(&self) -> Vec<Vec<i32>> {
+        (0..self.size)
+            .map(|row| (0..self.size).map(|col| self.get(row, col)).collect())
+            .collect()
+    }
+
+    pub fn to_flat(&self) -> Vec<i32> {
+        (0..self.size)
+            .flat_map(|row| (0..self.size).map(move |col| self.get(row, col)))
+            .collect()
+    }
+}
+
+impl TryFrom<Vec<Vec<i32>>> for Board {
+    type Error = String;
+
+    fn try_from(board: Vec<Vec<i32>>) -> Result<Self, Self::Error> {
+        Board::from_nested(&board)
+    }
+}
+
+impl From<Board> for Vec<Vec<i32>> {
+    fn from(board: Board) -> Self {
+        board.to_nested()
+    }
+}
+
+// 测试共用的局面构造
+#[cfg(test)]
+pub(crate) mod test_util {
+    use super::Board;
+    use crate::{BLACK, WHITE};
+
+    // 默认尺寸的棋盘上依次摆好黑子和白子
+    pub(crate) fn board_with(black: &[(usize, usize)], white: &[(usize, usize)]) -> Board {
+        let mut board = Board::new();
+        for &(row, col) in black {
+            board.make_move(row, col, BLACK);
+        }
+        for &(row, col) in white {
+            board.make_move(row, col, WHITE);
+        }
+        board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BLACK, WHITE};
+
+    // 按棋子重新计算的哈希
+    fn fresh_hash(board: &Board) -> u64 {
+        [BLACK, WHITE]
+            .iter()
+            .flat_map(|&player| board.stones(player).iter().map(move |idx| piece_key(player, idx)))
+            .fold(0, |hash, key| hash ^ key)
+    }
+
+    // 按固定步长走遍棋盘的一串不重复落点
+    fn spread_moves(seed: usize, count: usize) -> Vec<(usize, usize)> {
+        let cells: Vec<usize> = Board::new().empty_cells().iter().collect();
+        (0..count).map(|i| Board::coords(cells[(seed + i * 97) % cells.len()])).collect()
+    }
+
+    #[test]
+    fn make_and_unmake_round_trip() {
+        let start = Board::new();
+        let moves = spread_moves(1, 60);
+        let mut board = start;
+        let mut player = BLACK;
+        for &(row, col) in &moves {
+            board.make_move(row, col, player);
+            assert_eq!(board.get(row, col), player);
+            assert_eq!(board.hash(), fresh_hash(&board));
+            player = -player;
+        }
+        assert_eq!(board.stone_count(), 60);
+        assert_eq!(board.stones(BLACK).count(), 30);
+        assert_eq!(board.empty_cells().count(), board.cell_count() - 60);
+
+        for &(row, col) in moves.iter().rev() {
+            board.unmake_move(row, col);
+            assert!(board.is_empty_at(row, col));
+            assert_eq!(board.hash(), fresh_hash(&board));
+        }
+        assert_eq!(board, start);
+        assert!(board.is_empty());
+        assert_eq!(board.hash(), 0);
+    }
+
+    #[test]
+    fn hash_does_not_depend_on_move_order() {
+        let moves = spread_moves(2, 20);
+        let place = |order: &mut dyn Iterator<Item = &(usize, usize)>| {
+            let mut board = Board::new();
+            for &(row, col) in order {
+                board.make_move(row, col, if (row + col) % 2 == 0 { BLACK } else { WHITE });
+            }
+            board
+        };
+        let forward = place(&mut moves.iter());
+        let backward = place(&mut moves.iter().rev());
+        assert_eq!(forward, backward);
+        assert_eq!(forward.hash(), backward.hash());
+        assert_ne!(forward.hash(), 0);
+    }
+
+    #[test]
+    fn converts_to_and_from_arrays() {
+        let mut board = Board::new();
+        board.make_move(0, 0, BLACK);
+        board.make_move(7, 7, WHITE);
+        board.make_move(14, 3, BLACK);
+
+        let nested = board.to_nested();
+        assert_eq!(nested.len(), 15);
+        assert_eq!((nested[0][0], nested[7][7], nested[14][3], nested[3][14]), (1, -1, 1, 0));
+        let flat = board.to_flat();
+        assert_eq!(flat.len(), 225);
+        assert_eq!((flat[0], flat[7 * 15 + 7], flat[14 * 15 + 3]), (1, -1, 1));
+
+        for rebuilt in [Board::from_nested(&nested).unwrap(), Board::from_flat(&flat).unwrap()] {
+            assert_eq!(rebuilt, board);
+            assert_eq!(rebuilt.hash(), board.hash());
+            assert_eq!(rebuilt.stone_count(), 3);
+        }
+
+        let mut bad = flat.clone();
+        bad[5] = 2;
+        assert!(Board::from_flat(&bad).is_err());
+        assert!(Board::from_flat(&flat[..224]).is_err());
+    }
+
+    #[test]
+    fn try_make_move_rejects_bad_input() {
+        let mut board = Board::new();
+        assert!(board.try_make_move(7, 7, BLACK).is_ok());
+        let before = board;
+        assert!(board.try_make_move(7, 7, WHITE).is_err());
+        assert!(board.try_make_move(15, 0, WHITE).is_err());
+        assert!(board.try_make_move(0, 15, WHITE).is_err());
+        assert!(board.try_make_move(0, 0, 2).is_err());
+        assert_eq!((board, board.hash()), (before, before.hash()));
+        assert!(board.try_make_move(14, 14, WHITE).is_ok());
+        assert_eq!(board.get(14, 14), WHITE);
+    }
+
+    #[test]
+    fn from_flat_infers_19_and_20_boards() {
+        for size in [19, 20] {
+            let mut flat = vec![0; size * size];
+            flat[size * size - 1] = BLACK;
+            flat[size - 1] = WHITE;
+            let board = Board::from_flat(&flat).unwrap();
+            assert_eq!(board.size(), size);
+            assert_eq!((board.get(size - 1, size - 1), board.get(0, size - 1)), (BLACK, WHITE));
+            assert_eq!(board.to_flat(), flat);
+        }
+        for len in [0, 16, 224, 226, 360, 362, 399, 401, 441] {
+            assert!(Board::from_flat(&vec![0; len]).is_err(), "长度 {}", len);
+        }
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let mut board = Board::new();
+        for (i, (row, col)) in spread_moves(3, 30).into_iter().enumerate() {
+            board.make_move(row, col, if i % 2 == 0 { BLACK } else { WHITE });
+        }
+        let json = serde_json::to_string(&board).unwrap();
+        assert!(json.starts_with("[["));
+        let parsed: Board = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, board);
+        assert_eq!(parsed.hash(), board.hash());
+
+        assert!(serde_json::from_str::<Board>("[[0, 3], [0, 0]]").is_err());
+    }
+}
@@ -0,0 +1,273 @@
+// 候选着法生成 - 各种搜索共用的着法来源，只做筛选和静态打分，不截断
+// 任一方有四或活三时只给出与之相关的着法：
+//   己方能成五 → 成五点；对方有四 → 对方的成五点；对方有活三（或双四点）→ 己方全部成四点加上能化解它的防守点
+// 否则给出已有棋子周围2格内的全部空位，按紧迫度（进攻和防守的棋型）从高到低排序
+
+use crate::eval::{Evaluator, LineShape};
+use crate::{threat, Board, RuleSet, DIRECTIONS};
+
+// 一个候选着法；tactical 表示它是必须考虑的着法，搜索不能因为宽度限制把它剪掉
+// attack 是己方在这里落子后各方向中最强的棋型，静态搜索据此挑出成四、成活三的着法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candidate {
+    pub pos: (usize, usize),
+    pub urgency: u32,
+    pub tactical: bool,
+    pub attack: LineShape,
+}
+
+// 候选列表是怎样得出的
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateKind {
+    Win,          // 己方成五点
+    BlockFour,    // 对方有四，只能挡
+    AnswerThree,  // 对方有活三，挡或者冲四
+    Neighborhood, // 局面平静，周围的空位
+}
+
+#[derive(Debug, Clone)]
+pub struct Candidates {
+    pub kind: CandidateKind,
+    pub moves: Vec<Candidate>,
+}
+
+impl Candidates {
+    // 是否为被迫应对的着法（不在列表里的着法都会输）
+    pub fn is_forced(&self) -> bool {
+        self.kind != CandidateKind::Neighborhood
+    }
+}
+
+// 各棋型在紧迫度中的分值，己方成型（进攻）和挡住对方成型（防守）共用
+fn shape_value(shape: LineShape) -> u32 {
+    match shape {
+        LineShape::Five => 1 << 20,
+        LineShape::LiveFour => 1 << 16,
+        LineShape::RushFour | LineShape::LiveThree => 1 << 12,
+        LineShape::SleepThree | LineShape::LiveTwo => 1 << 8,
+        LineShape::SleepTwo | LineShape::LiveOne => 1 << 4,
+        LineShape::None => 0,
+    }
+}
+
+// 空位上双方各方向的棋型汇总
+struct CellShapes {
+    urgency: u32,
+    tactical: bool,
+    attack: LineShape,
+    own_five: bool,
+    own_four: bool,
+    opp_five: bool,
+    // 对方在这里落子就有两个成五点（活四或双四）
+    opp_double_five: bool,
+}
+
+fn cell_shapes(pos: &Evaluator, row: usize, col: usize, side: i32) -> CellShapes {
+    let mut cell = CellShapes {
+        urgency: 0,
+        tactical: false,
+        attack: LineShape::None,
+        own_five: false,
+        own_four: false,
+        opp_five: false,
+        opp_double_five: false,
+    };
+    let mut opp_fours = 0;
+    for dir in 0..DIRECTIONS.len() {
+        let attack = pos.shape(row, col, dir, side);
+        let defense = pos.shape(row, col, dir, -side);
+        // 进攻略优先于同级的防守
+        cell.urgency += shape_value(attack) * 2 + shape_value(defense);
+        // 己方成五、成四、成活三，或者挡住对方的成五点、活三的成活四点
+        cell.tactical |= attack <= LineShape::LiveThree || defense <= LineShape::LiveFour;
+        cell.attack = cell.attack.min(attack);
+        cell.own_five |= attack == LineShape::Five;
+        cell.own_four |= attack <= LineShape::RushFour;
+        cell.opp_five |= defense == LineShape::Five;
+        cell.opp_double_five |= defense == LineShape::LiveFour;
+        opp_fours += usize::from(defense == LineShape::RushFour);
+    }
+    cell.opp_double_five |= opp_fours >= 2;
+    cell
+}
+
+// 已有棋子周围2格内的空位（附近没有空位时为全部空位），按紧迫度从高到低排序；空棋盘只有中心
+pub fn neighborhood(pos: &Evaluator, side: i32) -> Vec<Candidate> {
+    let board = pos.board();
+    if board.is_empty() {
+        return vec![Candidate { pos: (board.center(), board.center()), urgency: 0, tactical: true, attack: LineShape::None }];
+    }
+    let mut cells = board.neighborhood(2);
+    if cells.is_empty() {
+        cells = board.empty_cells();
+    }
+    let mut moves: Vec<Candidate> = cells
+        .iter()
+        .map(|idx| {
+            let (row, col) = Board::coords(idx);
+            let shapes = cell_shapes(pos, row, col, side);
+            Candidate { pos: (row, col), urgency: shapes.urgency, tactical: shapes.tactical, attack: shapes.attack }
+        })
+        .collect();
+    moves.sort_by_key(|m| std::cmp::Reverse(m.urgency));
+    moves
+}
+
+// side 的候选着法
+pub fn generate(pos: &Evaluator, side: i32) -> Candidates {
+    let board = pos.board();
+    let rule = pos.rule();
+    let cells: Vec<((usize, usize), CellShapes)> = board
+        .neighborhood(2)
+        .iter()
+        .map(Board::coords)
+        .map(|(row, col)| ((row, col), cell_shapes(pos, row, col, side)))
+        .collect();
+    // 空棋盘，或者附近已没有空位
+    if cells.is_empty() {
+        return Candidates { kind: CandidateKind::Neighborhood, moves: neighborhood(pos, side) };
+    }
+
+    // 挑出满足条件的格子；被迫应对的着法都算战术着法
+    let select = |kind: CandidateKind, keep: &dyn Fn(&CellShapes) -> bool| {
+        let mut moves: Vec<Candidate> = cells
+            .iter()
+            .filter(|(_, shapes)| keep(shapes))
+            .map(|&(mv, ref shapes)| Candidate {
+                pos: mv,
+                urgency: shapes.urgency,
+                tactical: shapes.tactical || kind != CandidateKind::Neighborhood,
+                attack: shapes.attack,
+            })
+            .collect();
+        moves.sort_by_key(|m| std::cmp::Reverse(m.urgency));
+        Candidates { kind, moves }
+    };
+
+    if cells.iter().any(|(_, shapes)| shapes.own_five) {
+        return select(CandidateKind::Win, &|shapes| shapes.own_five);
+    }
+    if cells.iter().any(|(_, shapes)| shapes.opp_five) {
+        return select(CandidateKind::BlockFour, &|shapes| shapes.opp_five);
+    }
+
+    // 对方的活三：对方下一手就能有两个成五点的位置，禁手点不算
+    let mut scratch = *board;
+    let threats: Vec<(usize, usize)> = cells
+        .iter()
+        .filter(|(_, shapes)| shapes.opp_double_five)
+        .map(|&(mv, _)| mv)
+        .filter(|&(row, col)| !rule.is_forbidden(&mut scratch, row, col, -side))
+        .collect();
+    if !threats.is_empty() {
+        let defenses = threat::defenses_against(&mut scratch, -side, &threats, rule);
+        let mut answers = select(CandidateKind::AnswerThree, &|shapes| shapes.own_four);
+        for (row, col) in defenses {
+            if !answers.moves.iter().any(|m| m.pos == (row, col)) {
+                let shapes = cell_shapes(pos, row, col, side);
+                answers.moves.push(Candidate { pos: (row, col), urgency: shapes.urgency, tactical: true, attack: shapes.attack });
+            }
+        }
+        // 挡不住也没有四可冲，已经输了，挡其中一个
+        if answers.moves.is_empty() {
+            answers.moves = threats
+                .iter()
+                .map(|&(row, col)| {
+                    let shapes = cell_shapes(pos, row, col, side);
+                    Candidate { pos: (row, col), urgency: shapes.urgency, tactical: true, attack: shapes.attack }
+                })
+                .collect();
+        }
+        answers.moves.sort_by_key(|m| std::cmp::Reverse(m.urgency));
+        return answers;
+    }
+
+    select(CandidateKind::Neighborhood, &|_| true)
+}
+
+// 一次性生成 side 在 board 上的候选位置，不含禁手点；搜索中请用 generate 配合增量评估器
+pub fn candidate_moves(board: &Board, side: i32, rule: RuleSet) -> Vec<(usize, usize)> {
+    let pos = Evaluator::new(board, rule);
+    let mut scratch = *board;
+    generate(&pos, side)
+        .moves
+        .into_iter()
+        .map(|m| m.pos)
+        .filter(|&(row, col)| !rule.is_forbidden(&mut scratch, row, col, side))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::test_util::board_with;
+    use crate::threat::four_points_of_move;
+    use crate::{BLACK, WHITE};
+
+    fn positions(candidates: &Candidates) -> Vec<(usize, usize)> {
+        candidates.moves.iter().map(|m| m.pos).collect()
+    }
+
+    #[test]
+    fn opponent_four_leaves_only_the_block() {
+        // 黑棋在中心有一片棋子，白四只有 (0,7) 一个挡点
+        let board = board_with(&[(6, 6), (6, 8), (7, 6), (8, 7), (0, 2)], &[(7, 7), (8, 8), (0, 3), (0, 4), (0, 5), (0, 6)]);
+        let candidates = generate(&Evaluator::new(&board, RuleSet::Freestyle), BLACK);
+        assert_eq!(candidates.kind, CandidateKind::BlockFour);
+        assert_eq!(positions(&candidates), [(0, 7)]);
+        assert!(candidates.is_forced() && candidates.moves[0].tactical);
+
+        // 己方能成五时只给成五点
+        let candidates = generate(&Evaluator::new(&board, RuleSet::Freestyle), WHITE);
+        assert_eq!(candidates.kind, CandidateKind::Win);
+        assert_eq!(positions(&candidates), [(0, 7)]);
+    }
+
+    #[test]
+    fn open_three_leaves_defences_and_own_fours() {
+        // 白棋第7行活三，黑棋第3行有能冲四的三
+        let board = board_with(&[(3, 3), (3, 4), (3, 5), (10, 10)], &[(7, 5), (7, 6), (7, 7), (2, 2), (4, 6)]);
+        let candidates = generate(&Evaluator::new(&board, RuleSet::Freestyle), BLACK);
+        assert_eq!(candidates.kind, CandidateKind::AnswerThree);
+        let moves = positions(&candidates);
+        for pos in [(7, 4), (7, 8), (3, 6)] {
+            assert!(moves.contains(&pos), "缺少 {:?}", pos);
+        }
+        let mut scratch = board;
+        for &(row, col) in &moves {
+            let own_four = !four_points_of_move(&mut scratch, row, col, BLACK, RuleSet::Freestyle).is_empty();
+            let defence = row == 7 && [3, 4, 8, 9].contains(&col);
+            assert!(own_four || defence, "{:?} 既不成四也不防守", (row, col));
+        }
+        assert!(candidates.moves.iter().all(|m| m.tactical));
+    }
+
+    #[test]
+    fn quiet_position_lists_the_scored_neighbourhood() {
+        let board = board_with(&[(7, 7), (7, 8)], &[(8, 7)]);
+        let pos = Evaluator::new(&board, RuleSet::Freestyle);
+        let candidates = generate(&pos, BLACK);
+        assert_eq!(candidates.kind, CandidateKind::Neighborhood);
+        assert!(!candidates.is_forced());
+
+        let mut moves = positions(&candidates);
+        assert!(candidates.moves.windows(2).all(|w| w[0].urgency >= w[1].urgency));
+        // 连成活三的点排在最前
+        assert!([(7, 6), (7, 9)].contains(&moves[0]));
+        moves.sort();
+        let mut expected: Vec<(usize, usize)> = board.neighborhood(2).iter().map(Board::coords).collect();
+        expected.sort();
+        assert_eq!(moves, expected);
+
+        assert_eq!(positions(&generate(&Evaluator::new(&Board::new(), RuleSet::Freestyle), BLACK)), [(7, 7)]);
+    }
+
+    #[test]
+    fn renju_forbidden_cells_are_not_offered_to_black() {
+        // (7,7) 是黑棋的三三禁手
+        let board = board_with(&[(7, 5), (7, 6), (5, 7), (6, 7)], &[(10, 10), (11, 11), (10, 11)]);
+        assert!(!candidate_moves(&board, BLACK, RuleSet::Renju).contains(&(7, 7)));
+        assert!(candidate_moves(&board, BLACK, RuleSet::Freestyle).contains(&(7, 7)));
+        assert!(candidate_moves(&board, WHITE, RuleSet::Renju).contains(&(7, 7)));
+    }
+}
@@ -0,0 +1,491 @@
+// 增量评估 - 为每个格子的四条线维护前后各4格的四进制编码，落子/提子时只更新这几条线上受影响的格子
+// 编码以一方的视角：0 空位，1 己方棋子，2 对方棋子，3 棋盘外；中心格本身不编码
+// 对方棋子和棋盘外都不能落子，但 Caro 规则只把对方棋子算作堵住
+// 经过某颗棋子的棋型由预计算的表查出，按 EvalWeights 计分并缓存，局面分 = 己方棋子分之和 - 对方棋子分之和
+
+use std::sync::OnceLock;
+
+use crate::weights::EvalWeights;
+use crate::{Board, RuleSet, BLACK, DIRECTIONS, MAX_CELL_COUNT, WIN_LENGTH};
+
+// 窗口半径：中心格前后各看几格
+const HALF: i32 = 4;
+
+// 编码的格子数（不含中心）
+const WINDOW: usize = 2 * HALF as usize;
+
+// 每格的取值个数，编码总数 4^8
+const BASE: u16 = 4;
+const CODE_COUNT: usize = (BASE as usize).pow(WINDOW as u32);
+
+const EMPTY: u16 = 0;
+const OWN: u16 = 1;
+const BLOCKED: u16 = 2;
+const EDGE: u16 = 3;
+
+// 偏移 k（-4..=4，不含0）在编码中的位权
+const fn weight(k: i32) -> u16 {
+    let slot = if k < 0 { k + HALF } else { k + HALF - 1 };
+    BASE.pow(slot as u32)
+}
+
+// 一条线上经过某颗棋子的棋型，按从强到弱排列；识别不要求连续，跳三、跳四与连续的同等对待
+//   X 为己方棋子，_ 为空位
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LineShape {
+    Five,       // 按规则成五
+    LiveFour,   // 有两个成五点：_XXXX_、X_XXX_X 等
+    RushFour,   // 只有一个成五点：XXXX_、X_XXX、XX_XX 等
+    LiveThree,  // 再下一子能成活四：_XXX_、_X_XX_ 等
+    SleepThree, // 再下一子只能成冲四：XXX__、X_XX_、X__XX 等
+    LiveTwo,    // 再下一子能成活三：__XX__、_X_X_、_X__X_ 等
+    SleepTwo,   // 再下一子只能成眠三
+    LiveOne,    // 再下一子能成活二
+    None,       // 无价值：按规则已无法成五
+}
+
+// 成五的判定方式，决定查哪一张棋型表
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FiveKind {
+    AtLeast, // 五连及以上
+    Exact,   // 恰好五连
+    Caro,    // 五连及以上，两端都被对方棋子堵住不算
+}
+
+impl FiveKind {
+    fn of(rule: RuleSet, player: i32) -> Self {
+        match rule {
+            RuleSet::Standard => FiveKind::Exact,
+            RuleSet::Renju if player == BLACK => FiveKind::Exact,
+            RuleSet::Caro => FiveKind::Caro,
+            _ => FiveKind::AtLeast,
+        }
+    }
+}
+
+// 查表结果：棋型，以及构成它的己方棋子数（用来平分棋型分）
+#[derive(Debug, Clone, Copy)]
+struct LinePattern {
+    shape: LineShape,
+    stones: u8,
+}
+
+// 三种成五判定各一张棋型表，按编码索引
+struct ShapeTables {
+    tables: [Vec<LinePattern>; 3],
+}
+
+fn shape_tables() -> &'static ShapeTables {
+    static TABLES: OnceLock<ShapeTables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let build = |kind: FiveKind| {
+            let mut memo = vec![None; CODE_COUNT];
+            (0..CODE_COUNT).map(|code| classify(code as u16, kind, &mut memo)).collect()
+        };
+        ShapeTables {
+            tables: [build(FiveKind::AtLeast), build(FiveKind::Exact), build(FiveKind::Caro)],
+        }
+    })
+}
+
+fn lookup(code: u16, kind: FiveKind) -> LinePattern {
+    shape_tables().tables[kind as usize][code as usize]
+}
+
+// 解码：偏移 k 处的格子，中心格视为己方棋子；窗口外返回 None，按空位处理
+fn cell_at(code: u16, k: i32) -> Option<u16> {
+    match k {
+        0 => Some(OWN),
+        _ if (-HALF..=HALF).contains(&k) => Some(code / weight(k) % BASE),
+        _ => None,
+    }
+}
+
+// 递归识别：成五看中心所在的连子；四看成五点个数；三、二、一看再下一子能成的最强棋型
+fn classify(code: u16, kind: FiveKind, memo: &mut [Option<LinePattern>]) -> LinePattern {
+    if let Some(pattern) = memo[code as usize] {
+        return pattern;
+    }
+    let stones = stones_in_five_window(code, kind);
+    let shape = match five_at_center(code, kind) {
+        Some(five) => if five { LineShape::Five } else { LineShape::None },
+        None if stones == 0 => LineShape::None,
+        None => {
+            let fills: Vec<LineShape> = (-HALF..=HALF)
+                .filter(|&k| k != 0 && cell_at(code, k) == Some(EMPTY))
+                .map(|k| classify(code + OWN * weight(k), kind, memo).shape)
+                .collect();
+            match fills.iter().filter(|&&shape| shape == LineShape::Five).count() {
+                0 => fills.into_iter().map(weaken).min().unwrap_or(LineShape::None),
+                1 => LineShape::RushFour,
+                _ => LineShape::LiveFour,
+            }
+        }
+    };
+    let pattern = LinePattern { shape, stones: stones as u8 };
+    memo[code as usize] = Some(pattern);
+    pattern
+}
+
+// 再下一子后的棋型对应的当前棋型
+fn weaken(shape: LineShape) -> LineShape {
+    match shape {
+        LineShape::LiveFour => LineShape::LiveThree,
+        LineShape::RushFour => LineShape::SleepThree,
+        LineShape::LiveThree => LineShape::LiveTwo,
+        LineShape::SleepThree => LineShape::SleepTwo,
+        LineShape::LiveTwo => LineShape::LiveOne,
+        _ => LineShape::None,
+    }
+}
+
+// 中心所在的连子达到五子时，按规则是否成五；不足五子返回 None
+fn five_at_center(code: u16, kind: FiveKind) -> Option<bool> {
+    let extend = |step: i32| {
+        let mut k = 0;
+        while cell_at(code, k + step) == Some(OWN) {
+            k += step;
+        }
+        k
+    };
+    let (before, after) = (extend(-1), extend(1));
+    if ((after - before + 1) as usize) < WIN_LENGTH {
+        return None;
+    }
+    Some(match kind {
+        FiveKind::AtLeast => true,
+        FiveKind::Exact => (after - before + 1) as usize == WIN_LENGTH,
+        FiveKind::Caro => !(cell_at(code, before - 1) == Some(BLOCKED) && cell_at(code, after + 1) == Some(BLOCKED)),
+    })
+}
+
+// 包含中心格、只有己方棋子和空位（不出棋盘）、且按规则能成五的五格窗口中，己方棋子最多的个数；没有这样的窗口时为0
+fn stones_in_five_window(code: u16, kind: FiveKind) -> usize {
+    let span = WIN_LENGTH as i32;
+    (-span + 1..=0)
+        .filter(|&start| {
+            let open = (start..start + span).all(|k| !matches!(cell_at(code, k), Some(BLOCKED | EDGE)));
+            let (outside_before, outside_after) = (cell_at(code, start - 1), cell_at(code, start + span));
+            open && match kind {
+                // 窗口外紧邻己方棋子会成长连
+                FiveKind::Exact => outside_before != Some(OWN) && outside_after != Some(OWN),
+                FiveKind::Caro => !(outside_before == Some(BLOCKED) && outside_after == Some(BLOCKED)),
+                FiveKind::AtLeast => true,
+            }
+        })
+        .map(|start| (start..start + span).filter(|&k| cell_at(code, k) == Some(OWN)).count())
+        .max()
+        .unwrap_or(0)
+}
+
+#[inline]
+fn slot(player: i32) -> usize {
+    if player == BLACK { 0 } else { 1 }
+}
+
+// 不维护增量状态，直接从棋盘算出 (row, col) 沿 DIRECTIONS[dir] 以 player 视角的编码
+fn window_code(board: &Board, row: usize, col: usize, dir: usize, player: i32) -> u16 {
+    let (dx, dy) = DIRECTIONS[dir];
+    (-HALF..=HALF).filter(|&k| k != 0).fold(0, |code, k| {
+        let (r, c) = (row as i32 + dx * k, col as i32 + dy * k);
+        let cell = if !board.contains(r, c) {
+            EDGE
+        } else {
+            match board.get(r as usize, c as usize) {
+                0 => EMPTY,
+                v if v == player => OWN,
+                _ => BLOCKED,
+            }
+        };
+        code + cell * weight(k)
+    })
+}
+
+// 一次性评估整个棋盘，与 Evaluator::score 的结果相同；适合只评估一次的场合
+pub fn evaluate(board: &Board, player: i32, rule: RuleSet, weights: &EvalWeights) -> i32 {
+    let side_total = |side: i32| -> i32 {
+        let kind = FiveKind::of(rule, side);
+        board
+            .stones(side)
+            .iter()
+            .map(|idx| {
+                let (row, col) = Board::coords(idx);
+                let patterns = std::array::from_fn(|dir| lookup(window_code(board, row, col, dir, side), kind));
+                stone_score(patterns, row, col, board.center(), weights)
+            })
+            .sum()
+    };
+    side_total(player) - side_total(-player)
+}
+
+// 增量评估器：持有一份棋盘，随落子/提子更新编码和棋子分数
+#[derive(Clone)]
+pub struct Evaluator {
+    board: Board,
+    rule: RuleSet,
+    weights: EvalWeights,
+    // codes[方][格子][方向]
+    codes: [[[u16; 4]; MAX_CELL_COUNT]; 2],
+    // 每颗棋子当前的分数
+    stone_scores: [i32; MAX_CELL_COUNT],
+    // 双方棋子分之和
+    totals: [i32; 2],
+}
+
+impl Evaluator {
+    pub fn new(board: &Board, rule: RuleSet) -> Self {
+        Evaluator::with_weights(board, rule, EvalWeights::default())
+    }
+
+    pub fn with_weights(board: &Board, rule: RuleSet, weights: EvalWeights) -> Self {
+        let mut eval = Evaluator {
+            board: *board,
+            rule,
+            weights,
+            codes: [[[EMPTY; 4]; MAX_CELL_COUNT]; 2],
+            stone_scores: [0; MAX_CELL_COUNT],
+            totals: [0; 2],
+        };
+        for idx in board.area().iter() {
+            let (row, col) = Board::coords(idx);
+            for player in [BLACK, -BLACK] {
+                for dir in 0..DIRECTIONS.len() {
+                    eval.codes[slot(player)][idx][dir] = window_code(board, row, col, dir, player);
+                }
+            }
+        }
+        for player in [BLACK, -BLACK] {
+            for idx in board.stones(player).iter() {
+                let (row, col) = Board::coords(idx);
+                eval.stone_scores[idx] = eval.stone_score(row, col, player);
+                eval.totals[slot(player)] += eval.stone_scores[idx];
+            }
+        }
+        eval
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn rule(&self) -> RuleSet {
+        self.rule
+    }
+
+    pub fn weights(&self) -> &EvalWeights {
+        &self.weights
+    }
+
+    // 以 player 视角的局面分
+    pub fn score(&self, player: i32) -> i32 {
+        self.totals[slot(player)] - self.totals[slot(-player)]
+    }
+
+    // 空位或棋子 (row, col) 沿 DIRECTIONS[dir] 的棋型，假设该格是 player 的棋子
+    pub fn shape(&self, row: usize, col: usize, dir: usize, player: i32) -> LineShape {
+        self.pattern(row, col, dir, player).shape
+    }
+
+    fn pattern(&self, row: usize, col: usize, dir: usize, player: i32) -> LinePattern {
+        let code = self.codes[slot(player)][Board::index(row, col)][dir];
+        lookup(code, FiveKind::of(self.rule, player))
+    }
+
+    pub fn make_move(&mut self, row: usize, col: usize, player: i32) {
+        self.board.make_move(row, col, player);
+        self.update_lines(row, col, player, true);
+        let idx = Board::index(row, col);
+        self.stone_scores[idx] = self.stone_score(row, col, player);
+        self.totals[slot(player)] += self.stone_scores[idx];
+    }
+
+    pub fn unmake_move(&mut self, row: usize, col: usize) {
+        let idx = Board::index(row, col);
+        let player = self.board.get_index(idx);
+        self.totals[slot(player)] -= self.stone_scores[idx];
+        self.stone_scores[idx] = 0;
+        self.board.unmake_move(row, col);
+        self.update_lines(row, col, player, false);
+    }
+
+    // (row, col) 上的 player 棋子出现或消失：改写四条线上前后4格的编码，并重算其中棋子的分数
+    fn update_lines(&mut self, row: usize, col: usize, player: i32, placed: bool) {
+        for (dir, &(dx, dy)) in DIRECTIONS.iter().enumerate() {
+            for k in (-HALF..=HALF).filter(|&k| k != 0) {
+                let (r, c) = (row as i32 + dx * k, col as i32 + dy * k);
+                if !self.board.contains(r, c) {
+                    continue;
+                }
+                let (r, c) = (r as usize, c as usize);
+                let idx = Board::index(r, c);
+                // 从 (r, c) 看，(row, col) 在偏移 -k 处
+                let w = weight(-k);
+                let (own, other) = (slot(player), slot(-player));
+                if placed {
+                    self.codes[own][idx][dir] += OWN * w;
+                    self.codes[other][idx][dir] += BLOCKED * w;
+                } else {
+                    self.codes[own][idx][dir] -= OWN * w;
+                    self.codes[other][idx][dir] -= BLOCKED * w;
+                }
+
+                let stone = self.board.get_index(idx);
+                if stone != 0 {
+                    let score = self.stone_score(r, c, stone);
+                    self.totals[slot(stone)] += score - self.stone_scores[idx];
+                    self.stone_scores[idx] = score;
+                }
+            }
+        }
+    }
+
+    fn stone_score(&self, row: usize, col: usize, player: i32) -> i32 {
+        let patterns = std::array::from_fn(|dir| self.pattern(row, col, dir, player));
+        stone_score(patterns, row, col, self.board.center(), &self.weights)
+    }
+}
+
+// (row, col) 上棋子的分数：各方向分到的棋型分，加上组合和中心加分
+fn stone_score(patterns: [LinePattern; 4], row: usize, col: usize, center: usize, weights: &EvalWeights) -> i32 {
+    let mut score: i32 = patterns
+        .iter()
+        .filter(|pattern| pattern.stones > 0)
+        .map(|pattern| weights.shape_score(pattern.shape) / pattern.stones as i32)
+        .sum();
+
+    let count = |shapes: &[LineShape]| patterns.iter().filter(|pattern| shapes.contains(&pattern.shape)).count();
+    let fours = count(&[LineShape::LiveFour, LineShape::RushFour]);
+    let live_threes = count(&[LineShape::LiveThree]);
+    if fours >= 2 || (fours >= 1 && live_threes >= 1) {
+        score += weights.four_combo;
+    } else if live_threes >= 2 {
+        score += weights.double_three;
+    }
+
+    if row.abs_diff(center) + col.abs_diff(center) <= 2 {
+        score += weights.center;
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::test_util::board_with;
+    use crate::zobrist::splitmix64;
+    use crate::WHITE;
+
+    #[test]
+    fn recognises_basic_shapes() {
+        let pos = Evaluator::new(&board_with(&[(7, 6), (7, 7)], &[]), RuleSet::Freestyle);
+        assert_eq!(pos.shape(7, 8, 0, BLACK), LineShape::LiveThree);
+        assert_eq!(pos.shape(7, 9, 0, BLACK), LineShape::LiveThree);
+
+        let pos = Evaluator::new(&board_with(&[(7, 5), (7, 6), (7, 7)], &[(7, 4)]), RuleSet::Freestyle);
+        assert_eq!(pos.shape(7, 8, 0, BLACK), LineShape::RushFour);
+        assert_eq!(pos.shape(7, 9, 0, BLACK), LineShape::RushFour);
+
+        let pos = Evaluator::new(&board_with(&[(7, 4), (7, 5), (7, 6), (7, 7)], &[]), RuleSet::Freestyle);
+        assert_eq!(pos.shape(7, 8, 0, BLACK), LineShape::Five);
+        assert_eq!(pos.shape(7, 5, 0, BLACK), LineShape::LiveFour);
+    }
+
+    #[test]
+    fn recognises_gapped_shapes() {
+        // _X_XX_：每颗棋子都是活三，一端被堵是眠三
+        let board = board_with(&[(7, 4), (7, 6), (7, 7)], &[]);
+        let pos = Evaluator::new(&board, RuleSet::Freestyle);
+        for col in [4, 6, 7] {
+            assert_eq!(pos.shape(7, col, 0, BLACK), LineShape::LiveThree);
+            assert_eq!(pos.pattern(7, col, 0, BLACK).stones, 3);
+        }
+        assert_eq!(pos.shape(7, 5, 0, BLACK), LineShape::LiveFour);
+        let pos = Evaluator::new(&board_with(&[(7, 4), (7, 6), (7, 7)], &[(7, 3)]), RuleSet::Freestyle);
+        assert_eq!(pos.shape(7, 4, 0, BLACK), LineShape::SleepThree);
+
+        // XX_XX 和 X_XXX 只有中间一个成五点，两端空着也是冲四
+        for black in [[(7, 3), (7, 4), (7, 6), (7, 7)], [(7, 3), (7, 5), (7, 6), (7, 7)]] {
+            let pos = Evaluator::new(&board_with(&black, &[]), RuleSet::Freestyle);
+            for (row, col) in black {
+                assert_eq!(pos.shape(row, col, 0, BLACK), LineShape::RushFour, "{:?}", black);
+                assert_eq!(pos.pattern(row, col, 0, BLACK).stones, 4);
+            }
+        }
+    }
+
+    #[test]
+    fn shape_is_counted_once_and_split_among_its_stones() {
+        // 只给一种棋型计分，单颗棋子在其它方向上的棋型都不得分
+        let only = |field: &str, value: i32| {
+            let mut weights = EvalWeights::default();
+            for (name, weight) in weights.fields_mut() {
+                *weight = if name == field { value } else { 0 };
+            }
+            weights
+        };
+        let three = board_with(&[(2, 4), (2, 6), (2, 7)], &[]);
+        let weights = only("live_three", 300);
+        assert_eq!(evaluate(&three, BLACK, RuleSet::Freestyle, &weights), 300);
+        let pos = Evaluator::with_weights(&three, RuleSet::Freestyle, weights);
+        assert_eq!(pos.stone_score(2, 4, BLACK), 100);
+        assert_eq!(pos.score(BLACK), 300);
+        assert_eq!(pos.score(WHITE), -300);
+
+        let four = board_with(&[(2, 3), (2, 4), (2, 6), (2, 7)], &[]);
+        assert_eq!(evaluate(&four, BLACK, RuleSet::Freestyle, &only("rush_four", 400)), 400);
+    }
+
+    #[test]
+    fn exact_five_rules_reject_overlines() {
+        let board = board_with(&[(7, 2), (7, 3), (7, 4), (7, 6), (7, 7)], &[]);
+        assert_eq!(Evaluator::new(&board, RuleSet::Freestyle).shape(7, 5, 0, BLACK), LineShape::Five);
+        assert_ne!(Evaluator::new(&board, RuleSet::Standard).shape(7, 5, 0, BLACK), LineShape::Five);
+        assert_ne!(Evaluator::new(&board, RuleSet::Renju).shape(7, 5, 0, BLACK), LineShape::Five);
+        assert_eq!(Evaluator::new(&board, RuleSet::Renju).shape(7, 5, 0, WHITE), LineShape::None);
+    }
+
+    #[test]
+    fn caro_edge_is_not_a_block() {
+        // 左端是棋盘边缘、右端是黑子，(0,2) 成五按 Caro 规则算胜
+        let board = board_with(&[(0, 5)], &[(0, 0), (0, 1), (0, 3), (0, 4)]);
+        let pos = Evaluator::new(&board, RuleSet::Caro);
+        assert_eq!(pos.shape(0, 2, 0, WHITE), LineShape::Five);
+
+        // 两端都是对方棋子才算堵住
+        let board = board_with(&[(7, 2), (7, 8)], &[(7, 3), (7, 4), (7, 6), (7, 7)]);
+        assert_eq!(Evaluator::new(&board, RuleSet::Caro).shape(7, 5, 0, WHITE), LineShape::None);
+        assert_eq!(Evaluator::new(&board, RuleSet::Freestyle).shape(7, 5, 0, WHITE), LineShape::Five);
+    }
+
+    #[test]
+    fn incremental_score_matches_full_evaluation() {
+        let weights = EvalWeights::default();
+        for (rule, size) in [(RuleSet::Freestyle, 15), (RuleSet::Standard, 15), (RuleSet::Renju, 15), (RuleSet::Caro, 15), (RuleSet::Caro, 19)] {
+            let mut pos = Evaluator::new(&Board::with_size(size).unwrap(), rule);
+            let mut state = size as u64;
+            let mut played = Vec::new();
+            let mut player = BLACK;
+            // 随机落子，边下边与整盘评估比较；中途提掉一批棋子再继续
+            for step in 0..120 {
+                if step % 40 == 39 {
+                    for _ in 0..10 {
+                        let (row, col) = played.pop().unwrap();
+                        pos.unmake_move(row, col);
+                    }
+                } else {
+                    let empty: Vec<usize> = pos.board().empty_cells().iter().collect();
+                    let (next, value) = splitmix64(state);
+                    state = next;
+                    let (row, col) = Board::coords(empty[(value % empty.len() as u64) as usize]);
+                    pos.make_move(row, col, player);
+                    played.push((row, col));
+                    player = -player;
+                }
+                for side in [BLACK, WHITE] {
+                    assert_eq!(pos.score(side), evaluate(pos.board(), side, rule, &weights), "{:?} 第 {} 步", rule, step);
+                }
+            }
+            assert_eq!(Evaluator::new(pos.board(), rule).score(BLACK), pos.score(BLACK));
+        }
+    }
+}
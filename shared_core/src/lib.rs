@@ -2,61 +2,132 @@
 // 平台无关的业务逻辑，可同时用于Tauri和WASM
 
 use serde::{Deserialize, Serialize};
-use rayon::prelude::*;
-use std::sync::{Arc, Mutex};
+
+pub mod board;
+pub mod candidates;
+pub mod eval;
+pub mod mcts;
+pub mod opening;
+mod ordering;
+pub mod rules;
+pub mod search;
+pub mod threat;
+pub mod trace;
+pub mod tt;
+pub mod vcf;
+pub mod vct;
+pub mod weights;
+pub mod zobrist;
+
+pub use board::{BitSet, Board, MAX_BOARD_SIZE, MAX_CELL_COUNT, MIN_BOARD_SIZE};
+pub use candidates::{Candidate, CandidateKind, Candidates};
+pub use eval::{Evaluator, LineShape};
+pub use mcts::{search_mcts, Mcts, MctsLimits, MctsResult, MoveVisits};
+pub use opening::{choose_color, get_ai_color_choice, get_ai_opening_move, ColorChoice, GamePhase, OpeningRule, Participant};
+pub use rules::{forbidden_kind, Forbidden, RuleSet};
+pub use search::{principal_variation, search_best_move, IterationHook, PvLine, SearchFeatures, SearchLimits, SearchResult, Searcher};
+pub use trace::{DecisionStage, MoveReport, SearchInfo, TraceEvent, TraceHook};
+pub use tt::{Bound, TTEntry, TranspositionTable};
+pub use vcf::{find_vcf, VcfLimits, VcfOutcome};
+pub use vct::{find_vct, find_vct_defense, VctLimits, VctOutcome};
+pub use weights::EvalWeights;
+
+use search::Timer;
 
 // 游戏常量
+// 默认棋盘尺寸；实际尺寸由 Board 在运行时决定
 pub const BOARD_SIZE: usize = 15;
 pub const WIN_LENGTH: usize = 5;
 pub const WIN_SCORE: i32 = 100000;
 pub const LOSE_SCORE: i32 = -100000;
-pub const MAX_MOVES: usize = 15;
-pub const MAX_DEPTH: i32 = 4;
+pub const MAX_DEPTH: i32 = 16;
+
+// 黑棋先行；连珠规则的禁手只针对黑棋
+pub const BLACK: i32 = 1;
+pub const WHITE: i32 = -1;
+
+// 本机可用的搜索线程数，最多4个
+fn default_threads() -> usize {
+    if cfg!(target_arch = "wasm32") {
+        return 1;
+    }
+    std::thread::available_parallelism().map_or(1, |n| n.get()).min(4)
+}
 
 // AI难度等级
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AIDifficulty {
-    Easy,    // 简单：深度2，候选6，并行度1
-    Medium,  // 中等：深度3，候选10，并行度2
-    Hard,    // 困难：深度4，候选12，并行度4
+    Easy,    // 简单：深度2，候选6，常走次优着法，不找VCF
+    Medium,  // 中等：深度4，候选10，偶尔走次优着法，找VCF
+    Hard,    // 困难：迭代加深到深度8，候选12，找VCF/VCT并化解对手的VCT
 }
 
 impl AIDifficulty {
     pub fn get_max_depth(&self) -> i32 {
         match self {
-            AIDifficulty::Easy => 0,    // 初级不用深度搜索
-            AIDifficulty::Medium => 1,  // 中级1层
-            AIDifficulty::Hard => 2,    // 高级2层
+            AIDifficulty::Easy => 2,          // 初级2层（己方+对手）
+            AIDifficulty::Medium => 4,        // 中级4层
+            AIDifficulty::Hard => MAX_DEPTH,  // 高级迭代加深到最大深度，由时间预算截止
         }
     }
     
+    // 搜索中每个节点考虑的普通着法数；成五、挡四、活三等战术着法不受限制
     pub fn get_max_moves(&self) -> usize {
         match self {
-            AIDifficulty::Easy => 3,    // 初级只搜索3个位置
-            AIDifficulty::Medium => 5,  // 中级5个
-            AIDifficulty::Hard => 8,    // 高级8个
+            AIDifficulty::Easy => 6,
+            AIDifficulty::Medium => 10,
+            AIDifficulty::Hard => search::DEFAULT_SEARCH_WIDTH,
         }
     }
     
+    // 故意失误：根节点取前几条变化，在比最佳着法差不超过 margin 分的着法中随机选一个
+    pub fn get_handicap(&self) -> (usize, i32) {
+        match self {
+            AIDifficulty::Easy => (4, 1500),
+            AIDifficulty::Medium => (2, 150),
+            AIDifficulty::Hard => (1, 0),
+        }
+    }
+    
+    // 搜索线程数；wasm32 上始终单线程
     pub fn get_parallel_threads(&self) -> usize {
         match self {
             AIDifficulty::Easy => 1,    // 初级单线程
             AIDifficulty::Medium => 1,  // 中级单线程
-            AIDifficulty::Hard => 2,    // 高级2线程
+            AIDifficulty::Hard => default_threads(),  // 高级按CPU核数，最多4线程
+        }
+    }
+    
+    // 置换表内存预算（MB）
+    pub fn get_tt_size_mb(&self) -> usize {
+        match self {
+            AIDifficulty::Easy => 1,
+            AIDifficulty::Medium => 4,
+            AIDifficulty::Hard => tt::DEFAULT_TT_SIZE_MB,
         }
     }
     
+    // 搜索的时间预算（毫秒），迭代加深到时返回最后一次完整迭代的结果
     pub fn get_time_limit_ms(&self) -> u64 {
         match self {
-            AIDifficulty::Easy => 0,     // 初级无时间限制
-            AIDifficulty::Medium => 0,   // 中级无时间限制
-            AIDifficulty::Hard => 0,     // 高级无时间限制
+            AIDifficulty::Easy => 200,     // 初级0.2秒
+            AIDifficulty::Medium => 500,   // 中级0.5秒
+            AIDifficulty::Hard => 2000,    // 高级2秒
         }
     }
 }
 
+// 搜索后端；成五、挡五和 VCF/VCT 等战术阶段两者相同，只有最后的搜索不同
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EngineKind {
+    #[default]
+    AlphaBeta, // 迭代加深 Alpha-Beta
+    Mcts,      // 蒙特卡洛树搜索，按难度的时间预算和宽度模拟
+}
+
 // 方向向量
-const DIRECTIONS: [(i32, i32); 4] = [
+pub const DIRECTIONS: [(i32, i32); 4] = [
     (0, 1),   // 横向
     (1, 0),   // 纵向
     (1, 1),   // 主对角线
@@ -68,10 +139,18 @@ const DIRECTIONS: [(i32, i32); 4] = [
 // 游戏状态结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameState {
-    pub board: Vec<Vec<i32>>,
+    pub board: Board,
     pub current_player: i32,
     pub ai_player: i32,
     pub human_player: i32,
+    // 对局规则，旧数据没有该字段时按自由规则处理
+    #[serde(default)]
+    pub rule: RuleSet,
+    // 开局规则和当前阶段，旧数据没有这些字段时视为无开局协议、正在对局
+    #[serde(default)]
+    pub opening: OpeningRule,
+    #[serde(default)]
+    pub phase: GamePhase,
 }
 
 // 移动结果
@@ -82,804 +161,681 @@ pub struct MoveResult {
     pub score: i32,
 }
 
-// 检查位置是否有效
-pub fn is_valid_position(row: i32, col: i32) -> bool {
-    row >= 0 && row < BOARD_SIZE as i32 && col >= 0 && col < BOARD_SIZE as i32
+// 选点的附加选项，供分析界面和协议引擎使用
+#[derive(Default)]
+pub struct MoveOptions<'a> {
+    // 不允许AI走的点
+    pub excluded: &'a [(usize, usize)],
+    // 跨步复用的置换表；不提供时按难度新建
+    pub tt: Option<&'a TranspositionTable>,
+    // 覆盖难度自带的搜索时间（毫秒）
+    pub time_limit_ms: Option<u64>,
+    // 覆盖难度自带的搜索线程数
+    pub threads: Option<usize>,
+    // 评估权重，不提供时用内置默认值
+    pub weights: Option<&'a EvalWeights>,
+    // 搜索增强（PVS、渴望窗口、LMR、静态搜索）的开关，不提供时按默认设置
+    pub features: Option<SearchFeatures>,
+    // 搜索后端，不提供时用 Alpha-Beta
+    pub engine: Option<EngineKind>,
+    // 每得到一个（更深的）结果回调一次
+    pub on_iteration: Option<IterationHook<'a>>,
+    // 选点过程中的追踪事件
+    pub on_trace: Option<TraceHook<'a>>,
 }
 
-// 检查是否获胜
-pub fn check_win(board: &Vec<Vec<i32>>, row: usize, col: usize) -> bool {
-    let player = board[row][col];
-    if player == 0 {
-        return false;
-    }
+// 每个VCF/VCT求解器最多占用总时间预算的几分之一，其余留给搜索
+const SOLVER_TIME_SHARE: u64 = 4;
 
-    for (dx, dy) in DIRECTIONS.iter() {
-        let mut count = 1;
-        
-        // 检查正方向
-        for i in 1..WIN_LENGTH {
-            let new_row = row as i32 + dx * i as i32;
-            let new_col = col as i32 + dy * i as i32;
-            if !is_valid_position(new_row, new_col) || 
-               board[new_row as usize][new_col as usize] != player {
-                break;
-            }
-            count += 1;
-        }
-        
-        // 检查反方向
-        for i in 1..WIN_LENGTH {
-            let new_row = row as i32 - dx * i as i32;
-            let new_col = col as i32 - dy * i as i32;
-            if !is_valid_position(new_row, new_col) || 
-               board[new_row as usize][new_col as usize] != player {
-                break;
-            }
-            count += 1;
-        }
-        
-        if count >= WIN_LENGTH {
-            return true;
-        }
-    }
-    
-    false
+// 选点流程的状态：附加选项、累计的统计和计时
+struct MoveTracker<'a> {
+    options: MoveOptions<'a>,
+    info: SearchInfo,
+    timer: Timer,
 }
 
-// 五子棋模式识别结构
-#[derive(Debug, Clone)]
-struct PatternInfo {
-    count: usize,      // 连子数量
-    blocked: usize,    // 被阻塞的方向数
-    empty_ends: usize, // 空端数量
-}
+impl MoveTracker<'_> {
+    fn allows(&self, row: usize, col: usize) -> bool {
+        !self.options.excluded.contains(&(row, col))
+    }
 
-// 分析一个方向的模式
-fn analyze_direction(board: &Vec<Vec<i32>>, row: usize, col: usize, dx: i32, dy: i32, player: i32) -> PatternInfo {
-    let mut count = 1;
-    let mut blocked = 0;
-    let mut empty_ends = 0;
-    
-    // 检查正方向
-    let mut pos = 1;
-    loop {
-        let new_row = row as i32 + dx * pos;
-        let new_col = col as i32 + dy * pos;
-        if !is_valid_position(new_row, new_col) {
-            blocked += 1;
-            break;
-        }
-        let cell = board[new_row as usize][new_col as usize];
-        if cell == player {
-            count += 1;
-            pos += 1;
-        } else if cell == 0 {
-            empty_ends += 1;
-            break;
-        } else {
-            blocked += 1;
-            break;
+    fn trace(&mut self, event: TraceEvent) {
+        if let Some(hook) = self.options.on_trace.as_mut() {
+            hook(&event);
         }
     }
-    
-    // 检查反方向
-    pos = 1;
-    loop {
-        let new_row = row as i32 - dx * pos;
-        let new_col = col as i32 - dy * pos;
-        if !is_valid_position(new_row, new_col) {
-            blocked += 1;
-            break;
-        }
-        let cell = board[new_row as usize][new_col as usize];
-        if cell == player {
-            count += 1;
-            pos += 1;
-        } else if cell == 0 {
-            empty_ends += 1;
-            break;
-        } else {
-            blocked += 1;
-            break;
+
+    // 总预算中还剩下的时间，至少留1毫秒；总预算为0（不限时）时仍为0
+    fn remaining_ms(&self, time_limit_ms: u64) -> u64 {
+        match time_limit_ms {
+            0 => 0,
+            total => total.saturating_sub(self.timer.elapsed_ms()).max(1),
         }
     }
-    
-    PatternInfo { count, blocked, empty_ends }
-}
 
-// 评估位置分数（改进版）
-fn evaluate_position(board: &Vec<Vec<i32>>, row: usize, col: usize, player: i32) -> i32 {
-    let mut score = 0;
-    let mut patterns = Vec::new();
-    
-    // 分析四个方向的模式
-    for (dx, dy) in DIRECTIONS.iter() {
-        let pattern = analyze_direction(board, row, col, *dx, *dy, player);
-        patterns.push(pattern);
-    }
-    
-    // 根据模式组合评分
-    for pattern in &patterns {
-        match pattern.count {
-            5 => score += WIN_SCORE, // 五连
-            4 => {
-                if pattern.blocked == 0 {
-                    score += 50000; // 活四
-                } else if pattern.blocked == 1 {
-                    score += 10000; // 冲四
-                }
-            },
-            3 => {
-                if pattern.blocked == 0 {
-                    score += 5000; // 活三
-                } else if pattern.blocked == 1 {
-                    score += 1000; // 眠三
-                }
-            },
-            2 => {
-                if pattern.blocked == 0 {
-                    score += 500; // 活二
-                } else if pattern.blocked == 1 {
-                    score += 100; // 眠二
-                }
-            },
-            1 => {
-                if pattern.blocked == 0 {
-                    score += 50; // 活一
-                }
-            },
-            _ => {}
+    // 单个VCF/VCT求解器的时间预算：总预算的一部分，且不超过剩余时间
+    fn solver_ms(&self, time_limit_ms: u64) -> u64 {
+        match time_limit_ms {
+            0 => 0,
+            total => (total / SOLVER_TIME_SHARE).clamp(1, self.remaining_ms(total)),
         }
     }
-    
-    // 特别关注3连和4连的威胁
-    let live_fours = patterns.iter().filter(|p| p.count == 4 && p.blocked == 0).count();
-    let live_threes = patterns.iter().filter(|p| p.count == 3 && p.blocked == 0).count();
-    let sleep_fours = patterns.iter().filter(|p| p.count == 4 && p.blocked == 1).count();
-    let sleep_threes = patterns.iter().filter(|p| p.count == 3 && p.blocked == 1).count();
-    
-    // 3连威胁加分
-    if live_threes > 0 {
-        score += live_threes as i32 * 8000; // 活三威胁
-    }
-    if sleep_threes > 0 {
-        score += sleep_threes as i32 * 2000; // 眠三威胁
-    }
-    
-    // 4连威胁加分
-    if live_fours > 0 {
-        score += live_fours as i32 * 60000; // 活四威胁
-    }
-    if sleep_fours > 0 {
-        score += sleep_fours as i32 * 15000; // 冲四威胁
-    }
-    
-    // 特殊模式加分
-    let live_fours = patterns.iter().filter(|p| p.count == 4 && p.blocked == 0).count();
-    let live_threes = patterns.iter().filter(|p| p.count == 3 && p.blocked == 0).count();
-    let live_twos = patterns.iter().filter(|p| p.count == 2 && p.blocked == 0).count();
-    
-    // 双活三、双活四等组合模式
-    if live_fours >= 2 {
-        score += 100000; // 双活四
-    } else if live_fours >= 1 && live_threes >= 1 {
-        score += 80000; // 活四+活三
-    } else if live_threes >= 2 {
-        score += 30000; // 双活三
-    } else if live_threes >= 1 && live_twos >= 2 {
-        score += 15000; // 活三+双活二
-    }
-    
-    // 中心位置加分
-    let center = 7;
-    let distance_from_center = (row as i32 - center).abs() + (col as i32 - center).abs();
-    if distance_from_center <= 2 {
-        score += 100; // 中心区域加分
+
+    // 记录一次VCF/VCT求解的结论，节点数计入本次选点的统计
+    fn solver(&mut self, stage: DecisionStage, found: bool, nodes: u64, started: Timer) {
+        self.info.nodes += nodes;
+        self.trace(TraceEvent::Solver { stage, found, nodes, elapsed_ms: started.elapsed_ms() });
     }
-    
-    score
-}
 
-// 评估整个棋盘
-pub fn evaluate_board(board: &Vec<Vec<i32>>, player: i32) -> i32 {
-    let mut score = 0;
-    
-    for row in 0..BOARD_SIZE {
-        for col in 0..BOARD_SIZE {
-            if board[row][col] == player {
-                score += evaluate_position(board, row, col, player);
-            } else if board[row][col] == -player {
-                score -= evaluate_position(board, row, col, -player);
+    // 确定着法：line 为从该着法开始的变化；战术阶段的着法也按一次迭代汇报，depth 为着法序列长度
+    fn decide(&mut self, stage: DecisionStage, line: &[(usize, usize)], score: i32) -> Option<MoveResult> {
+        let (row, col) = line[0];
+        if stage != DecisionStage::Search {
+            self.info.depth = line.len() as i32;
+            self.info.pv = line.to_vec();
+            if let Some(hook) = self.options.on_iteration.as_mut() {
+                hook(&SearchResult {
+                    best_move: Some((row, col)),
+                    score,
+                    depth: line.len() as i32,
+                    nodes: self.info.nodes,
+                    cutoffs: 0,
+                    tt_hits: 0,
+                    elapsed_ms: self.timer.elapsed_ms(),
+                    pv: line.to_vec(),
+                    lines: vec![PvLine { row, col, score, pv: line.to_vec() }],
+                });
             }
         }
+        self.info.stage = Some(stage);
+        self.info.score = score;
+        let elapsed_ms = self.timer.elapsed_ms();
+        self.trace(TraceEvent::Decision { stage, row, col, score, elapsed_ms });
+        Some(MoveResult { row, col, score })
     }
-    
-    score
 }
 
-// 获取可能的移动位置（智能版）
-pub fn get_possible_moves(board: &Vec<Vec<i32>>) -> Vec<(usize, usize)> {
-    let mut moves = Vec::new();
-    
-    // 检查是否为空棋盘
-    let mut has_pieces = false;
-    for row in 0..BOARD_SIZE {
-        for col in 0..BOARD_SIZE {
-            if board[row][col] != 0 {
-                has_pieces = true;
-                break;
-            }
-        }
-        if has_pieces { break; }
-    }
-    
-    // 如果棋盘为空，返回中心位置
-    if !has_pieces {
-        moves.push((7, 7));
-        return moves;
-    }
-    
-    // 找到所有已有棋子的位置
-    let mut piece_positions = Vec::new();
-    for row in 0..BOARD_SIZE {
-        for col in 0..BOARD_SIZE {
-            if board[row][col] != 0 {
-                piece_positions.push((row, col));
-            }
-        }
-    }
-    
-    // 为每个已有棋子周围的空位添加候选位置（扩大搜索范围）
-    let mut candidate_positions = std::collections::HashSet::new();
-    
-    for (piece_row, piece_col) in piece_positions {
-        // 搜索周围2格范围内的位置，增加候选数量
-        for dr in -2..=2 {
-            for dc in -2..=2 {
-                if dr == 0 && dc == 0 { continue; } // 跳过自己
-                
-                let new_row = piece_row as i32 + dr;
-                let new_col = piece_col as i32 + dc;
-                
-                if is_valid_position(new_row, new_col) {
-                    let row = new_row as usize;
-                    let col = new_col as usize;
-                    
-                    if board[row][col] == 0 {
-                        candidate_positions.insert((row, col));
-                    }
-                }
-            }
-        }
-    }
-    
-    // 将候选位置添加到移动列表
-    for (row, col) in candidate_positions {
-        moves.push((row, col));
+// 检查是否获胜（自由规则）
+pub fn check_win(board: &Board, row: usize, col: usize) -> bool {
+    check_win_with_rule(board, row, col, RuleSet::Freestyle)
+}
+
+// 按指定规则检查 (row, col) 上的棋子是否获胜
+pub fn check_win_with_rule(board: &Board, row: usize, col: usize, rule: RuleSet) -> bool {
+    if !board.contains_cell(row, col) {
+        return false;
     }
-    
-    // 按位置价值排序
-    moves.sort_by_key(|&(row, col)| {
-        // 计算位置价值：考虑中心距离和周围棋子密度
-        let center = 7;
-        let distance_from_center = (row as i32 - center).abs() + (col as i32 - center).abs();
-        
-        // 计算周围棋子密度
-        let mut neighbor_count = 0;
-        for dr in -1..=1 {
-            for dc in -1..=1 {
-                if dr == 0 && dc == 0 { continue; }
-                let new_row = row as i32 + dr;
-                let new_col = col as i32 + dc;
-                if is_valid_position(new_row, new_col) && board[new_row as usize][new_col as usize] != 0 {
-                    neighbor_count += 1;
-                }
-            }
-        }
-        
-        // 优先选择：距离中心近且周围有棋子的位置
-        // 对于相同价值的位置，优先选择更靠近中心的位置
-        (distance_from_center, -neighbor_count, row.abs_diff(center as usize), col.abs_diff(center as usize))
-    });
-    
-    // 限制数量但保留更多候选
-    if moves.len() > MAX_MOVES {
-        moves.truncate(MAX_MOVES);
+    let player = board.get(row, col);
+    if player == 0 {
+        return false;
     }
+
+    (0..DIRECTIONS.len()).any(|dir| rule.is_five_in_direction(board, row, col, dir, player))
+}
+
+// 评估整个棋盘（自由规则）
+pub fn evaluate_board(board: &Board, player: i32) -> i32 {
+    evaluate_board_with_rule(board, player, RuleSet::Freestyle)
+}
+
+// 按规则评估整个棋盘；搜索中请直接使用增量维护的 Evaluator
+pub fn evaluate_board_with_rule(board: &Board, player: i32, rule: RuleSet) -> i32 {
+    evaluate_board_with_weights(board, player, rule, &EvalWeights::default())
+}
+
+// 按规则和指定的评估权重评估整个棋盘
+pub fn evaluate_board_with_weights(board: &Board, player: i32, rule: RuleSet, weights: &EvalWeights) -> i32 {
+    eval::evaluate(board, player, rule, weights)
+}
+
+// 获取轮到的一方（按子数的奇偶）在自由规则下的候选位置
+pub fn get_possible_moves(board: &Board) -> Vec<(usize, usize)> {
+    let player = if board.stone_count().is_multiple_of(2) { BLACK } else { WHITE };
+    get_possible_moves_with_rule(board, player, RuleSet::Freestyle)
+}
+
+// 按规则获取 player 可走的候选位置：与搜索共用候选生成，不截断，被迫应对时只有应对的着法，不含禁手点
+pub fn get_possible_moves_with_rule(board: &Board, player: i32, rule: RuleSet) -> Vec<(usize, usize)> {
+    let mut moves = candidates::candidate_moves(board, player, rule);
     
-    // 如果没有找到任何移动，返回所有空位
+    // 应对点全是禁手时退回到其它空位
     if moves.is_empty() {
-        for row in 0..BOARD_SIZE {
-            for col in 0..BOARD_SIZE {
-                if board[row][col] == 0 {
-                    moves.push((row, col));
-                }
-            }
-        }
+        let mut scratch = *board;
+        moves.extend(
+            board.empty_cells().iter().map(Board::coords)
+                .filter(|&(row, col)| !rule.is_forbidden(&mut scratch, row, col, player)),
+        );
     }
     
     moves
 }
 
-// Minimax算法（智能版）
-fn minimax(
-    board: &mut Vec<Vec<i32>>,
-    depth: i32,
-    alpha: i32,
-    beta: i32,
-    is_maximizing: bool,
-    player: i32,
-    opponent: i32,
-) -> i32 {
-    if depth == 0 {
-        return evaluate_board(board, player);
-    }
-    
-    // 获取可能的移动
-    let moves = get_possible_moves(board);
-    if moves.is_empty() {
-        return evaluate_board(board, player);
-    }
-    
-    // 动态调整搜索的移动数量
-    let search_moves = if moves.len() > 12 {
-        &moves[..12]  // 搜索前12个最佳候选位置
-    } else {
-        &moves
-    };
-    
-    if is_maximizing {
-        let mut max_eval = LOSE_SCORE;
-        let mut alpha = alpha;
-        for &(row, col) in search_moves {
-            board[row][col] = player;
-            
-            // 快速获胜检查
-            if check_win(board, row, col) {
-                board[row][col] = 0;
-                return WIN_SCORE;
-            }
-            
-            let eval = minimax(board, depth - 1, alpha, beta, false, player, opponent);
-            board[row][col] = 0;
-            max_eval = max_eval.max(eval);
-            alpha = alpha.max(eval);
-            if beta <= alpha {
-                break; // Alpha-Beta剪枝
-            }
-        }
-        max_eval
-    } else {
-        let mut min_eval = WIN_SCORE;
-        let mut beta = beta;
-        for &(row, col) in search_moves {
-            board[row][col] = opponent;
-            
-            // 快速获胜检查
-            if check_win(board, row, col) {
-                board[row][col] = 0;
-                return LOSE_SCORE;
-            }
-            
-            let eval = minimax(board, depth - 1, alpha, beta, true, player, opponent);
-            board[row][col] = 0;
-            min_eval = min_eval.min(eval);
-            beta = beta.min(eval);
-            if beta <= alpha {
-                break; // Alpha-Beta剪枝
-            }
-        }
-        min_eval
-    }
+// 获取最佳移动（智能版）
+pub fn get_best_move(board: &Board, ai_player: i32, human_player: i32) -> Option<MoveResult> {
+    get_best_move_with_difficulty(board, ai_player, human_player, AIDifficulty::Hard)
 }
 
-// 并行Minimax算法（高性能版）
-fn parallel_minimax(
-    board: &Vec<Vec<i32>>,
-    depth: i32,
-    alpha: i32,
-    beta: i32,
-    is_maximizing: bool,
-    player: i32,
-    opponent: i32,
+// 根据难度等级获取最佳移动（自由规则）
+pub fn get_best_move_with_difficulty(board: &Board, ai_player: i32, human_player: i32, difficulty: AIDifficulty) -> Option<MoveResult> {
+    get_best_move_with_rule(board, ai_player, human_player, difficulty, RuleSet::Freestyle)
+}
+
+// 按规则和难度等级获取最佳移动
+pub fn get_best_move_with_rule(board: &Board, ai_player: i32, human_player: i32, difficulty: AIDifficulty, rule: RuleSet) -> Option<MoveResult> {
+    get_best_move_with_options(board, ai_player, human_player, difficulty, rule, MoveOptions::default())
+}
+
+// 按规则、难度和附加选项获取最佳移动
+pub fn get_best_move_with_options(
+    board: &Board,
+    ai_player: i32,
+    human_player: i32,
     difficulty: AIDifficulty,
-) -> i32 {
-    if depth == 0 {
-        return evaluate_board(board, player);
-    }
-    
-    // 获取可能的移动
-    let moves = get_possible_moves(board);
-    if moves.is_empty() {
-        return evaluate_board(board, player);
-    }
-    
-    // 根据难度调整搜索的移动数量
-    let max_moves = difficulty.get_max_moves();
-    let search_moves = if moves.len() > max_moves {
-        &moves[..max_moves]
-    } else {
-        &moves
+    rule: RuleSet,
+    options: MoveOptions,
+) -> Option<MoveResult> {
+    get_best_move_with_info(board, ai_player, human_player, difficulty, rule, options).0
+}
+
+// 获取最佳移动，同时返回搜索统计（决定着法的阶段、深度、节点数、用时等）
+pub fn get_best_move_with_info(
+    board: &Board,
+    ai_player: i32,
+    human_player: i32,
+    difficulty: AIDifficulty,
+    rule: RuleSet,
+    options: MoveOptions,
+) -> (Option<MoveResult>, SearchInfo) {
+    let mut tracker = MoveTracker {
+        options,
+        info: SearchInfo::default(),
+        timer: Timer::start(),
     };
-    
-    if is_maximizing {
-        let mut max_eval = LOSE_SCORE;
-        let mut alpha = alpha;
-        
-        // 并行处理移动
-        let results: Vec<(usize, usize, i32)> = search_moves
-            .par_iter()
-            .map(|&(row, col)| {
-                let mut test_board = board.clone();
-                test_board[row][col] = player;
-                
-                // 快速获胜检查
-                if check_win(&test_board, row, col) {
-                    return (row, col, WIN_SCORE);
-                }
-                
-                let eval = parallel_minimax(&test_board, depth - 1, alpha, beta, false, player, opponent, difficulty);
-                (row, col, eval)
-            })
-            .collect();
-        
-        // 按分数排序并应用Alpha-Beta剪枝
-        for (row, col, eval) in results {
-            max_eval = max_eval.max(eval);
-            alpha = alpha.max(eval);
-            if beta <= alpha {
-                break; // Alpha-Beta剪枝
-            }
-        }
-        
-        max_eval
-    } else {
-        let mut min_eval = WIN_SCORE;
-        let mut beta = beta;
-        
-        // 并行处理移动
-        let results: Vec<(usize, usize, i32)> = search_moves
-            .par_iter()
-            .map(|&(row, col)| {
-                let mut test_board = board.clone();
-                test_board[row][col] = opponent;
-                
-                // 快速获胜检查
-                if check_win(&test_board, row, col) {
-                    return (row, col, LOSE_SCORE);
-                }
-                
-                let eval = parallel_minimax(&test_board, depth - 1, alpha, beta, true, player, opponent, difficulty);
-                (row, col, eval)
-            })
-            .collect();
-        
-        // 按分数排序并应用Alpha-Beta剪枝
-        for (row, col, eval) in results {
-            min_eval = min_eval.min(eval);
-            beta = beta.min(eval);
-            if beta <= alpha {
-                break; // Alpha-Beta剪枝
-            }
-        }
-        
-        min_eval
-    }
+    let best = choose_move(board, ai_player, human_player, difficulty, rule, &mut tracker);
+    let mut info = tracker.info;
+    info.elapsed_ms = tracker.timer.elapsed_ms();
+    (best, info)
 }
 
-// 获取最佳移动（智能版）
-pub fn get_best_move(board: &Vec<Vec<i32>>, ai_player: i32, human_player: i32) -> Option<MoveResult> {
-    get_best_move_with_difficulty(board, ai_player, human_player, AIDifficulty::Hard)
+// 获取最佳移动，并收集全部追踪事件
+pub fn get_best_move_report(board: &Board, ai_player: i32, human_player: i32, difficulty: AIDifficulty, rule: RuleSet) -> MoveReport {
+    let mut trace = Vec::new();
+    let mut record = |event: &TraceEvent| trace.push(event.clone());
+    let options = MoveOptions {
+        on_trace: Some(&mut record),
+        ..Default::default()
+    };
+    let (best, info) = get_best_move_with_info(board, ai_player, human_player, difficulty, rule, options);
+    MoveReport { best, info, trace }
 }
 
-// 根据难度等级获取最佳移动（高性能并行版）
-pub fn get_best_move_with_difficulty(board: &Vec<Vec<i32>>, ai_player: i32, human_player: i32, difficulty: AIDifficulty) -> Option<MoveResult> {
-    
-    // 0. AI先手必须下天元（中心位置）
-    let move_count = count_moves(board);
-    if move_count == 0 {
-        // 第一步：AI先手必须下天元
-        if board[7][7] == 0 {
-            return Some(MoveResult {
-                row: 7,
-                col: 7,
-                score: 10000, // 天元位置的高分
-            });
-        }
-    }
-    
-    // 0.5. AI后手第二步：在人类棋子的八个方向中选择一个
-    if move_count == 1 {
-        // 找到人类棋子的位置
-        let mut human_pos = None;
-        for row in 0..BOARD_SIZE {
-            for col in 0..BOARD_SIZE {
-                if board[row][col] != 0 {
-                    human_pos = Some((row, col));
-                    break;
-                }
-            }
-            if human_pos.is_some() { break; }
-        }
-        
-        if let Some((h_row, h_col)) = human_pos {
-            // 八个方向：上下左右和四个对角线
-            let directions = [
-                (-1, -1), (-1, 0), (-1, 1),  // 上左、上、上右
-                (0, -1),           (0, 1),   // 左、右
-                (1, -1),  (1, 0),  (1, 1),   // 下左、下、下右
-            ];
-            
-            // 按优先级选择方向：优先选择中心方向
-            let center = 7;
-            let mut best_direction = None;
-            let mut best_distance = 100;
-            
-            for &(dr, dc) in &directions {
-                let new_row = h_row as i32 + dr;
-                let new_col = h_col as i32 + dc;
-                
-                if is_valid_position(new_row, new_col) {
-                    let row = new_row as usize;
-                    let col = new_col as usize;
-                    
-                    if board[row][col] == 0 {
-                        // 计算这个位置到中心的距离
-                        let distance = (row as i32 - center).abs() + (col as i32 - center).abs();
-                        if distance < best_distance {
-                            best_distance = distance;
-                            best_direction = Some((row, col));
-                        }
-                    }
-                }
-            }
-            
-            if let Some((row, col)) = best_direction {
-                return Some(MoveResult {
-                    row,
-                    col,
-                    score: 8000, // 八个方向位置的高分
-                });
-            }
-        }
-    }
+// 选点流程：一步成五 → 挡五 → VCF → VCT → 化解VCT → 启发式威胁判断 → 搜索/静态评估
+fn choose_move(
+    board: &Board,
+    ai_player: i32,
+    human_player: i32,
+    difficulty: AIDifficulty,
+    rule: RuleSet,
+    tracker: &mut MoveTracker,
+) -> Option<MoveResult> {
+    // 搜索过程中在副本上落子/撤销，不再克隆整个棋盘
+    let mut board = *board;
     
     // 1. 检查AI立即获胜（最高优先级）
-    for row in 0..BOARD_SIZE {
-        for col in 0..BOARD_SIZE {
-            if board[row][col] == 0 {
-                let mut test_board = board.clone();
-                test_board[row][col] = ai_player;
-                if check_win(&test_board, row, col) {
-                    return Some(MoveResult {
-                        row,
-                        col,
-                        score: WIN_SCORE,
-                    });
-                }
-            }
+    for (row, col) in board.empty_cells().iter().map(Board::coords).filter(|&(r, c)| tracker.allows(r, c)) {
+        board.make_move(row, col, ai_player);
+        let won = check_win_with_rule(&board, row, col, rule);
+        board.unmake_move(row, col);
+        if won {
+            return tracker.decide(DecisionStage::InstantWin, &[(row, col)], WIN_SCORE);
         }
     }
     
-    // 2. 检查对手立即获胜（必须防守）
-    for row in 0..BOARD_SIZE {
-        for col in 0..BOARD_SIZE {
-            if board[row][col] == 0 {
-                let mut test_board = board.clone();
-                test_board[row][col] = human_player;
-                if check_win(&test_board, row, col) {
-                    return Some(MoveResult {
-                        row,
-                        col,
-                        score: WIN_SCORE - 1,
-                    });
-                }
-            }
+    // 2. 检查对手立即获胜（必须防守；挡点是AI的禁手时无法防守）
+    for (row, col) in board.empty_cells().iter().map(Board::coords).filter(|&(r, c)| tracker.allows(r, c)) {
+        board.make_move(row, col, human_player);
+        let won = check_win_with_rule(&board, row, col, rule);
+        board.unmake_move(row, col);
+        if won && !rule.is_forbidden(&mut board, row, col, ai_player) {
+            return tracker.decide(DecisionStage::ForcedBlock, &[(row, col)], WIN_SCORE - 1);
         }
     }
     
-    // 3. 评估当前威胁情况
-    let mut ai_threats = Vec::new();
-    let mut human_threats = Vec::new();
-    
-    for row in 0..BOARD_SIZE {
-        for col in 0..BOARD_SIZE {
-            if board[row][col] == 0 {
-                // 评估AI在这个位置的威胁
-                let mut test_board = board.clone();
-                test_board[row][col] = ai_player;
-                let ai_score = evaluate_board(&test_board, ai_player);
-                
-                // 评估对手在这个位置的威胁
-                let mut test_board2 = board.clone();
-                test_board2[row][col] = human_player;
-                let human_score = evaluate_board(&test_board2, human_player);
-                
-                ai_threats.push((row, col, ai_score));
-                human_threats.push((row, col, human_score));
+    // 求解器和搜索共用一个时间预算，从选点开始计时
+    let time_limit_ms = tracker.options.time_limit_ms.unwrap_or(difficulty.get_time_limit_ms());
+
+    // 2.5. AI存在连续冲四必胜（VCF），按序列走第一手
+    if difficulty != AIDifficulty::Easy {
+        let started = Timer::start();
+        let limits = VcfLimits { time_limit_ms: tracker.solver_ms(time_limit_ms), ..Default::default() };
+        let (outcome, nodes) = vcf::solve_vcf(&board, ai_player, rule, limits);
+        tracker.solver(DecisionStage::Vcf, matches!(outcome, VcfOutcome::Win(_)), nodes, started);
+        if let VcfOutcome::Win(sequence) = outcome {
+            let (row, col) = sequence[0];
+            if tracker.allows(row, col) {
+                return tracker.decide(DecisionStage::Vcf, &sequence, WIN_SCORE - sequence.len() as i32);
             }
         }
     }
     
-    // 4. 智能决策：根据威胁程度决定进攻还是防守
-    let max_human_threat = human_threats.iter().map(|(_, _, score)| *score).max().unwrap_or(0);
-    let max_ai_threat = ai_threats.iter().map(|(_, _, score)| *score).max().unwrap_or(0);
-    
-    // 决策逻辑：
-    // - 如果对手有高威胁（活三、活四），必须防守
-    // - 如果AI有高威胁且对手威胁较低，优先进攻
-    // - 如果双方威胁相当，选择威胁最大的位置
-    
-    if max_human_threat > 5000 {
-        // 对手有高威胁，必须防守
-        let best_defense = human_threats.iter()
-            .max_by_key(|(_, _, score)| *score)
-            .unwrap();
-        return Some(MoveResult {
-            row: best_defense.0,
-            col: best_defense.1,
-            score: best_defense.2,
-        });
-    } else if max_ai_threat > 1000 && max_ai_threat > max_human_threat {
-        // AI有优势，优先进攻
-        let best_attack = ai_threats.iter()
-            .max_by_key(|(_, _, score)| *score)
-            .unwrap();
-        return Some(MoveResult {
-            row: best_attack.0,
-            col: best_attack.1,
-            score: best_attack.2,
-        });
-    } else {
-        // 双方威胁相当，选择综合评分最高的位置
-        let mut best_move = None;
-        let mut best_score = LOSE_SCORE;
-        
-        for i in 0..ai_threats.len() {
-            let ai_score = ai_threats[i].2;
-            let human_score = human_threats[i].2;
-            let combined_score = ai_score - human_score; // 进攻分数减去防守分数
-            
-            if combined_score > best_score {
-                best_score = combined_score;
-                best_move = Some(MoveResult {
-                    row: ai_threats[i].0,
-                    col: ai_threats[i].1,
-                    score: combined_score,
-                });
+    // 2.6. 高级：AI存在连续威胁必胜（VCT），按主变化走第一手
+    if difficulty == AIDifficulty::Hard {
+        let started = Timer::start();
+        let limits = VctLimits { time_limit_ms: tracker.solver_ms(time_limit_ms), ..Default::default() };
+        let (outcome, nodes) = vct::solve_vct(&board, ai_player, rule, limits);
+        tracker.solver(DecisionStage::Vct, matches!(outcome, VctOutcome::Win(_)), nodes, started);
+        if let VctOutcome::Win(line) = outcome {
+            let (row, col) = line[0];
+            if tracker.allows(row, col) {
+                return tracker.decide(DecisionStage::Vct, &line, WIN_SCORE - line.len() as i32);
             }
         }
         
-        if let Some(move_result) = best_move {
-            return Some(move_result);
+        // 2.7. 高级：对手存在VCT时先化解
+        let started = Timer::start();
+        let limits = VctLimits { time_limit_ms: tracker.solver_ms(time_limit_ms), ..Default::default() };
+        let (defense, nodes) = vct::solve_vct_defense(&board, ai_player, rule, limits);
+        tracker.solver(DecisionStage::VctDefense, defense.is_some(), nodes, started);
+        if let Some((row, col)) = defense.filter(|&(r, c)| tracker.allows(r, c)) {
+            board.make_move(row, col, ai_player);
+            let weights = tracker.options.weights.copied().unwrap_or_default();
+            let score = evaluate_board_with_weights(&board, ai_player, rule, &weights);
+            board.unmake_move(row, col);
+            return tracker.decide(DecisionStage::VctDefense, &[(row, col)], score);
+        }
+    }
+    
+    let (lines, margin) = difficulty.get_handicap();
+    let weights = tracker.options.weights.copied().unwrap_or_default();
+    // 搜索只用求解器剩下的时间
+    let time_limit_ms = tracker.remaining_ms(time_limit_ms);
+
+    // 3'. MCTS 后端：按难度的时间预算和宽度模拟，走访问次数最多的着法
+    if tracker.options.engine.unwrap_or_default() == EngineKind::Mcts {
+        let limits = MctsLimits { time_limit_ms, max_iterations: 0, width: difficulty.get_max_moves() };
+        let result = Mcts::new(limits, rule)
+            .exclude_root_moves(tracker.options.excluded)
+            .weights(weights)
+            .search(&board, ai_player);
+        tracker.info.nodes += result.iterations;
+        tracker.info.pv = result.pv.clone();
+        tracker.info.visits = result.visits.clone();
+        let (row, col) = result.best_move?;
+        let candidates: Vec<PvLine> = result.visits.iter()
+            .take(lines)
+            .map(|m| PvLine { row: m.row, col: m.col, score: m.score, pv: vec![(m.row, m.col)] })
+            .collect();
+        if let Some(pick) = handicap_pick(&board, &candidates, result.score, margin).filter(|pick| (pick.row, pick.col) != (row, col)) {
+            return tracker.decide(DecisionStage::Handicap, &pick.pv, pick.score);
         }
+        let line = if result.pv.is_empty() { vec![(row, col)] } else { result.pv };
+        return tracker.decide(DecisionStage::Mcts, &line, result.score);
     }
     
-    
-    // 使用简化的AI算法
-    let moves = get_possible_moves(board);
-    
-    if moves.is_empty() {
-        return None;
-    }
-    
-    // 根据难度等级调整搜索的移动数量
-    let max_moves = difficulty.get_max_moves();
-    let search_moves = if moves.len() > max_moves {
-        &moves[..max_moves]
-    } else {
-        &moves
+    // 3. 按难度的深度、宽度和时间预算迭代加深搜索
+    let limits = SearchLimits {
+        max_depth: difficulty.get_max_depth(),
+        time_limit_ms,
+        max_nodes: 0,
+        width: difficulty.get_max_moves(),
     };
-    
-    // 简单评估所有候选位置
-    let mut best_move = None;
-    let mut best_score = LOSE_SCORE;
-    
-    for &(row, col) in search_moves {
-        let mut test_board = board.clone();
-        test_board[row][col] = ai_player;
-        let mut score = evaluate_board(&test_board, ai_player);
-        
-        // 如果是中高级难度，进行简单的1层搜索
-        if difficulty != AIDifficulty::Easy {
-            score = simple_minimax(&test_board, difficulty.get_max_depth(), ai_player, human_player);
+    let threads = tracker.options.threads.unwrap_or(difficulty.get_parallel_threads());
+    let features = tracker.options.features.unwrap_or_default();
+    let owned_tt;
+    let tt = match tracker.options.tt {
+        Some(tt) => tt,
+        None => {
+            owned_tt = TranspositionTable::new(difficulty.get_tt_size_mb());
+            &owned_tt
         }
-        
-        if score > best_score {
-            best_score = score;
-            best_move = Some(MoveResult {
-                row,
-                col,
-                score,
+    };
+    // 每轮迭代既转给调用方的回调，也记一条追踪事件
+    let MoveOptions { on_iteration, on_trace, excluded, .. } = &mut tracker.options;
+    let mut report = |result: &SearchResult| {
+        if let Some(hook) = on_iteration.as_mut() {
+            hook(result);
+        }
+        if let Some(hook) = on_trace.as_mut() {
+            hook(&TraceEvent::Iteration {
+                depth: result.depth,
+                score: result.score,
+                nodes: result.nodes,
+                elapsed_ms: result.elapsed_ms,
+                pv: result.pv.clone(),
             });
         }
-    }
-    
-    best_move
+    };
+    let result = Searcher::new(tt, limits, rule)
+        .exclude_root_moves(excluded)
+        .multi_pv(lines)
+        .threads(threads)
+        .weights(weights)
+        .features(features)
+        .on_iteration(&mut report)
+        .iterative_deepening(&board, ai_player);
+    tracker.info.depth = result.depth;
+    tracker.info.nodes += result.nodes;
+    tracker.info.cutoffs = result.cutoffs;
+    tracker.info.tt_hits = result.tt_hits;
+    tracker.info.pv = result.pv.clone();
+    let (row, col) = result.best_move?;
+    
+    // 4. 低难度故意失误
+    if let Some(pick) = handicap_pick(&board, &result.lines, result.score, margin).filter(|pick| (pick.row, pick.col) != (row, col)) {
+        let line = if pick.pv.is_empty() { vec![(pick.row, pick.col)] } else { pick.pv.clone() };
+        return tracker.decide(DecisionStage::Handicap, &line, pick.score);
+    }
+    let line = if result.pv.is_empty() { vec![(row, col)] } else { result.pv };
+    tracker.decide(DecisionStage::Search, &line, result.score)
 }
 
-// 简化的Minimax算法（快速版）
-fn simple_minimax(board: &Vec<Vec<i32>>, depth: i32, ai_player: i32, human_player: i32) -> i32 {
-    if depth == 0 {
-        return evaluate_board(board, ai_player);
-    }
-    
-    let moves = get_possible_moves(board);
-    if moves.is_empty() {
-        return evaluate_board(board, ai_player);
-    }
-    
-    // 只搜索前3个最佳位置
-    let search_moves = if moves.len() > 3 {
-        &moves[..3]
-    } else {
-        &moves
-    };
-    
-    let mut best_score = LOSE_SCORE;
-    
-    for &(row, col) in search_moves {
-        let mut test_board = board.clone();
-        test_board[row][col] = human_player; // 对手的回合
-        
-        // 快速获胜检查
-        if check_win(&test_board, row, col) {
-            return LOSE_SCORE; // 对手获胜
-        }
-        
-        let score = -simple_minimax(&test_board, depth - 1, ai_player, human_player);
-        best_score = best_score.max(score);
+// 在分差不超过 margin 的几条变化中按局面哈希挑一条，不走必败的着法；只有一条可选时返回 None
+fn handicap_pick<'l>(board: &Board, lines: &'l [PvLine], best_score: i32, margin: i32) -> Option<&'l PvLine> {
+    let playable: Vec<&PvLine> = lines.iter()
+        .filter(|line| line.score >= best_score - margin && line.score > -search::MATE_BOUND)
+        .collect();
+    if playable.len() < 2 {
+        return None;
     }
-    
-    best_score
+    let (_, mixed) = zobrist::splitmix64(board.hash());
+    Some(playable[(mixed % playable.len() as u64) as usize])
 }
 
-// 计算棋盘上的棋子数量
-fn count_moves(board: &Vec<Vec<i32>>) -> usize {
-    let mut count = 0;
-    for row in 0..BOARD_SIZE {
-        for col in 0..BOARD_SIZE {
-            if board[row][col] != 0 {
-                count += 1;
-            }
-        }
-    }
-    count
+// 分析局面：给出 player 的前 multi_pv 个不同着法，各带分数和主要变化，按分数从高到低
+// 中高级先找VCF（高级还找VCT），有必胜时必胜线排在第一条，其余由多主变搜索补足
+pub fn analyze_position(board: &Board, player: i32, difficulty: AIDifficulty, rule: RuleSet, multi_pv: usize) -> Vec<PvLine> {
+    analyze_position_with_weights(board, player, difficulty, rule, multi_pv, &EvalWeights::default())
 }
 
+// 用指定的评估权重分析局面
+pub fn analyze_position_with_weights(
+    board: &Board,
+    player: i32,
+    difficulty: AIDifficulty,
+    rule: RuleSet,
+    multi_pv: usize,
+    weights: &EvalWeights,
+) -> Vec<PvLine> {
+    let wanted = multi_pv.max(1);
+    let mut lines = Vec::new();
+    
+    if difficulty != AIDifficulty::Easy {
+        let winning_line = match find_vcf(board, player, rule, VcfLimits::default()) {
+            VcfOutcome::Win(sequence) => Some(sequence),
+            _ if difficulty == AIDifficulty::Hard => match find_vct(board, player, rule, VctLimits::default()) {
+                VctOutcome::Win(line) => Some(line),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(pv) = winning_line {
+            let (row, col) = pv[0];
+            lines.push(PvLine { row, col, score: WIN_SCORE - pv.len() as i32, pv });
+        }
+    }
+    
+    if lines.len() < wanted {
+        let limits = SearchLimits {
+            max_depth: difficulty.get_max_depth(),
+            time_limit_ms: difficulty.get_time_limit_ms(),
+            max_nodes: 0,
+            width: difficulty.get_max_moves(),
+        };
+        let tt = TranspositionTable::new(difficulty.get_tt_size_mb());
+        let excluded: Vec<(usize, usize)> = lines.iter().map(|line| (line.row, line.col)).collect();
+        let result = Searcher::new(&tt, limits, rule)
+            .exclude_root_moves(&excluded)
+            .multi_pv(wanted - lines.len())
+            .threads(difficulty.get_parallel_threads())
+            .weights(*weights)
+            .iterative_deepening(board, player);
+        lines.extend(result.lines);
+    }
+    
+    lines
+}
 
 // 创建新的游戏状态
 pub fn create_game_state(ai_player: i32, human_player: i32) -> GameState {
+    create_game_state_with_rule(ai_player, human_player, RuleSet::Freestyle)
+}
+
+// 按指定规则创建新的游戏状态
+pub fn create_game_state_with_rule(ai_player: i32, human_player: i32, rule: RuleSet) -> GameState {
+    new_game_state(Board::new(), ai_player, human_player, rule)
+}
+
+// 按指定规则和棋盘尺寸（15、19、20 等）创建新的游戏状态
+pub fn create_game_state_with_size(ai_player: i32, human_player: i32, rule: RuleSet, size: usize) -> Result<GameState, String> {
+    Ok(new_game_state(Board::with_size(size)?, ai_player, human_player, rule))
+}
+
+fn new_game_state(board: Board, ai_player: i32, human_player: i32, rule: RuleSet) -> GameState {
     GameState {
-        board: vec![vec![0; BOARD_SIZE]; BOARD_SIZE],
-        current_player: human_player, // 人类先手
+        board,
+        current_player: BLACK, // 黑方先手
         ai_player,
         human_player,
+        rule,
+        opening: OpeningRule::Free,
+        phase: GamePhase::Playing,
     }
 }
 
+// 按开局协议创建新的游戏状态：proposer 摆开局棋子，选色之前暂时执黑
+pub fn create_game_state_with_opening(rule: RuleSet, opening: OpeningRule, proposer: Participant, size: usize) -> Result<GameState, String> {
+    let (ai_player, human_player) = match proposer {
+        Participant::Ai => (BLACK, WHITE),
+        Participant::Human => (WHITE, BLACK),
+    };
+    Ok(GameState {
+        opening,
+        phase: opening::initial_phase(opening, proposer),
+        ..create_game_state_with_size(ai_player, human_player, rule, size)?
+    })
+}
+
 // 执行移动
 pub fn make_move(game_state: &mut GameState, row: usize, col: usize) -> Result<bool, String> {
-    if !is_valid_position(row as i32, col as i32) {
+    if !game_state.board.contains_cell(row, col) {
         return Err("无效的位置".to_string());
     }
     
-    if game_state.board[row][col] != 0 {
+    if !game_state.board.is_empty_at(row, col) {
         return Err("位置已被占用".to_string());
     }
     
-    game_state.board[row][col] = game_state.current_player;
+    // 开局阶段按开局协议处理
+    if game_state.phase != GamePhase::Playing {
+        return opening::play_opening_move(game_state, row, col);
+    }
+    
+    if game_state.rule.is_forbidden(&mut game_state.board, row, col, game_state.current_player) {
+        return Err("黑棋禁手".to_string());
+    }
+    
+    game_state.board.make_move(row, col, game_state.current_player);
     
     // 检查是否获胜
-    let won = check_win(&game_state.board, row, col);
+    let won = check_win_with_rule(&game_state.board, row, col, game_state.rule);
     
     // 切换玩家
-    game_state.current_player = if game_state.current_player == game_state.ai_player {
-        game_state.human_player
-    } else {
-        game_state.ai_player
-    };
+    game_state.current_player = -game_state.current_player;
     
     Ok(won)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::test_util::board_with;
+
+    #[test]
+    fn solvers_get_a_share_of_what_is_left() {
+        let tracker = |elapsed_ms: u64| MoveTracker {
+            options: MoveOptions::default(),
+            info: SearchInfo::default(),
+            timer: Timer::started_ms_ago(elapsed_ms),
+        };
+        // 刚开始：每个求解器最多四分之一，三个求解器用完后搜索仍有剩余
+        let fresh = tracker(0);
+        assert_eq!(fresh.solver_ms(400), 400 / SOLVER_TIME_SHARE);
+        assert!(fresh.remaining_ms(400) > 3 * fresh.solver_ms(400));
+        // 剩余时间不足一份时只给剩下的
+        let late = tracker(350);
+        assert!(late.solver_ms(400) <= 50 && late.solver_ms(400) >= 1);
+        assert!(late.remaining_ms(400) <= 50);
+        // 超时后至少留1毫秒，不会变成“不限时”的0
+        let over = tracker(500);
+        assert_eq!((over.solver_ms(400), over.remaining_ms(400)), (1, 1));
+        assert_eq!((over.solver_ms(0), over.remaining_ms(0)), (0, 0));
+    }
+
+    #[test]
+    #[ignore = "按墙钟计时，机器繁忙时可能超出"]
+    fn solvers_and_search_share_the_time_limit() {
+        // 威胁很多的中局，VCT 和化解VCT 不限时都要很久
+        let board = board_with(
+            &[(4, 6), (5, 8), (5, 9), (6, 6), (6, 10), (7, 4), (9, 8), (10, 7)],
+            &[(4, 9), (4, 10), (5, 6), (7, 5), (7, 6), (7, 9), (7, 10), (8, 6)],
+        );
+        for engine in [EngineKind::AlphaBeta, EngineKind::Mcts] {
+            let options = MoveOptions { time_limit_ms: Some(400), engine: Some(engine), ..Default::default() };
+            let (best, info) = get_best_move_with_info(&board, BLACK, WHITE, AIDifficulty::Hard, RuleSet::Freestyle, options);
+            assert!(best.is_some());
+            assert!(info.elapsed_ms < 1200, "{:?} 用时 {} 毫秒", engine, info.elapsed_ms);
+        }
+    }
+
+    #[test]
+    fn possible_moves_keep_a_far_away_block() {
+        // 中心有一大片棋子，白四唯一的挡点在棋盘边上
+        let board = board_with(
+            &[(6, 6), (6, 7), (6, 8), (7, 6), (7, 8), (8, 7), (0, 2)],
+            &[(7, 7), (8, 6), (8, 8), (0, 3), (0, 4), (0, 5), (0, 6)],
+        );
+        assert_eq!(get_possible_moves(&board), [(0, 7)]);
+        assert_eq!(get_possible_moves_with_rule(&board, BLACK, RuleSet::Renju), [(0, 7)]);
+
+        // 平静局面不截断
+        let board = board_with(&[(7, 7), (7, 9)], &[(8, 8), (6, 9)]);
+        assert!(get_possible_moves(&board).len() > 15);
+    }
+
+    #[test]
+    fn handicap_picks_a_close_line_but_never_a_lost_one() {
+        let line = |row: usize, score: i32| PvLine { row, col: 0, score, pv: vec![(row, 0)] };
+        // 第4条走了之后对方下一手成五
+        let lines = [line(1, 1000), line(2, 900), line(3, -400), line(4, -(WIN_SCORE - 2))];
+        for (difficulty, allowed) in [(AIDifficulty::Easy, &[1, 2, 3][..]), (AIDifficulty::Medium, &[1, 2][..])] {
+            let (count, margin) = difficulty.get_handicap();
+            let mut picked = Vec::new();
+            // 挑哪一条只取决于局面，换不同的局面来覆盖各种选择
+            for cell in 0..40 {
+                let board = board_with(&[(cell / 15, cell % 15)], &[]);
+                let pick = handicap_pick(&board, &lines[..count.min(lines.len())], 1000, margin).expect("有多条可选");
+                assert!(allowed.contains(&pick.row), "{:?} 选了 {:?}", difficulty, pick);
+                if !picked.contains(&pick.row) {
+                    picked.push(pick.row);
+                }
+            }
+            picked.sort();
+            assert_eq!(picked, allowed, "{:?}", difficulty);
+        }
+
+        let (count, margin) = AIDifficulty::Hard.get_handicap();
+        assert!(handicap_pick(&Board::new(), &lines[..count], 1000, margin).is_none());
+        // 只剩必败的变化可选时不故意失误
+        assert!(handicap_pick(&Board::new(), &[line(1, 1000), line(4, -(WIN_SCORE - 2))], 1000, 1500).is_none());
+    }
+
+    #[test]
+    fn check_win_reaches_the_edges_of_a_20x20_board() {
+        let mut board = Board::with_size(20).unwrap();
+        // 最后一列的竖五、最后一行的横五、贴着右下角的斜五
+        for i in 15..20 {
+            board.make_move(i, 19, BLACK);
+            board.make_move(19, i - 15, WHITE);
+        }
+        assert!(check_win(&board, 19, 19) && check_win(&board, 15, 19));
+        assert!(check_win(&board, 19, 0) && check_win(&board, 19, 4));
+
+        let mut board = Board::with_size(20).unwrap();
+        for i in 0..4 {
+            board.make_move(15 + i, 15 + i, BLACK);
+        }
+        assert!(!check_win(&board, 18, 18));
+        board.make_move(19, 19, BLACK);
+        assert!(check_win(&board, 19, 19));
+        // 15x15 之外的格子照常识别，出界的坐标不算
+        assert!(!check_win(&board, 20, 20));
+    }
+
+    // 按选点过程记录追踪事件和每轮迭代汇报的节点数
+    fn traced_move(board: &Board, difficulty: AIDifficulty) -> (MoveResult, SearchInfo, Vec<TraceEvent>, Vec<u64>) {
+        let mut trace = Vec::new();
+        let mut iterations = Vec::new();
+        let mut record = |event: &TraceEvent| trace.push(event.clone());
+        let mut report = |result: &SearchResult| iterations.push(result.nodes);
+        let options = MoveOptions {
+            time_limit_ms: Some(300),
+            threads: Some(1),
+            on_trace: Some(&mut record),
+            on_iteration: Some(&mut report),
+            ..Default::default()
+        };
+        let (best, info) = get_best_move_with_info(board, BLACK, WHITE, difficulty, RuleSet::Freestyle, options);
+        (best.expect("应当有着法"), info, trace, iterations)
+    }
+
+    fn solver_nodes(trace: &[TraceEvent]) -> u64 {
+        trace.iter().map(|event| match event {
+            TraceEvent::Solver { nodes, .. } => *nodes,
+            _ => 0,
+        }).sum()
+    }
+
+    #[test]
+    fn trace_runs_solvers_then_iterations_then_the_decision() {
+        let board = board_with(&[(7, 7), (8, 8), (6, 8), (9, 6)], &[(7, 8), (8, 7), (6, 6), (9, 9)]);
+        let (best, info, trace, iterations) = traced_move(&board, AIDifficulty::Hard);
+        let solvers: Vec<DecisionStage> = trace.iter().filter_map(|event| match event {
+            TraceEvent::Solver { stage, found, .. } => {
+                assert!(!found);
+                Some(*stage)
+            }
+            _ => None,
+        }).collect();
+        assert_eq!(solvers, [DecisionStage::Vcf, DecisionStage::Vct, DecisionStage::VctDefense]);
+        assert!(trace[3..trace.len() - 1].iter().all(|event| matches!(event, TraceEvent::Iteration { .. })));
+        assert!(trace.len() > 4);
+        assert!(matches!(trace.last(), Some(&TraceEvent::Decision { stage: DecisionStage::Search, row, col, score, .. })
+            if (row, col, score) == (best.row, best.col, best.score)));
+        assert_eq!(info.stage, Some(DecisionStage::Search));
+        // 统计的节点数包括求解器和搜索
+        assert_eq!(info.nodes, solver_nodes(&trace) + iterations.last().unwrap());
+    }
+
+    #[test]
+    fn tactical_stages_report_their_own_stage_and_nodes() {
+        // 黑棋 (7,6) 冲四、(6,6) 冲四成四三，VCF 取胜
+        let board = board_with(
+            &[(7, 3), (7, 4), (7, 5), (8, 6), (9, 6), (5, 7)],
+            &[(7, 2), (10, 6), (11, 10), (11, 11), (12, 9), (2, 12)],
+        );
+        let (best, info, trace, iterations) = traced_move(&board, AIDifficulty::Medium);
+        assert_eq!(info.stage, Some(DecisionStage::Vcf));
+        assert!(matches!(trace[..], [
+            TraceEvent::Solver { stage: DecisionStage::Vcf, found: true, nodes, .. },
+            TraceEvent::Decision { stage: DecisionStage::Vcf, row, col, .. },
+        ] if nodes > 0 && nodes == info.nodes && (row, col) == (best.row, best.col)));
+        assert_eq!(iterations, [info.nodes]);
+
+        // 一步成五不展开节点
+        let board = board_with(&[(7, 3), (7, 4), (7, 5), (7, 6)], &[(8, 3), (8, 4), (8, 5), (0, 0)]);
+        let (_, info, trace, iterations) = traced_move(&board, AIDifficulty::Hard);
+        assert_eq!(info.stage, Some(DecisionStage::InstantWin));
+        assert!(matches!(trace[..], [TraceEvent::Decision { stage: DecisionStage::InstantWin, .. }]));
+        assert_eq!((info.nodes, iterations), (0, vec![0]));
+    }
+}
@@ -0,0 +1,430 @@
+// 蒙特卡洛树搜索（MCTS）- 与 Alpha-Beta 并列的另一种搜索后端
+// 选择用 PUCT 公式：候选着法按紧迫度排名给出先验概率；叶子不做随机走子，由棋型评估经 sigmoid 换算成行棋方的胜率
+// 展开用 candidates::generate，有四或活三时只展开必须应对的着法，树自然集中在战术变化上
+// 结果给出根节点各着法的访问次数和胜率，分析界面可以当作概率显示；单线程
+
+use serde::{Deserialize, Serialize};
+
+use crate::candidates::{self, Candidate};
+use crate::eval::{Evaluator, LineShape};
+use crate::search::{Timer, DEFAULT_SEARCH_WIDTH, MATE_BOUND};
+use crate::weights::EvalWeights;
+use crate::{check_win_with_rule, Board, RuleSet, WIN_SCORE};
+
+// 评估分换算胜率的尺度：领先这么多分约合 73% 的胜率
+const VALUE_SCALE: f64 = 1000.0;
+
+// PUCT 的探索系数
+const EXPLORATION: f64 = 1.5;
+
+// 未访问着法的胜率按父节点的胜率减去该值估计
+const FPU_REDUCTION: f64 = 0.2;
+
+// 每隔多少次模拟检查一次时间
+const CHECK_INTERVAL: u64 = 64;
+
+// 时间和次数都不限制时的模拟次数
+pub const DEFAULT_ITERATIONS: u64 = 10000;
+
+// 树的节点上限（每个节点约 40 字节，共约 10MB），超过后不再展开，叶子直接评估
+// 移动端和 wasm 的内存有限，宁可让长时间的搜索在已有的树上继续细化
+const MAX_TREE_NODES: usize = 1 << 18;
+
+// 搜索预算，0 表示不限制；两者都为 0 时模拟 DEFAULT_ITERATIONS 次
+#[derive(Debug, Clone, Copy)]
+pub struct MctsLimits {
+    pub time_limit_ms: u64,
+    pub max_iterations: u64,
+    // 平静局面每个节点展开的普通着法数；战术着法总会展开，不计入限制
+    pub width: usize,
+}
+
+impl Default for MctsLimits {
+    fn default() -> Self {
+        MctsLimits {
+            time_limit_ms: 0,
+            max_iterations: 0,
+            width: DEFAULT_SEARCH_WIDTH,
+        }
+    }
+}
+
+impl MctsLimits {
+    pub fn iterations(max_iterations: u64) -> Self {
+        MctsLimits { max_iterations, ..Default::default() }
+    }
+
+    pub fn time(time_limit_ms: u64) -> Self {
+        MctsLimits { time_limit_ms, ..Default::default() }
+    }
+}
+
+// 根节点一个着法的统计
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MoveVisits {
+    pub row: usize,
+    pub col: usize,
+    pub visits: u64,
+    // 走这步一方的平均胜率，0 到 1
+    pub win_rate: f64,
+    // 胜率换算回的评估分，必胜着法为胜利分数
+    pub score: i32,
+}
+
+// 一次 MCTS 的结果
+#[derive(Debug, Clone)]
+pub struct MctsResult {
+    pub best_move: Option<(usize, usize)>,
+    pub score: i32,
+    pub iterations: u64,
+    pub elapsed_ms: u64,
+    // 从根节点起每层取访问次数最多的着法
+    pub pv: Vec<(usize, usize)>,
+    // 根节点各着法，按访问次数从多到少
+    pub visits: Vec<MoveVisits>,
+}
+
+// 树上的一个节点，对应一步着法；胜负都以走这步的一方为视角
+// 节点数有上限，字段取能容纳的最小类型以压缩内存
+struct Node {
+    row: u8,
+    col: u8,
+    // 子节点在节点表中连续存放
+    child_count: u16,
+    first_child: u32,
+    visits: u32,
+    prior: f32,
+    value: f64,
+    expanded: bool,
+    // 终局时走这步一方的得分：1 为胜，0.5 为和，0 为负
+    terminal: Option<f32>,
+}
+
+impl Node {
+    fn new((row, col): (usize, usize), prior: f64) -> Self {
+        Node {
+            row: row as u8,
+            col: col as u8,
+            child_count: 0,
+            first_child: 0,
+            visits: 0,
+            prior: prior as f32,
+            value: 0.0,
+            expanded: false,
+            terminal: None,
+        }
+    }
+
+    fn mv(&self) -> (usize, usize) {
+        (self.row as usize, self.col as usize)
+    }
+
+    fn children(&self) -> std::ops::Range<usize> {
+        let first = self.first_child as usize;
+        first..first + self.child_count as usize
+    }
+
+    fn win_rate(&self) -> f64 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.value / self.visits as f64
+        }
+    }
+
+    fn score(&self) -> i32 {
+        if self.terminal == Some(1.0) {
+            return WIN_SCORE - 1;
+        }
+        let rate = self.win_rate().clamp(1e-6, 1.0 - 1e-6);
+        let score = VALUE_SCALE * (rate / (1.0 - rate)).ln();
+        (score.round() as i32).clamp(-MATE_BOUND + 1, MATE_BOUND - 1)
+    }
+}
+
+// side 视角的局面胜率
+fn leaf_value(pos: &Evaluator, side: i32) -> f64 {
+    1.0 / (1.0 + (-(pos.score(side) as f64) / VALUE_SCALE).exp())
+}
+
+pub struct Mcts {
+    limits: MctsLimits,
+    rule: RuleSet,
+    weights: EvalWeights,
+    excluded: Vec<(usize, usize)>,
+    nodes: Vec<Node>,
+}
+
+impl Mcts {
+    pub fn new(limits: MctsLimits, rule: RuleSet) -> Self {
+        Mcts {
+            limits,
+            rule,
+            weights: EvalWeights::default(),
+            excluded: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    // 根节点不考虑这些着法
+    pub fn exclude_root_moves(mut self, moves: &[(usize, usize)]) -> Self {
+        self.excluded = moves.to_vec();
+        self
+    }
+
+    // 叶子评估使用的权重
+    pub fn weights(mut self, weights: EvalWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    // 按预算模拟，返回访问次数最多的着法；没有可走的着法时 best_move 为 None
+    pub fn search(&mut self, board: &Board, side: i32) -> MctsResult {
+        let timer = Timer::start();
+        let mut pos = Evaluator::with_weights(board, self.rule, self.weights);
+        let max_iterations = match (self.limits.time_limit_ms, self.limits.max_iterations) {
+            (0, 0) => DEFAULT_ITERATIONS,
+            (_, n) => n,
+        };
+        self.nodes.clear();
+        self.nodes.push(Node::new((0, 0), 1.0));
+
+        let mut iterations = 0;
+        loop {
+            self.simulate(&mut pos, side);
+            iterations += 1;
+            // 无着法可走时不必再模拟；只有一步可走时再模拟一次，让这步有真实的胜率
+            if self.nodes[0].terminal.is_some() || (self.nodes[0].child_count == 1 && iterations >= 2) {
+                break;
+            }
+            if max_iterations > 0 && iterations >= max_iterations {
+                break;
+            }
+            if self.limits.time_limit_ms > 0 && iterations % CHECK_INTERVAL == 0 && timer.elapsed_ms() >= self.limits.time_limit_ms {
+                break;
+            }
+        }
+
+        let mut children: Vec<&Node> = self.nodes[self.nodes[0].children()].iter().collect();
+        children.sort_by(|a, b| b.visits.cmp(&a.visits).then(b.win_rate().total_cmp(&a.win_rate())));
+        let visits: Vec<MoveVisits> = children
+            .iter()
+            .map(|child| MoveVisits {
+                row: child.row as usize,
+                col: child.col as usize,
+                visits: child.visits as u64,
+                win_rate: child.win_rate(),
+                score: child.score(),
+            })
+            .collect();
+        MctsResult {
+            best_move: children.first().map(|child| child.mv()),
+            score: children.first().map_or(0, |child| child.score()),
+            iterations,
+            elapsed_ms: timer.elapsed_ms(),
+            pv: self.principal_variation(),
+            visits,
+        }
+    }
+
+    // 一次模拟：按 PUCT 选到叶子，展开并评估，再沿路径回传
+    fn simulate(&mut self, pos: &mut Evaluator, side: i32) {
+        let mut path = vec![0];
+        let mut node = 0;
+        let mut to_move = side;
+        while self.nodes[node].expanded && self.nodes[node].terminal.is_none() {
+            node = self.select_child(node);
+            let (row, col) = self.nodes[node].mv();
+            pos.make_move(row, col, to_move);
+            path.push(node);
+            to_move = -to_move;
+        }
+
+        // 走进叶子一方的得分
+        let mut value = match self.nodes[node].terminal {
+            Some(value) => value as f64,
+            None => 1.0 - self.expand(pos, node, to_move),
+        };
+        for &idx in path.iter().rev() {
+            let node = &mut self.nodes[idx];
+            node.visits += 1;
+            node.value += value;
+            value = 1.0 - value;
+        }
+        for &idx in path[1..].iter().rev() {
+            let (row, col) = self.nodes[idx].mv();
+            pos.unmake_move(row, col);
+        }
+    }
+
+    // PUCT：胜率加上按先验和访问次数折算的探索项
+    fn select_child(&self, parent: usize) -> usize {
+        let node = &self.nodes[parent];
+        let explore = EXPLORATION * (node.visits as f64).sqrt();
+        // 子节点由另一方落子，父节点的胜率要反过来看
+        let first_play = (1.0 - node.win_rate() - FPU_REDUCTION).max(0.0);
+        node.children()
+            .map(|idx| {
+                let child = &self.nodes[idx];
+                let q = if child.visits == 0 { first_play } else { child.win_rate() };
+                (idx, q + explore * child.prior as f64 / (1 + child.visits) as f64)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(idx, _)| idx)
+            .expect("展开过的非终局节点至少有一个子节点")
+    }
+
+    // 展开 side 行棋的叶子，返回 side 视角的价值；无着法可走时把叶子记为终局
+    fn expand(&mut self, pos: &Evaluator, node: usize, side: i32) -> f64 {
+        if self.nodes.len() >= MAX_TREE_NODES {
+            return leaf_value(pos, side);
+        }
+        let candidates = candidates::generate(pos, side);
+        let board = pos.board();
+        let mut moves = self.select_moves(board, &candidates.moves, node == 0, side);
+        if moves.is_empty() && node == 0 {
+            // 根节点必须给出着法：应对点都是禁手或被排除时改走周围的空位，再不行走任意空位
+            moves = self.select_moves(board, &candidates::neighborhood(pos, side), true, side);
+            if moves.is_empty() {
+                let cells: Vec<Candidate> = board
+                    .empty_cells()
+                    .iter()
+                    .map(|idx| Candidate { pos: Board::coords(idx), urgency: 0, tactical: false, attack: LineShape::None })
+                    .collect();
+                moves = self.select_moves(board, &cells, true, side);
+            }
+        }
+
+        if moves.is_empty() {
+            // 棋盘下满或附近全是禁手按和棋处理；被迫应对却无子可下则输
+            let value = if candidates.is_forced() && !candidates.moves.is_empty() { 0.0 } else { 0.5 };
+            self.nodes[node].terminal = Some(1.0 - value as f32);
+            return value;
+        }
+
+        // 先验概率按排名递减：第 i 个着法正比于 1/(i+1)
+        let total: f64 = (1..=moves.len()).map(|i| 1.0 / i as f64).sum();
+        let first_child = self.nodes.len();
+        let mut board = *board;
+        let mut wins = false;
+        for (i, &(row, col)) in moves.iter().enumerate() {
+            let mut child = Node::new((row, col), 1.0 / (i + 1) as f64 / total);
+            board.make_move(row, col, side);
+            if check_win_with_rule(&board, row, col, self.rule) {
+                child.terminal = Some(1.0);
+                wins = true;
+            }
+            board.unmake_move(row, col);
+            self.nodes.push(child);
+        }
+        let parent = &mut self.nodes[node];
+        parent.first_child = first_child as u32;
+        parent.child_count = moves.len() as u16;
+        parent.expanded = true;
+
+        if wins {
+            1.0
+        } else {
+            leaf_value(pos, side)
+        }
+    }
+
+    // 从候选中挑出要展开的着法：跳过禁手（根节点还跳过排除的着法），普通着法最多 width 个
+    fn select_moves(&self, board: &Board, candidates: &[Candidate], root: bool, side: i32) -> Vec<(usize, usize)> {
+        // 判断禁手用的棋盘副本
+        let mut scratch = self.rule.has_forbidden_moves(side).then_some(*board);
+        let mut quiet = 0;
+        let mut moves = Vec::new();
+        for candidate in candidates {
+            let (row, col) = candidate.pos;
+            if root && self.excluded.contains(&(row, col)) {
+                continue;
+            }
+            if !candidate.tactical && quiet >= self.limits.width.max(1) {
+                continue;
+            }
+            if let Some(board) = scratch.as_mut() {
+                if self.rule.is_forbidden(board, row, col, side) {
+                    continue;
+                }
+            }
+            quiet += usize::from(!candidate.tactical);
+            moves.push((row, col));
+        }
+        moves
+    }
+
+    // 从根节点起每层取访问次数最多的子节点
+    fn principal_variation(&self) -> Vec<(usize, usize)> {
+        let mut pv = Vec::new();
+        let mut node = 0;
+        while let Some(best) = self.nodes[node].children().filter(|&idx| self.nodes[idx].visits > 0).max_by_key(|&idx| self.nodes[idx].visits) {
+            pv.push(self.nodes[best].mv());
+            node = best;
+        }
+        pv
+    }
+}
+
+// 便捷入口：按预算用 MCTS 搜索 side 的最佳着法
+pub fn search_mcts(board: &Board, side: i32, rule: RuleSet, limits: MctsLimits) -> MctsResult {
+    Mcts::new(limits, rule).search(board, side)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::test_util::board_with;
+    use crate::{BLACK, WHITE};
+
+    #[test]
+    fn node_stays_small() {
+        assert!(std::mem::size_of::<Node>() <= 40);
+    }
+
+    #[test]
+    fn forbidden_forced_block_still_returns_a_move() {
+        // 白四唯一的挡点 (7,7) 是黑棋的三三禁手
+        let board = board_with(&[(7, 2), (5, 7), (6, 7), (8, 8), (9, 9)], &[(7, 3), (7, 4), (7, 5), (7, 6)]);
+        let result = search_mcts(&board, BLACK, RuleSet::Renju, MctsLimits::iterations(200));
+        let (row, col) = result.best_move.expect("棋盘未满时应当有着法");
+        let mut scratch = board;
+        assert!(board.is_empty_at(row, col) && !RuleSet::Renju.is_forbidden(&mut scratch, row, col, BLACK));
+    }
+
+    #[test]
+    fn takes_the_win_and_reports_visits() {
+        let board = board_with(&[(7, 3), (7, 4), (7, 5), (7, 6)], &[(8, 3), (8, 4), (8, 5)]);
+        let result = search_mcts(&board, BLACK, RuleSet::Freestyle, MctsLimits::iterations(200));
+        assert!(matches!(result.best_move, Some((7, 2)) | Some((7, 7))));
+        assert_eq!(result.score, WIN_SCORE - 1);
+
+        let board = board_with(&[(7, 7), (8, 8)], &[(7, 8)]);
+        let result = search_mcts(&board, WHITE, RuleSet::Freestyle, MctsLimits::iterations(500));
+        assert_eq!(result.iterations, 500);
+        // 第一次模拟只展开根节点
+        assert_eq!(result.visits.iter().map(|m| m.visits).sum::<u64>(), 499);
+        assert!(result.visits.windows(2).all(|w| w[0].visits >= w[1].visits));
+        assert_eq!(result.best_move, Some((result.visits[0].row, result.visits[0].col)));
+    }
+
+    #[test]
+    fn single_forced_reply_is_not_reported_as_a_loss() {
+        // 白四只有 (7,7) 一个挡点，挡住后局面平稳
+        let board = board_with(&[(7, 2), (9, 9)], &[(7, 3), (7, 4), (7, 5), (7, 6)]);
+        let result = search_mcts(&board, BLACK, RuleSet::Freestyle, MctsLimits::iterations(200));
+        assert_eq!(result.best_move, Some((7, 7)));
+        assert_eq!(result.iterations, 2);
+        assert_eq!(result.visits.len(), 1);
+        assert_eq!(result.visits[0].visits, 1);
+        assert!(result.score > -VALUE_SCALE as i32 * 3, "score {}", result.score);
+    }
+
+    #[test]
+    fn excluded_root_moves_are_skipped() {
+        let board = board_with(&[(7, 7)], &[]);
+        let mut mcts = Mcts::new(MctsLimits::iterations(100), RuleSet::Freestyle).exclude_root_moves(&[(6, 6), (7, 8)]);
+        let result = mcts.search(&board, WHITE);
+        assert!(result.visits.iter().all(|m| (m.row, m.col) != (6, 6) && (m.row, m.col) != (7, 8)));
+    }
+}
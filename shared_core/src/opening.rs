@@ -0,0 +1,527 @@
+// 开局规则 - Swap、Swap2、Soosõrv 等平衡开局协议的状态机，以及AI作为提议方/选择方时的决策
+// 开局阶段摆放的棋子颜色只由盘面子数决定（黑白交替），双方的执子颜色在选色后才确定
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::search::{SearchLimits, Searcher};
+use crate::{
+    check_win_with_rule, evaluate_board_with_rule, get_best_move_with_rule, get_possible_moves_with_rule, AIDifficulty, Board, GameState,
+    MoveResult, RuleSet, TranspositionTable, BLACK, WHITE,
+};
+
+// Soosõrv 未指定 N 时黑方给出的第五手候选数
+pub const SOOSORV_FIFTH_OFFERS: usize = 2;
+
+// 选择方认为局面已经平衡、宁可再摆两子的评分范围（Swap2）
+const BALANCE_MARGIN: i32 = 1500;
+
+// 序列化为 "free"、"swap"、"swap2"、"soosorv-N"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum OpeningRule {
+    // 无开局协议，黑方直接开始
+    #[default]
+    Free,
+    // 提议方摆三子（黑白黑），对方选择执黑或执白
+    Swap,
+    // 提议方摆三子，对方可以直接选色，也可以再摆两子（白黑）把选色权交回
+    Swap2,
+    // 提议方摆三子，对方可交换；执白方下第四手，对方可交换；黑方给出 fifth_offers 个第五手候选，白方选其一（Soosõrv-N）
+    Soosorv { fifth_offers: usize },
+}
+
+impl OpeningRule {
+    // 默认 N 的 Soosõrv
+    pub fn soosorv() -> Self {
+        OpeningRule::Soosorv { fifth_offers: SOOSORV_FIFTH_OFFERS }
+    }
+}
+
+impl FromStr for OpeningRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_lowercase();
+        match name.as_str() {
+            "free" | "none" => return Ok(OpeningRule::Free),
+            "swap" => return Ok(OpeningRule::Swap),
+            "swap2" => return Ok(OpeningRule::Swap2),
+            "soosorv" | "soosõrv" => return Ok(OpeningRule::soosorv()),
+            _ => {}
+        }
+        // soosorv-N：N 至少为1
+        let count = name.strip_prefix("soosorv-").or_else(|| name.strip_prefix("soosõrv-"));
+        match count.and_then(|n| n.parse::<usize>().ok()) {
+            Some(fifth_offers) if fifth_offers >= 1 => Ok(OpeningRule::Soosorv { fifth_offers }),
+            _ => Err(format!("未知的开局规则: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for OpeningRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpeningRule::Free => write!(f, "free"),
+            OpeningRule::Swap => write!(f, "swap"),
+            OpeningRule::Swap2 => write!(f, "swap2"),
+            OpeningRule::Soosorv { fifth_offers } => write!(f, "soosorv-{}", fifth_offers),
+        }
+    }
+}
+
+impl TryFrom<String> for OpeningRule {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<OpeningRule> for String {
+    fn from(opening: OpeningRule) -> Self {
+        opening.to_string()
+    }
+}
+
+// 对局双方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Participant {
+    Ai,
+    Human,
+}
+
+impl Participant {
+    pub fn other(self) -> Self {
+        match self {
+            Participant::Ai => Participant::Human,
+            Participant::Human => Participant::Ai,
+        }
+    }
+}
+
+// 选色阶段的决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorChoice {
+    Black,
+    White,
+    // Swap2：再摆两子，由对方选色
+    PlaceTwo,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "black" => Ok(ColorChoice::Black),
+            "white" => Ok(ColorChoice::White),
+            "place_two" | "placetwo" => Ok(ColorChoice::PlaceTwo),
+            _ => Err(format!("未知的选色: {}", s)),
+        }
+    }
+}
+
+// 对局阶段
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum GamePhase {
+    // by 还要摆 remaining 颗开局棋子
+    PlaceStones { by: Participant, remaining: usize },
+    // by 选择执黑或执白
+    ChooseColor { by: Participant, can_place_two: bool },
+    // Soosõrv：执黑的 by 给出第五手候选
+    OfferFifth { by: Participant, offers: Vec<(usize, usize)> },
+    // Soosõrv：执白的 by 从候选中选一个作为黑方第五手
+    SelectFifth { by: Participant, offers: Vec<(usize, usize)> },
+    // 正常对局
+    #[default]
+    Playing,
+}
+
+// 按开局规则得到初始阶段
+pub fn initial_phase(opening: OpeningRule, proposer: Participant) -> GamePhase {
+    match opening {
+        OpeningRule::Free => GamePhase::Playing,
+        _ => GamePhase::PlaceStones { by: proposer, remaining: 3 },
+    }
+}
+
+// 下一颗开局棋子的颜色：黑白交替
+pub fn color_to_place(board: &Board) -> i32 {
+    if board.stone_count().is_multiple_of(2) {
+        BLACK
+    } else {
+        WHITE
+    }
+}
+
+// 执 color 的一方
+pub fn holder_of(game_state: &GameState, color: i32) -> Participant {
+    if game_state.ai_player == color {
+        Participant::Ai
+    } else {
+        Participant::Human
+    }
+}
+
+// 当前该行动的一方
+pub fn acting_participant(game_state: &GameState) -> Participant {
+    match &game_state.phase {
+        GamePhase::PlaceStones { by, .. }
+        | GamePhase::ChooseColor { by, .. }
+        | GamePhase::OfferFifth { by, .. }
+        | GamePhase::SelectFifth { by, .. } => *by,
+        GamePhase::Playing => holder_of(game_state, game_state.current_player),
+    }
+}
+
+fn assign_color(game_state: &mut GameState, participant: Participant, color: i32) {
+    match participant {
+        Participant::Ai => {
+            game_state.ai_player = color;
+            game_state.human_player = -color;
+        }
+        Participant::Human => {
+            game_state.human_player = color;
+            game_state.ai_player = -color;
+        }
+    }
+}
+
+// 开局棋子摆完后进入的阶段
+fn after_placement(game_state: &GameState, placer: Participant) -> GamePhase {
+    let stones = game_state.board.stone_count();
+    match (game_state.opening, stones) {
+        (OpeningRule::Swap | OpeningRule::Swap2 | OpeningRule::Soosorv { .. }, 3) => GamePhase::ChooseColor {
+            by: placer.other(),
+            can_place_two: game_state.opening == OpeningRule::Swap2,
+        },
+        (OpeningRule::Swap2, 5) | (OpeningRule::Soosorv { .. }, 4) => GamePhase::ChooseColor { by: placer.other(), can_place_two: false },
+        _ => GamePhase::Playing,
+    }
+}
+
+// 开局阶段的落子：摆开局棋子、给出或选定第五手候选；由 make_move 在非对局阶段调用
+pub(crate) fn play_opening_move(game_state: &mut GameState, row: usize, col: usize) -> Result<bool, String> {
+    let rule = game_state.rule;
+    match game_state.phase.clone() {
+        GamePhase::PlaceStones { by, remaining } => {
+            let color = color_to_place(&game_state.board);
+            if rule.is_forbidden(&mut game_state.board, row, col, color) {
+                return Err("黑棋禁手".to_string());
+            }
+            game_state.board.make_move(row, col, color);
+            let won = check_win_with_rule(&game_state.board, row, col, rule);
+            game_state.phase = if remaining > 1 {
+                GamePhase::PlaceStones { by, remaining: remaining - 1 }
+            } else {
+                after_placement(game_state, by)
+            };
+            game_state.current_player = color_to_place(&game_state.board);
+            Ok(won)
+        }
+        GamePhase::OfferFifth { by, mut offers } => {
+            if offers.contains(&(row, col)) {
+                return Err("该候选已给出".to_string());
+            }
+            if rule.is_forbidden(&mut game_state.board, row, col, BLACK) {
+                return Err("黑棋禁手".to_string());
+            }
+            offers.push((row, col));
+            let wanted = match game_state.opening {
+                OpeningRule::Soosorv { fifth_offers } => fifth_offers,
+                _ => SOOSORV_FIFTH_OFFERS,
+            };
+            game_state.phase = if offers.len() >= wanted {
+                GamePhase::SelectFifth { by: by.other(), offers }
+            } else {
+                GamePhase::OfferFifth { by, offers }
+            };
+            Ok(false)
+        }
+        GamePhase::SelectFifth { offers, .. } => {
+            if !offers.contains(&(row, col)) {
+                return Err("只能从黑方给出的候选中选择".to_string());
+            }
+            game_state.board.make_move(row, col, BLACK);
+            let won = check_win_with_rule(&game_state.board, row, col, rule);
+            game_state.phase = GamePhase::Playing;
+            game_state.current_player = WHITE;
+            Ok(won)
+        }
+        GamePhase::ChooseColor { .. } => Err("请先选择执黑或执白".to_string()),
+        GamePhase::Playing => Err("开局阶段已经结束".to_string()),
+    }
+}
+
+// 选色阶段的决定
+pub fn choose_color(game_state: &mut GameState, choice: ColorChoice) -> Result<(), String> {
+    let GamePhase::ChooseColor { by, can_place_two } = game_state.phase else {
+        return Err("当前不是选色阶段".to_string());
+    };
+
+    let color = match choice {
+        ColorChoice::PlaceTwo if can_place_two => {
+            game_state.phase = GamePhase::PlaceStones { by, remaining: 2 };
+            return Ok(());
+        }
+        ColorChoice::PlaceTwo => return Err("当前不能选择再摆两子".to_string()),
+        ColorChoice::Black => BLACK,
+        ColorChoice::White => WHITE,
+    };
+    assign_color(game_state, by, color);
+
+    let stones = game_state.board.stone_count();
+    game_state.phase = match (game_state.opening, stones) {
+        (OpeningRule::Soosorv { .. }, 3) => GamePhase::PlaceStones { by: holder_of(game_state, WHITE), remaining: 1 },
+        (OpeningRule::Soosorv { .. }, 4) => GamePhase::OfferFifth { by: holder_of(game_state, BLACK), offers: Vec::new() },
+        _ => GamePhase::Playing,
+    };
+    game_state.current_player = color_to_place(&game_state.board);
+    Ok(())
+}
+
+// 以黑方视角估计局面优劣：按难度做一次短时间搜索，考虑了轮到谁走
+fn black_advantage(board: &Board, rule: RuleSet, difficulty: AIDifficulty) -> i32 {
+    let side = color_to_place(board);
+    let limits = SearchLimits {
+        max_depth: difficulty.get_max_depth().clamp(1, 4),
+        time_limit_ms: difficulty.get_time_limit_ms() / 4,
+        ..Default::default()
+    };
+    let tt = TranspositionTable::new(difficulty.get_tt_size_mb());
+    let result = Searcher::new(&tt, limits, rule).iterative_deepening(board, side);
+    result.score * side
+}
+
+// 提议方摆放开局棋子：选使局面最接近均势的位置，第一子下在中心
+fn balanced_placement(board: &Board, rule: RuleSet) -> Option<MoveResult> {
+    let center = board.center();
+    if board.is_empty() {
+        return Some(MoveResult { row: center, col: center, score: 0 });
+    }
+
+    let color = color_to_place(board);
+    let mut board = *board;
+    get_possible_moves_with_rule(&board, color, rule)
+        .into_iter()
+        .map(|(row, col)| {
+            board.make_move(row, col, color);
+            let score = evaluate_board_with_rule(&board, BLACK, rule);
+            board.unmake_move(row, col);
+            MoveResult { row, col, score }
+        })
+        .min_by_key(|m| m.score.abs())
+}
+
+// AI在开局阶段的落子：摆开局棋子、给出或选择第五手；对局阶段返回正常的最佳着法
+pub fn get_ai_opening_move(game_state: &GameState, difficulty: AIDifficulty) -> Option<MoveResult> {
+    let rule = game_state.rule;
+    let mut board = game_state.board;
+    match &game_state.phase {
+        // 摆完之后由对方选色，所以要摆得尽量平衡；Soosõrv 第四手同理
+        GamePhase::PlaceStones { .. } => balanced_placement(&board, rule),
+        GamePhase::OfferFifth { offers, .. } => {
+            // 白方会挑对黑方最不利的一个，所以黑方依次给出自己最好的几手
+            get_possible_moves_with_rule(&board, BLACK, rule)
+                .into_iter()
+                .filter(|pos| !offers.contains(pos))
+                .map(|(row, col)| {
+                    board.make_move(row, col, BLACK);
+                    let score = evaluate_board_with_rule(&board, BLACK, rule);
+                    board.unmake_move(row, col);
+                    MoveResult { row, col, score }
+                })
+                .max_by_key(|m| m.score)
+        }
+        GamePhase::SelectFifth { offers, .. } => offers
+            .iter()
+            .map(|&(row, col)| {
+                board.make_move(row, col, BLACK);
+                let score = -black_advantage(&board, rule, difficulty);
+                board.unmake_move(row, col);
+                MoveResult { row, col, score }
+            })
+            .max_by_key(|m| m.score),
+        GamePhase::ChooseColor { .. } => None,
+        GamePhase::Playing => {
+            get_best_move_with_rule(&board, game_state.ai_player, game_state.human_player, difficulty, rule)
+        }
+    }
+}
+
+// AI作为选择方的决定：有明显优势的一方就执该色；Swap2 中局面接近均势时再摆两子
+pub fn get_ai_color_choice(game_state: &GameState, difficulty: AIDifficulty) -> Option<ColorChoice> {
+    let GamePhase::ChooseColor { can_place_two, .. } = game_state.phase else {
+        return None;
+    };
+
+    let advantage = black_advantage(&game_state.board, game_state.rule, difficulty);
+    Some(if can_place_two && advantage.abs() < BALANCE_MARGIN {
+        ColorChoice::PlaceTwo
+    } else if advantage >= 0 {
+        ColorChoice::Black
+    } else {
+        ColorChoice::White
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_game_state_with_opening, make_move};
+
+    fn new_game(opening: OpeningRule, proposer: Participant) -> GameState {
+        create_game_state_with_opening(RuleSet::Freestyle, opening, proposer, 15).unwrap()
+    }
+
+    fn place(game_state: &mut GameState, stones: &[(usize, usize)]) {
+        for &(row, col) in stones {
+            assert!(!make_move(game_state, row, col).unwrap());
+        }
+    }
+
+    #[test]
+    fn swap_chooser_takes_a_color() {
+        let mut state = new_game(OpeningRule::Swap, Participant::Human);
+        assert_eq!(state.phase, GamePhase::PlaceStones { by: Participant::Human, remaining: 3 });
+        assert_eq!(acting_participant(&state), Participant::Human);
+        assert!(choose_color(&mut state, ColorChoice::Black).is_err());
+
+        place(&mut state, &[(7, 7), (7, 8), (8, 8)]);
+        assert_eq!(state.phase, GamePhase::ChooseColor { by: Participant::Ai, can_place_two: false });
+        assert!(make_move(&mut state, 6, 6).is_err());
+        assert!(choose_color(&mut state, ColorChoice::PlaceTwo).is_err());
+
+        choose_color(&mut state, ColorChoice::Black).unwrap();
+        assert_eq!(state.phase, GamePhase::Playing);
+        assert_eq!((state.ai_player, state.human_player), (BLACK, WHITE));
+        assert_eq!(state.current_player, WHITE);
+        assert_eq!(acting_participant(&state), Participant::Human);
+    }
+
+    #[test]
+    fn swap2_place_two_hands_the_choice_back() {
+        let mut state = new_game(OpeningRule::Swap2, Participant::Ai);
+        place(&mut state, &[(7, 7), (7, 8), (8, 8)]);
+        assert_eq!(state.phase, GamePhase::ChooseColor { by: Participant::Human, can_place_two: true });
+
+        choose_color(&mut state, ColorChoice::PlaceTwo).unwrap();
+        assert_eq!(state.phase, GamePhase::PlaceStones { by: Participant::Human, remaining: 2 });
+        assert_eq!(state.current_player, WHITE);
+        place(&mut state, &[(6, 8), (6, 6)]);
+        assert_eq!(state.phase, GamePhase::ChooseColor { by: Participant::Ai, can_place_two: false });
+
+        choose_color(&mut state, ColorChoice::White).unwrap();
+        assert_eq!(state.phase, GamePhase::Playing);
+        assert_eq!((state.ai_player, state.human_player), (WHITE, BLACK));
+        assert_eq!(state.current_player, WHITE);
+    }
+
+    #[test]
+    fn soosorv_runs_through_fourth_move_and_fifth_offers() {
+        let mut state = new_game(OpeningRule::soosorv(), Participant::Human);
+        place(&mut state, &[(7, 7), (7, 8), (8, 8)]);
+        assert_eq!(state.phase, GamePhase::ChooseColor { by: Participant::Ai, can_place_two: false });
+
+        // AI 执白，由 AI 下第四手
+        choose_color(&mut state, ColorChoice::White).unwrap();
+        assert_eq!(state.phase, GamePhase::PlaceStones { by: Participant::Ai, remaining: 1 });
+        place(&mut state, &[(6, 6)]);
+        assert_eq!(state.phase, GamePhase::ChooseColor { by: Participant::Human, can_place_two: false });
+
+        // 人类换成执黑，给出第五手候选
+        choose_color(&mut state, ColorChoice::Black).unwrap();
+        assert_eq!(state.phase, GamePhase::OfferFifth { by: Participant::Human, offers: Vec::new() });
+        place(&mut state, &[(9, 9)]);
+        assert!(make_move(&mut state, 9, 9).is_err());
+        place(&mut state, &[(5, 9)]);
+        assert_eq!(state.board.stone_count(), 4);
+        assert_eq!(state.phase, GamePhase::SelectFifth { by: Participant::Ai, offers: vec![(9, 9), (5, 9)] });
+
+        assert!(make_move(&mut state, 3, 3).is_err());
+        place(&mut state, &[(5, 9)]);
+        assert_eq!(state.phase, GamePhase::Playing);
+        assert_eq!(state.board.get(5, 9), BLACK);
+        assert!(state.board.is_empty_at(9, 9));
+        assert_eq!(state.current_player, WHITE);
+        assert_eq!(acting_participant(&state), Participant::Ai);
+    }
+
+    #[test]
+    fn ai_opening_decisions() {
+        let mut state = new_game(OpeningRule::Swap2, Participant::Ai);
+        let first = get_ai_opening_move(&state, AIDifficulty::Easy).unwrap();
+        assert_eq!((first.row, first.col), (7, 7));
+        assert_eq!(get_ai_color_choice(&state, AIDifficulty::Easy), None);
+
+        while matches!(state.phase, GamePhase::PlaceStones { .. }) {
+            let mv = get_ai_opening_move(&state, AIDifficulty::Easy).unwrap();
+            make_move(&mut state, mv.row, mv.col).unwrap();
+        }
+        assert_eq!(state.board.stone_count(), 3);
+        assert_eq!(get_ai_opening_move(&state, AIDifficulty::Easy).map(|m| (m.row, m.col)), None);
+        let choice = get_ai_color_choice(&state, AIDifficulty::Easy).unwrap();
+        choose_color(&mut state, choice).unwrap();
+        let expected = match choice {
+            ColorChoice::PlaceTwo => GamePhase::PlaceStones { by: Participant::Human, remaining: 2 },
+            _ => GamePhase::Playing,
+        };
+        assert_eq!(state.phase, expected);
+
+        // Soosõrv-3：AI 执黑给出三个互不相同的合法第五手，执白时从候选中选一个
+        let mut state = new_game(OpeningRule::Soosorv { fifth_offers: 3 }, Participant::Human);
+        place(&mut state, &[(7, 7), (7, 8), (8, 8)]);
+        choose_color(&mut state, ColorChoice::White).unwrap();
+        let fourth = get_ai_opening_move(&state, AIDifficulty::Easy).unwrap();
+        make_move(&mut state, fourth.row, fourth.col).unwrap();
+        choose_color(&mut state, ColorChoice::White).unwrap();
+        assert_eq!(acting_participant(&state), Participant::Ai);
+        while matches!(state.phase, GamePhase::OfferFifth { .. }) {
+            let mv = get_ai_opening_move(&state, AIDifficulty::Easy).unwrap();
+            make_move(&mut state, mv.row, mv.col).unwrap();
+        }
+        let GamePhase::SelectFifth { by, offers } = state.phase.clone() else {
+            panic!("应当进入选第五手阶段: {:?}", state.phase);
+        };
+        assert_eq!(by, Participant::Human);
+        assert_eq!(offers.len(), 3);
+        let mut board = state.board;
+        for (i, &(row, col)) in offers.iter().enumerate() {
+            assert!(!offers[..i].contains(&(row, col)));
+            assert!(board.is_empty_at(row, col) && !state.rule.is_forbidden(&mut board, row, col, BLACK));
+        }
+        let pick = get_ai_opening_move(&state, AIDifficulty::Easy).unwrap();
+        assert!(offers.contains(&(pick.row, pick.col)));
+    }
+
+    #[test]
+    fn soosorv_takes_the_number_of_fifth_offers() {
+        assert_eq!("soosorv".parse(), Ok(OpeningRule::Soosorv { fifth_offers: SOOSORV_FIFTH_OFFERS }));
+        assert_eq!("Soosõrv-4".parse(), Ok(OpeningRule::Soosorv { fifth_offers: 4 }));
+        assert!("soosorv-0".parse::<OpeningRule>().is_err());
+        assert!("soosorv-x".parse::<OpeningRule>().is_err());
+        for opening in [OpeningRule::Free, OpeningRule::Swap2, OpeningRule::Soosorv { fifth_offers: 3 }] {
+            let json = serde_json::to_string(&opening).unwrap();
+            assert_eq!(serde_json::from_str::<OpeningRule>(&json).unwrap(), opening);
+        }
+        assert_eq!(serde_json::to_string(&OpeningRule::Soosorv { fifth_offers: 3 }).unwrap(), "\"soosorv-3\"");
+        assert_eq!(serde_json::from_str::<OpeningRule>("\"soosorv\"").unwrap(), OpeningRule::soosorv());
+
+        // 人类执黑给出四个候选后才轮到白方选
+        let mut state = new_game(OpeningRule::Soosorv { fifth_offers: 4 }, Participant::Human);
+        place(&mut state, &[(7, 7), (7, 8), (8, 8)]);
+        choose_color(&mut state, ColorChoice::White).unwrap();
+        place(&mut state, &[(6, 6)]);
+        choose_color(&mut state, ColorChoice::Black).unwrap();
+        let offers = [(9, 9), (5, 9), (9, 6), (6, 9)];
+        for (i, &(row, col)) in offers.iter().enumerate() {
+            assert!(matches!(state.phase, GamePhase::OfferFifth { .. }), "第 {} 个候选之前", i + 1);
+            place(&mut state, &[(row, col)]);
+        }
+        assert_eq!(state.phase, GamePhase::SelectFifth { by: Participant::Ai, offers: offers.to_vec() });
+    }
+}
@@ -0,0 +1,140 @@
+// 着法排序 - 置换表着法最先，其次是战术着法（成五、挡五、成四、挡活三、成活三），再是杀手着法，最后是按历史表排序的普通着法
+// 战术着法从不截断；只有普通着法受搜索宽度限制，所以剪枝不会丢掉必须应对的着法
+
+use crate::candidates::Candidate;
+use crate::{Board, BLACK, MAX_CELL_COUNT};
+
+// 杀手着法记录的最大层数
+const MAX_PLY: usize = 64;
+
+// 历史分上限，超过后整体减半，避免早期的分数长期主导排序
+const HISTORY_LIMIT: u32 = 1 << 24;
+
+// 排序档次，数值大的排在前面
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum MoveClass {
+    Quiet,
+    Killer,
+    Tactical,
+    TtMove,
+}
+
+// 排好序的一个候选着法
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ScoredMove {
+    pub(crate) mv: (usize, usize),
+    pub(crate) class: MoveClass,
+    key: u32,
+}
+
+// 一个线程的杀手着法和历史表，整次搜索内保留
+pub(crate) struct MoveOrdering {
+    killers: [[Option<(usize, usize)>; 2]; MAX_PLY],
+    // history[方][格子]
+    history: Box<[[u32; MAX_CELL_COUNT]; 2]>,
+}
+
+impl MoveOrdering {
+    pub(crate) fn new() -> Self {
+        MoveOrdering {
+            killers: [[None; 2]; MAX_PLY],
+            history: Box::new([[0; MAX_CELL_COUNT]; 2]),
+        }
+    }
+
+    fn is_killer(&self, ply: usize, mv: (usize, usize)) -> bool {
+        ply < MAX_PLY && self.killers[ply].contains(&Some(mv))
+    }
+
+    // 候选着法按档次和分数从高到低排序
+    pub(crate) fn order(&self, candidates: &[Candidate], side: i32, ply: usize, tt_move: Option<(usize, usize)>) -> Vec<ScoredMove> {
+        let slot = if side == BLACK { 0 } else { 1 };
+        let mut moves: Vec<ScoredMove> = candidates
+            .iter()
+            .map(|candidate| {
+                let mv = candidate.pos;
+                let class = if Some(mv) == tt_move {
+                    MoveClass::TtMove
+                } else if candidate.tactical {
+                    MoveClass::Tactical
+                } else if self.is_killer(ply, mv) {
+                    MoveClass::Killer
+                } else {
+                    MoveClass::Quiet
+                };
+                let key = match class {
+                    MoveClass::Quiet => candidate.urgency.saturating_add(self.history[slot][Board::index(mv.0, mv.1)]),
+                    _ => candidate.urgency,
+                };
+                ScoredMove { mv, class, key }
+            })
+            .collect();
+        moves.sort_by_key(|m| std::cmp::Reverse((m.class, m.key)));
+        moves
+    }
+
+    // 普通着法引起剪枝：记为本层的杀手着法，并按深度平方加历史分
+    pub(crate) fn record_cutoff(&mut self, side: i32, ply: usize, depth: i32, mv: (usize, usize)) {
+        if ply < MAX_PLY && self.killers[ply][0] != Some(mv) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(mv);
+        }
+        let slot = if side == BLACK { 0 } else { 1 };
+        let entry = &mut self.history[slot][Board::index(mv.0, mv.1)];
+        *entry += (depth * depth) as u32;
+        if *entry > HISTORY_LIMIT {
+            self.history.iter_mut().flatten().for_each(|h| *h /= 2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::LineShape;
+    use crate::WHITE;
+
+    fn candidate(pos: (usize, usize), urgency: u32, tactical: bool) -> Candidate {
+        Candidate { pos, urgency, tactical, attack: LineShape::None }
+    }
+
+    fn order_of(moves: &[ScoredMove]) -> Vec<(usize, usize)> {
+        moves.iter().map(|m| m.mv).collect()
+    }
+
+    #[test]
+    fn tt_move_tactical_killer_then_quiet() {
+        let mut ordering = MoveOrdering::new();
+        ordering.record_cutoff(BLACK, 3, 1, (5, 5));
+        let candidates = [
+            candidate((1, 1), 900, false),
+            candidate((5, 5), 10, false),
+            candidate((2, 2), 5000, true),
+            candidate((3, 3), 20, false),
+        ];
+        let moves = ordering.order(&candidates, BLACK, 3, Some((3, 3)));
+        assert_eq!(order_of(&moves), [(3, 3), (2, 2), (5, 5), (1, 1)]);
+        let classes: Vec<MoveClass> = moves.iter().map(|m| m.class).collect();
+        assert_eq!(classes, [MoveClass::TtMove, MoveClass::Tactical, MoveClass::Killer, MoveClass::Quiet]);
+
+        // 杀手着法只在记录它的那一层有效
+        let moves = ordering.order(&candidates, BLACK, 4, None);
+        assert_eq!(order_of(&moves), [(2, 2), (1, 1), (3, 3), (5, 5)]);
+    }
+
+    #[test]
+    fn history_reorders_quiet_moves() {
+        let mut ordering = MoveOrdering::new();
+        let candidates = [candidate((1, 1), 100, false), candidate((4, 4), 50, false)];
+        assert_eq!(order_of(&ordering.order(&candidates, BLACK, 0, None)), [(1, 1), (4, 4)]);
+
+        // 在别的层剪枝，只留下历史分：深度 6 加 36 分还不够，再加一次就超过
+        ordering.record_cutoff(BLACK, 10, 6, (4, 4));
+        assert_eq!(order_of(&ordering.order(&candidates, BLACK, 0, None)), [(1, 1), (4, 4)]);
+        ordering.record_cutoff(BLACK, 10, 6, (4, 4));
+        assert_eq!(order_of(&ordering.order(&candidates, BLACK, 0, None)), [(4, 4), (1, 1)]);
+
+        // 历史表按行棋方分开
+        assert_eq!(order_of(&ordering.order(&candidates, WHITE, 0, None)), [(1, 1), (4, 4)]);
+    }
+}
@@ -0,0 +1,335 @@
+// 规则集 - 自由规则、标准规则（恰好五连）、连珠规则（黑棋禁手）、Caro规则（两端被堵的五连不算胜）
+// 连珠禁手：黑棋不能下三三、四四、长连，黑棋只有恰好五连才算胜；五连优先于禁手
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{Board, BLACK, DIRECTIONS, WIN_LENGTH};
+
+// 判定“活三是否为真活三”时递归检查禁手的最大层数
+const MAX_FORBIDDEN_RECURSION: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSet {
+    // 自由规则：五连及以上即胜
+    #[default]
+    Freestyle,
+    // 标准规则：双方都必须恰好五连，长连不算胜
+    Standard,
+    // 连珠规则：黑棋恰好五连且有禁手，白棋五连及以上即胜
+    Renju,
+    // Caro（越南）规则：五连及以上，但两端都被对方棋子堵住时不算胜；棋盘边缘不算堵
+    Caro,
+}
+
+impl RuleSet {
+    // player 沿一条线连成 len 子是否算胜
+    pub fn is_winning_run(&self, len: usize, player: i32) -> bool {
+        match self {
+            RuleSet::Freestyle => len >= WIN_LENGTH,
+            RuleSet::Standard => len == WIN_LENGTH,
+            RuleSet::Renju if player == BLACK => len == WIN_LENGTH,
+            RuleSet::Renju => len >= WIN_LENGTH,
+            RuleSet::Caro => len >= WIN_LENGTH,
+        }
+    }
+
+    // (row, col) 上的棋子沿 DIRECTIONS[dir] 是否构成胜利的五连
+    pub fn is_five_in_direction(&self, board: &Board, row: usize, col: usize, dir: usize, player: i32) -> bool {
+        if !self.is_winning_run(board.run_length(row, col, dir, player), player) {
+            return false;
+        }
+        if *self != RuleSet::Caro {
+            return true;
+        }
+        // Caro：连子两端的格子不能都是对方棋子
+        let (before, after) = run_bounds(board, row, col, dir, player);
+        let blocked = |k: i32| offset(board, row, col, dir, k).is_some_and(|(r, c)| board.get(r, c) == -player);
+        !(blocked(before - 1) && blocked(after + 1))
+    }
+
+    // 经过 (row, col) 的这条线上是否还有能按规则成五的五格窗口（窗口内只有己方棋子和空位）
+    // 评估时用它排除已经“死掉”的棋型；自由规则下不做额外限制
+    pub fn can_make_five(&self, board: &Board, row: usize, col: usize, dir: usize, player: i32) -> bool {
+        if *self == RuleSet::Freestyle {
+            return true;
+        }
+        let exact = *self == RuleSet::Standard || (*self == RuleSet::Renju && player == BLACK);
+        let cell = |k: i32| offset(board, row, col, dir, k).map(|(r, c)| board.get(r, c));
+
+        (-4..=0).any(|start| {
+            let open = (start..start + WIN_LENGTH as i32).all(|k| cell(k).is_some_and(|v| v != -player));
+            if !open {
+                return false;
+            }
+            let (outside_before, outside_after) = (cell(start - 1), cell(start + WIN_LENGTH as i32));
+            if exact {
+                // 窗口外紧邻己方棋子会成长连
+                outside_before != Some(player) && outside_after != Some(player)
+            } else if *self == RuleSet::Caro {
+                !(outside_before == Some(-player) && outside_after == Some(-player))
+            } else {
+                true
+            }
+        })
+    }
+
+    // player 在这个规则下是否受禁手限制
+    pub fn has_forbidden_moves(&self, player: i32) -> bool {
+        *self == RuleSet::Renju && player == BLACK
+    }
+
+    // player 在空位 (row, col) 落子是否为禁手
+    pub fn is_forbidden(&self, board: &mut Board, row: usize, col: usize, player: i32) -> bool {
+        self.has_forbidden_moves(player) && forbidden_at(board, row, col, 0)
+    }
+}
+
+impl FromStr for RuleSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "freestyle" | "free" => Ok(RuleSet::Freestyle),
+            "standard" | "exact5" => Ok(RuleSet::Standard),
+            "renju" => Ok(RuleSet::Renju),
+            "caro" => Ok(RuleSet::Caro),
+            _ => Err(format!("未知的规则: {}", s)),
+        }
+    }
+}
+
+// 禁手类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Forbidden {
+    DoubleThree, // 三三
+    DoubleFour,  // 四四
+    Overline,    // 长连
+}
+
+// 黑棋在空位 (row, col) 落子构成的禁手类型；不是禁手（或直接成五）时返回 None
+pub fn forbidden_kind(board: &mut Board, row: usize, col: usize) -> Option<Forbidden> {
+    forbidden_kind_at(board, row, col, 0)
+}
+
+fn forbidden_at(board: &mut Board, row: usize, col: usize, depth: usize) -> bool {
+    forbidden_kind_at(board, row, col, depth).is_some()
+}
+
+fn forbidden_kind_at(board: &mut Board, row: usize, col: usize, depth: usize) -> Option<Forbidden> {
+    // 三三、四四、长连都要求至少一条线上4格以内已有两颗以上黑子
+    let crowded = (0..DIRECTIONS.len()).any(|dir| {
+        (-4..=4)
+            .filter_map(|k| offset(board, row, col, dir, k).filter(|_| k != 0))
+            .filter(|&(r, c)| board.get(r, c) == BLACK)
+            .count()
+            >= 2
+    });
+    if !crowded {
+        return None;
+    }
+
+    board.make_move(row, col, BLACK);
+    let kind = classify_black_move(board, row, col, depth);
+    board.unmake_move(row, col);
+    kind
+}
+
+// (row, col) 上已落黑子，判断它构成的禁手
+fn classify_black_move(board: &mut Board, row: usize, col: usize, depth: usize) -> Option<Forbidden> {
+    let runs: Vec<usize> = (0..DIRECTIONS.len()).map(|dir| board.run_length(row, col, dir, BLACK)).collect();
+    // 恰好五连直接获胜，不算禁手
+    if runs.contains(&WIN_LENGTH) {
+        return None;
+    }
+    if runs.iter().any(|&len| len > WIN_LENGTH) {
+        return Some(Forbidden::Overline);
+    }
+
+    let fours: Vec<usize> = (0..DIRECTIONS.len()).map(|dir| count_fours(board, row, col, dir)).collect();
+    if fours.iter().sum::<usize>() >= 2 {
+        return Some(Forbidden::DoubleFour);
+    }
+
+    // 已经成四的线不再算三，四三不是禁手
+    let threes = (0..DIRECTIONS.len())
+        .filter(|&dir| fours[dir] == 0 && is_real_three(board, row, col, dir, depth))
+        .count();
+    if threes >= 2 {
+        return Some(Forbidden::DoubleThree);
+    }
+    None
+}
+
+// (row, col) 所在 player 连子两端相对 (row, col) 的偏移量（含端点）
+fn run_bounds(board: &Board, row: usize, col: usize, dir: usize, player: i32) -> (i32, i32) {
+    let extend = |step: i32| {
+        let mut k = 0;
+        while offset(board, row, col, dir, k + step).is_some_and(|(r, c)| board.get(r, c) == player) {
+            k += step;
+        }
+        k
+    };
+    (extend(-1), extend(1))
+}
+
+// 线上偏移 k 处的格子
+fn offset(board: &Board, row: usize, col: usize, dir: usize, k: i32) -> Option<(usize, usize)> {
+    let (dx, dy) = DIRECTIONS[dir];
+    let r = row as i32 + dx * k;
+    let c = col as i32 + dy * k;
+    board.contains(r, c).then_some((r as usize, c as usize))
+}
+
+// 经过 (row, col) 黑子的这条线上，能让黑棋恰好成五且五连包含 (row, col) 的点的偏移量
+fn black_five_offsets(board: &mut Board, row: usize, col: usize, dir: usize) -> Vec<i32> {
+    let mut offsets = Vec::new();
+    for k in -4..=4 {
+        let Some((r, c)) = offset(board, row, col, dir, k) else { continue };
+        if k == 0 || !board.is_empty_at(r, c) {
+            continue;
+        }
+        board.make_move(r, c, BLACK);
+        // 新子与 (row, col) 之间全是黑子、且整段恰好五连，五连才包含 (row, col)
+        let connected = (1..k.abs()).all(|i| {
+            offset(board, row, col, dir, i * k.signum()).is_some_and(|(rr, cc)| board.get(rr, cc) == BLACK)
+        });
+        let five = connected && board.run_length(r, c, dir, BLACK) == WIN_LENGTH;
+        board.unmake_move(r, c);
+        if five {
+            offsets.push(k);
+        }
+    }
+    offsets
+}
+
+// 这条线上包含 (row, col) 的四的个数；活四（两端成五点相距5格）只算一个
+fn count_fours(board: &mut Board, row: usize, col: usize, dir: usize) -> usize {
+    let offsets = black_five_offsets(board, row, col, dir);
+    if offsets.len() == 2 && (offsets[0] - offsets[1]).abs() == WIN_LENGTH as i32 {
+        1
+    } else {
+        offsets.len()
+    }
+}
+
+// 这条线上是否有包含 (row, col) 的真活三：存在一个不是禁手的点，落下后形成活四
+fn is_real_three(board: &mut Board, row: usize, col: usize, dir: usize, depth: usize) -> bool {
+    for k in -4..=4 {
+        let Some((r, c)) = offset(board, row, col, dir, k) else { continue };
+        if k == 0 || !board.is_empty_at(r, c) {
+            continue;
+        }
+        board.make_move(r, c, BLACK);
+        let offsets = black_five_offsets(board, row, col, dir);
+        let straight_four = offsets.len() == 2 && (offsets[0] - offsets[1]).abs() == WIN_LENGTH as i32;
+        board.unmake_move(r, c);
+
+        // 成活四的点本身是禁手时，这个三不是真活三
+        if straight_four && (depth >= MAX_FORBIDDEN_RECURSION || !forbidden_at(board, r, c, depth + 1)) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::test_util::board_with;
+    use crate::threat::five_points;
+    use crate::{check_win_with_rule, WHITE};
+
+    #[test]
+    fn four_three_is_allowed() {
+        let mut board = board_with(&[(7, 4), (7, 5), (7, 6), (8, 7), (9, 7)], &[]);
+        assert_eq!(forbidden_kind(&mut board, 7, 7), None);
+        // 线上多一颗无关的黑子，四仍然不算三
+        board.make_move(7, 10, BLACK);
+        assert_eq!(forbidden_kind(&mut board, 7, 7), None);
+    }
+
+    #[test]
+    fn double_three_is_forbidden() {
+        let mut board = board_with(&[(7, 5), (7, 6), (5, 7), (6, 7)], &[]);
+        assert_eq!(forbidden_kind(&mut board, 7, 7), Some(Forbidden::DoubleThree));
+        assert!(RuleSet::Renju.is_forbidden(&mut board, 7, 7, BLACK));
+        assert!(!RuleSet::Renju.is_forbidden(&mut board, 7, 7, WHITE));
+        assert!(!RuleSet::Freestyle.is_forbidden(&mut board, 7, 7, BLACK));
+    }
+
+    #[test]
+    fn blocked_three_does_not_count() {
+        let mut board = board_with(&[(7, 5), (7, 6), (5, 7), (6, 7)], &[(7, 4), (7, 8)]);
+        assert_eq!(forbidden_kind(&mut board, 7, 7), None);
+    }
+
+    #[test]
+    fn double_four_is_forbidden() {
+        let mut board = board_with(&[(7, 4), (7, 5), (7, 6), (4, 7), (5, 7), (6, 7)], &[]);
+        assert_eq!(forbidden_kind(&mut board, 7, 7), Some(Forbidden::DoubleFour));
+    }
+
+    #[test]
+    fn overline_is_forbidden_but_exact_five_wins() {
+        let mut board = board_with(&[(7, 2), (7, 3), (7, 4), (7, 6), (7, 7)], &[]);
+        assert_eq!(forbidden_kind(&mut board, 7, 5), Some(Forbidden::Overline));
+
+        // 五连优先于同时形成的三三
+        let mut board = board_with(&[(7, 3), (7, 4), (7, 5), (7, 6), (8, 7), (9, 7), (8, 8), (9, 9)], &[]);
+        assert_eq!(forbidden_kind(&mut board, 7, 7), None);
+        board.make_move(7, 7, BLACK);
+        assert!(check_win_with_rule(&board, 7, 7, RuleSet::Renju));
+    }
+
+    #[test]
+    fn caro_five_needs_an_open_end() {
+        // 两端都被对方堵住不算胜
+        let board = board_with(&[(7, 3), (7, 4), (7, 5), (7, 6), (7, 7)], &[(7, 2), (7, 8)]);
+        assert!(!check_win_with_rule(&board, 7, 5, RuleSet::Caro));
+        assert!(check_win_with_rule(&board, 7, 5, RuleSet::Freestyle));
+
+        // 棋盘边缘不算堵
+        let board = board_with(&[(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)], &[(0, 5)]);
+        assert!(check_win_with_rule(&board, 0, 2, RuleSet::Caro));
+        assert!(RuleSet::Caro.can_make_five(&board, 0, 2, 0, BLACK));
+    }
+
+    #[test]
+    fn standard_rule_needs_exactly_five() {
+        for player in [BLACK, WHITE] {
+            let stones = [(7, 2), (7, 3), (7, 4), (7, 5), (7, 6), (7, 7)];
+            let board = if player == BLACK { board_with(&stones, &[]) } else { board_with(&[], &stones) };
+            assert!(!check_win_with_rule(&board, 7, 4, RuleSet::Standard));
+            assert!(check_win_with_rule(&board, 7, 4, RuleSet::Freestyle));
+            assert_eq!(check_win_with_rule(&board, 7, 4, RuleSet::Renju), player == WHITE);
+            assert!(check_win_with_rule(&board, 7, 4, RuleSet::Caro));
+        }
+
+        // 再落一子就成长连的窗口对标准规则是死的
+        let board = board_with(&[(7, 3), (7, 4), (7, 5), (7, 8)], &[(7, 2)]);
+        assert!(!RuleSet::Standard.can_make_five(&board, 7, 4, 0, BLACK));
+        assert!(RuleSet::Freestyle.can_make_five(&board, 7, 4, 0, BLACK));
+    }
+
+    #[test]
+    fn caro_five_points_at_the_edge() {
+        // 一端是棋盘边缘、另一端是对方棋子，中间的空点仍是成五点
+        let mut board = board_with(&[(0, 5)], &[(0, 0), (0, 1), (0, 3), (0, 4)]);
+        assert_eq!(five_points(&mut board, WHITE, RuleSet::Caro), [(0, 2)]);
+
+        // 两端都被对方堵住时没有成五点
+        let mut board = board_with(&[(7, 2), (7, 8)], &[(7, 3), (7, 4), (7, 6), (7, 7)]);
+        assert!(five_points(&mut board, WHITE, RuleSet::Caro).is_empty());
+        assert_eq!(five_points(&mut board, WHITE, RuleSet::Standard), [(7, 5)]);
+    }
+
+    #[test]
+    fn parses_rule_names() {
+        for (name, rule) in [("free", RuleSet::Freestyle), ("Standard", RuleSet::Standard), ("exact5", RuleSet::Standard), ("RENJU", RuleSet::Renju), ("caro", RuleSet::Caro)] {
+            assert_eq!(name.parse::<RuleSet>(), Ok(rule));
+        }
+        assert!("gomoku".parse::<RuleSet>().is_err());
+    }
+}
@@ -0,0 +1,1025 @@
+// 迭代加深搜索 - 依次搜索深度1、2、3...，时间或节点预算用完时返回最后一次完整迭代的最佳着法
+// 内部是以行棋方视角计分的 Alpha-Beta（negamax）搜索，配合置换表使用；局面分由增量评估器随落子更新
+// 候选着法来自 candidates::generate，按置换表着法、战术紧迫度、杀手着法和历史表排序，战术着法不受搜索宽度限制
+// 剪枝增强（可分别开关）：主要变化搜索（PVS）、根节点渴望窗口、后期普通着法减少深度（LMR）
+// 到达名义深度后接静态搜索：只走成四、挡四（可选活三及其应对）等强制着法，直到局面平静或达到层数上限，避免地平线效应
+// 多线程时采用 Lazy SMP：辅助线程在同一个置换表上各自迭代加深，结果取主线程的
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tt::{Bound, TranspositionTable};
+use crate::candidates::{self, CandidateKind};
+use crate::eval::LineShape;
+use crate::eval::Evaluator;
+use crate::ordering::{MoveClass, MoveOrdering};
+use crate::weights::EvalWeights;
+use crate::{check_win_with_rule, zobrist, Board, RuleSet, MAX_DEPTH, WIN_SCORE};
+
+// 超过该值的分数视为必胜/必败分数（WIN_SCORE - 步数）
+pub const MATE_BOUND: i32 = WIN_SCORE - 1000;
+
+// 每搜索多少个节点检查一次时间
+const CHECK_INTERVAL: u64 = 1024;
+
+// 默认每个节点搜索的候选数量
+pub const DEFAULT_SEARCH_WIDTH: usize = 12;
+
+// 剩余深度不少于该值才做LMR
+const LMR_MIN_DEPTH: i32 = 3;
+
+// 每个节点前几个着法不减深度
+const LMR_FULL_MOVES: usize = 3;
+
+// LMR减少的层数；取偶数，保证叶子节点的行棋方不变，五子棋的静态评估对行棋方很敏感
+const LMR_REDUCTION: i32 = 2;
+
+// 渴望窗口失败后窗口扩大的倍数
+const ASPIRATION_GROWTH: i32 = 4;
+
+// 搜索预算，0 表示不限制
+#[derive(Debug, Clone, Copy)]
+pub struct SearchLimits {
+    pub max_depth: i32,
+    pub time_limit_ms: u64,
+    pub max_nodes: u64,
+    // 每个节点最多搜索的候选着法数；战术着法总会搜索，不计入限制
+    pub width: usize,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            max_depth: MAX_DEPTH,
+            time_limit_ms: 0,
+            max_nodes: 0,
+            width: DEFAULT_SEARCH_WIDTH,
+        }
+    }
+}
+
+impl SearchLimits {
+    pub fn depth(max_depth: i32) -> Self {
+        SearchLimits { max_depth, ..Default::default() }
+    }
+
+    pub fn time(time_limit_ms: u64) -> Self {
+        SearchLimits { time_limit_ms, ..Default::default() }
+    }
+}
+
+// 搜索增强的开关，默认全部开启；用来比较各项的效果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFeatures {
+    // 主要变化搜索：每个节点第一个着法之后先用零宽窗口试探，超出 alpha 才完整重搜
+    pub pvs: bool,
+    // 根节点以上一轮分数为中心的渴望窗口半宽，0 表示关闭；失败时逐步扩大
+    pub aspiration_window: i32,
+    // 后期着法减少深度：排序靠后的普通着法少搜一到两层，超出 alpha 时按原深度重搜
+    pub lmr: bool,
+    // 名义深度之外静态搜索的最大层数，0 表示关闭（叶子直接取静态评估）
+    pub quiescence_plies: i32,
+    // 静态搜索是否也延伸活三：己方成活三、对方有活三时必须应对
+    // 默认关闭：同样深度下节点数多出几十倍，迭代加深浅得多；活三连攻由搜索前的 VCT 求解器负责
+    pub quiescence_threes: bool,
+}
+
+impl Default for SearchFeatures {
+    fn default() -> Self {
+        SearchFeatures {
+            pvs: true,
+            aspiration_window: 300,
+            lmr: true,
+            quiescence_plies: 8,
+            quiescence_threes: false,
+        }
+    }
+}
+
+impl SearchFeatures {
+    // 全部关闭，即普通的全窗口 Alpha-Beta
+    pub fn none() -> Self {
+        SearchFeatures {
+            pvs: false,
+            aspiration_window: 0,
+            lmr: false,
+            quiescence_plies: 0,
+            quiescence_threes: false,
+        }
+    }
+}
+
+// 迭代加深的结果
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub best_move: Option<(usize, usize)>,
+    pub score: i32,
+    // 最后一次完整完成的迭代深度
+    pub depth: i32,
+    pub nodes: u64,
+    // Alpha-Beta 剪枝次数
+    pub cutoffs: u64,
+    // 置换表命中可直接返回的次数
+    pub tt_hits: u64,
+    pub elapsed_ms: u64,
+    // 主要变化（从根节点起双方的预期着法）
+    pub pv: Vec<(usize, usize)>,
+    // 多主变模式下按分数从高到低的各条变化，第一条与 best_move/score/pv 相同
+    pub lines: Vec<PvLine>,
+}
+
+// 一个候选着法及其分数和主要变化
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PvLine {
+    pub row: usize,
+    pub col: usize,
+    pub score: i32,
+    pub pv: Vec<(usize, usize)>,
+}
+
+// 毫秒计时器；wasm32-unknown-unknown 上 std::time::Instant 不可用，改用 Date.now()
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Timer {
+    start: f64,
+}
+
+impl Timer {
+    pub(crate) fn start() -> Self {
+        Timer { start: now_ms() }
+    }
+
+    pub(crate) fn elapsed_ms(&self) -> u64 {
+        (now_ms() - self.start).max(0.0) as u64
+    }
+
+    // 已经走了 ms 毫秒的计时器，测试时间预算的分配用
+    #[cfg(test)]
+    pub(crate) fn started_ms_ago(ms: u64) -> Self {
+        Timer { start: now_ms() - ms as f64 }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+// 置换表中的必胜分数按“距当前节点的步数”存储，读写时换算
+pub(crate) fn score_to_tt(score: i32, ply: i32) -> i32 {
+    if score > MATE_BOUND {
+        score + ply
+    } else if score < -MATE_BOUND {
+        score - ply
+    } else {
+        score
+    }
+}
+
+pub(crate) fn score_from_tt(score: i32, ply: i32) -> i32 {
+    if score > MATE_BOUND {
+        score - ply
+    } else if score < -MATE_BOUND {
+        score + ply
+    } else {
+        score
+    }
+}
+
+// 按搜索窗口确定存入置换表的界类型
+pub(crate) fn bound_for(score: i32, alpha: i32, beta: i32) -> Bound {
+    if score <= alpha {
+        Bound::Upper
+    } else if score >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    }
+}
+
+// 静态评估，压到必胜分数区间以内，避免和真正的胜负分数混淆
+fn static_eval(pos: &Evaluator, side: i32) -> i32 {
+    pos.score(side).clamp(-MATE_BOUND + 1, MATE_BOUND - 1)
+}
+
+// 每完成一轮迭代调用一次，分析界面用来实时显示深度、分数和主要变化
+pub type IterationHook<'a> = &'a mut dyn FnMut(&SearchResult);
+
+pub struct Searcher<'a> {
+    tt: &'a TranspositionTable,
+    limits: SearchLimits,
+    rule: RuleSet,
+    timer: Timer,
+    nodes: u64,
+    cutoffs: u64,
+    tt_hits: u64,
+    stopped: bool,
+    // 根节点不考虑的着法
+    excluded: Vec<(usize, usize)>,
+    // 根节点给出几条互不相同的最佳变化
+    multi_pv: usize,
+    // 搜索线程数（含主线程）
+    threads: usize,
+    // 迭代加深的起始深度，辅助线程错开深度以减少重复工作
+    first_depth: i32,
+    // 辅助线程的停止信号，主线程搜完后置位
+    stop_signal: Option<&'a AtomicBool>,
+    weights: EvalWeights,
+    features: SearchFeatures,
+    ordering: MoveOrdering,
+    on_iteration: Option<IterationHook<'a>>,
+}
+
+impl<'a> Searcher<'a> {
+    pub fn new(tt: &'a TranspositionTable, limits: SearchLimits, rule: RuleSet) -> Self {
+        Searcher {
+            tt,
+            limits,
+            rule,
+            timer: Timer::start(),
+            nodes: 0,
+            cutoffs: 0,
+            tt_hits: 0,
+            stopped: false,
+            excluded: Vec::new(),
+            multi_pv: 1,
+            threads: 1,
+            first_depth: 1,
+            stop_signal: None,
+            weights: EvalWeights::default(),
+            features: SearchFeatures::default(),
+            ordering: MoveOrdering::new(),
+            on_iteration: None,
+        }
+    }
+
+    // 根节点排除这些着法
+    pub fn exclude_root_moves(mut self, moves: &[(usize, usize)]) -> Self {
+        self.excluded = moves.to_vec();
+        self
+    }
+
+    // 每轮迭代给出前 n 个不同根着法的分数和主要变化
+    pub fn multi_pv(mut self, n: usize) -> Self {
+        self.multi_pv = n.max(1);
+        self
+    }
+
+    // 每完成一轮迭代回调一次
+    pub fn on_iteration(mut self, hook: IterationHook<'a>) -> Self {
+        self.on_iteration = Some(hook);
+        self
+    }
+
+    // 叶子节点评估用的权重
+    pub fn weights(mut self, weights: EvalWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    // PVS、渴望窗口、LMR和静态搜索的开关
+    pub fn features(mut self, features: SearchFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    // 搜索线程数；wasm32 上没有线程，始终单线程
+    pub fn threads(mut self, n: usize) -> Self {
+        self.threads = n.max(1);
+        self
+    }
+
+    // 迭代加深；多线程时另起 threads - 1 个辅助线程共享置换表，统计数据累加所有线程
+    pub fn iterative_deepening(&mut self, board: &Board, side: i32) -> SearchResult {
+        self.tt.new_search();
+        if self.threads <= 1 || cfg!(target_arch = "wasm32") {
+            return self.deepen(board, side);
+        }
+
+        let stop = AtomicBool::new(false);
+        let (tt, limits, rule, weights, features) = (self.tt, self.limits, self.rule, self.weights, self.features);
+        let excluded = self.excluded.clone();
+        let excluded = &excluded;
+        std::thread::scope(|scope| {
+            let helpers: Vec<_> = (1..self.threads)
+                .map(|id| {
+                    let stop = &stop;
+                    scope.spawn(move || {
+                        let mut helper = Searcher::new(tt, limits, rule).exclude_root_moves(excluded).weights(weights).features(features);
+                        helper.first_depth = 1 + (id % 2) as i32;
+                        helper.stop_signal = Some(stop);
+                        helper.deepen(board, side);
+                        (helper.nodes, helper.cutoffs, helper.tt_hits)
+                    })
+                })
+                .collect();
+
+            let mut result = self.deepen(board, side);
+            stop.store(true, Ordering::Relaxed);
+            for helper in helpers {
+                let (nodes, cutoffs, tt_hits) = helper.join().expect("辅助搜索线程异常退出");
+                result.nodes += nodes;
+                result.cutoffs += cutoffs;
+                result.tt_hits += tt_hits;
+            }
+            result
+        })
+    }
+
+    // 单个线程的迭代加深主循环
+    fn deepen(&mut self, board: &Board, side: i32) -> SearchResult {
+        self.timer = Timer::start();
+        self.nodes = 0;
+        self.cutoffs = 0;
+        self.tt_hits = 0;
+        self.stopped = false;
+        self.ordering = MoveOrdering::new();
+
+        let mut pos = Evaluator::with_weights(board, self.rule, self.weights);
+        let mut root_moves = self.root_moves(&pos, side);
+
+        let mut result = SearchResult {
+            best_move: root_moves.first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            cutoffs: 0,
+            tt_hits: 0,
+            elapsed_ms: 0,
+            pv: Vec::new(),
+            lines: Vec::new(),
+        };
+
+        // 静态评估偏向行棋方，奇偶深度的分数差别很大，渴望窗口以同奇偶的上一轮分数为中心
+        let mut parity_scores = [None; 2];
+        for depth in self.first_depth..=self.limits.max_depth.max(1) {
+            let previous = parity_scores[(depth % 2) as usize];
+            let Some(lines) = self.search_lines(&mut pos, depth, side, &mut root_moves, previous) else {
+                break; // 本轮被打断，沿用上一轮结果
+            };
+            let best = &lines[0];
+            let score = best.score;
+            parity_scores[(depth % 2) as usize] = Some(score);
+            result.best_move = Some((best.row, best.col));
+            result.score = score;
+            result.pv = best.pv.clone();
+            result.lines = lines;
+            result.depth = depth;
+            result.nodes = self.nodes;
+            result.cutoffs = self.cutoffs;
+            result.tt_hits = self.tt_hits;
+            result.elapsed_ms = self.timer.elapsed_ms();
+            if let Some(hook) = self.on_iteration.as_mut() {
+                hook(&result);
+            }
+
+            // 已找到必胜/必败，或者剩余时间不够再完成一轮
+            if score.abs() > MATE_BOUND || self.should_not_deepen() {
+                break;
+            }
+        }
+
+        result.nodes = self.nodes;
+        result.cutoffs = self.cutoffs;
+        result.tt_hits = self.tt_hits;
+        result.elapsed_ms = self.timer.elapsed_ms();
+        result
+    }
+
+    // 下一轮通常比已用时间长得多，用掉一半预算后就不再开新一轮
+    fn should_not_deepen(&self) -> bool {
+        let time_up = self.limits.time_limit_ms > 0 && self.timer.elapsed_ms() * 2 >= self.limits.time_limit_ms;
+        let nodes_up = self.limits.max_nodes > 0 && self.nodes * 2 >= self.limits.max_nodes;
+        time_up || nodes_up
+    }
+
+    fn check_limits(&mut self) {
+        if self.limits.time_limit_ms > 0 && self.timer.elapsed_ms() >= self.limits.time_limit_ms {
+            self.stopped = true;
+        }
+        if self.limits.max_nodes > 0 && self.nodes >= self.limits.max_nodes {
+            self.stopped = true;
+        }
+        if self.stop_signal.is_some_and(|stop| stop.load(Ordering::Relaxed)) {
+            self.stopped = true;
+        }
+    }
+
+    // 根节点候选：排除指定着法和禁手，战术着法全部保留，普通着法补足到搜索宽度
+    // 被迫应对的着法全被排除或都是禁手时改从周围空位中选，周围也没有可走的点时退回到其它空位
+    fn root_moves(&self, pos: &Evaluator, side: i32) -> Vec<(usize, usize)> {
+        let tt_move = self.tt.probe(zobrist::position_key(pos.board().hash(), side)).and_then(|entry| entry.best_move);
+        let mut scratch = *pos.board();
+        let mut legal = |(row, col): (usize, usize)| !self.excluded.contains(&(row, col)) && !self.rule.is_forbidden(&mut scratch, row, col, side);
+        let mut candidates = candidates::generate(pos, side);
+        if candidates.is_forced() && !candidates.moves.iter().any(|m| legal(m.pos)) {
+            candidates.kind = CandidateKind::Neighborhood;
+            candidates.moves = candidates::neighborhood(pos, side);
+        }
+        let width = self.limits.width.max(1);
+        let mut moves = Vec::new();
+        let mut quiet = 0;
+        for scored in self.ordering.order(&candidates.moves, side, 0, tt_move) {
+            if scored.class == MoveClass::Quiet && quiet >= width {
+                break;
+            }
+            if legal(scored.mv) {
+                quiet += usize::from(scored.class == MoveClass::Quiet);
+                moves.push(scored.mv);
+            }
+        }
+        if moves.is_empty() {
+            moves.extend(pos.board().empty_cells().iter().map(Board::coords).filter(|&mv| legal(mv)).take(width));
+        }
+        moves
+    }
+
+    // 一轮迭代：依次排除已选出的根着法，得到 multi_pv 条变化；被打断或无着法时返回 None
+    // previous 为同奇偶深度上一轮的分数，单主变时用来设置渴望窗口
+    fn search_lines(&mut self, pos: &mut Evaluator, depth: i32, side: i32, root_moves: &mut [(usize, usize)], previous: Option<i32>) -> Option<Vec<PvLine>> {
+        let mut lines: Vec<PvLine> = Vec::new();
+        let mut chosen = Vec::new();
+        while lines.len() < self.multi_pv.min(root_moves.len()) {
+            let line = match previous {
+                Some(score) if self.multi_pv == 1 && self.features.aspiration_window > 0 && score.abs() <= MATE_BOUND => {
+                    self.search_aspiration(pos, depth, side, root_moves, score)?
+                }
+                _ => self.search_root(pos, depth, side, root_moves, &chosen, -WIN_SCORE, WIN_SCORE)?,
+            };
+            if lines.is_empty() {
+                let key = zobrist::position_key(pos.board().hash(), side);
+                self.tt.store(key, depth, score_to_tt(line.score, 0), Bound::Exact, Some((line.row, line.col)));
+            }
+            chosen.push((line.row, line.col));
+            lines.push(line);
+        }
+        // 本轮排出的名次作为下一轮的根着法顺序
+        for (i, mv) in chosen.iter().enumerate() {
+            if let Some(pos) = root_moves.iter().position(|m| m == mv) {
+                root_moves[i..=pos].rotate_right(1);
+            }
+        }
+        (!lines.is_empty()).then_some(lines)
+    }
+
+    // 以 previous 为中心的渴望窗口搜索根节点，分数落在窗口外时朝失败的一侧扩大窗口重搜
+    fn search_aspiration(&mut self, pos: &mut Evaluator, depth: i32, side: i32, root_moves: &[(usize, usize)], previous: i32) -> Option<PvLine> {
+        let mut delta = self.features.aspiration_window;
+        let (mut alpha, mut beta) = (previous - delta, previous + delta);
+        loop {
+            let line = self.search_root(pos, depth, side, root_moves, &[], alpha, beta)?;
+            delta = delta.saturating_mul(ASPIRATION_GROWTH);
+            if line.score <= alpha && alpha > -WIN_SCORE {
+                alpha = if line.score.abs() > MATE_BOUND { -WIN_SCORE } else { (previous - delta).max(-WIN_SCORE) };
+            } else if line.score >= beta && beta < WIN_SCORE {
+                beta = if line.score.abs() > MATE_BOUND { WIN_SCORE } else { (previous + delta).min(WIN_SCORE) };
+            } else {
+                return Some(line);
+            }
+        }
+    }
+
+    // 根节点在 (alpha, beta) 窗口内搜索（跳过 skip 中的着法），上一轮的最佳着法排在最前；被打断时返回 None
+    #[allow(clippy::too_many_arguments)]
+    fn search_root(
+        &mut self,
+        pos: &mut Evaluator,
+        depth: i32,
+        side: i32,
+        root_moves: &[(usize, usize)],
+        skip: &[(usize, usize)],
+        mut alpha: i32,
+        beta: i32,
+    ) -> Option<PvLine> {
+        let mut best: Option<PvLine> = None;
+        let mut child_pv = Vec::new();
+
+        for &(row, col) in root_moves {
+            if skip.contains(&(row, col)) {
+                continue;
+            }
+            pos.make_move(row, col, side);
+            let score = if check_win_with_rule(pos.board(), row, col, self.rule) {
+                child_pv.clear();
+                WIN_SCORE - 1
+            } else {
+                self.search_child(pos, depth, 0, alpha, beta, side, best.is_none(), 0, &mut child_pv)
+            };
+            pos.unmake_move(row, col);
+
+            if self.stopped {
+                return None;
+            }
+            if best.is_none() || score > alpha {
+                alpha = alpha.max(score);
+                let mut pv = vec![(row, col)];
+                pv.extend_from_slice(&child_pv);
+                best = Some(PvLine { row, col, score, pv });
+            }
+            if alpha >= beta {
+                break; // 渴望窗口失败（高），由调用方扩大窗口重搜
+            }
+        }
+
+        // 置换表截断的变化用表里的着法补全
+        best.map(|mut line| {
+            line.pv = principal_variation(self.tt, pos.board(), side, self.rule, depth as usize, &line.pv);
+            line
+        })
+    }
+
+    // side 已在 ply 层落子后搜索子节点，返回 side 视角的分数；first 为该节点的第一个着法
+    // 其余着法先按 reduction 减少深度、用零宽窗口（PVS 开启时）试探，超出 alpha 再逐步恢复深度和窗口重搜
+    #[allow(clippy::too_many_arguments)]
+    fn search_child(&mut self, pos: &mut Evaluator, depth: i32, ply: i32, alpha: i32, beta: i32, side: i32, first: bool, reduction: i32, pv: &mut Vec<(usize, usize)>) -> i32 {
+        if first {
+            return -self.alpha_beta(pos, depth - 1, ply + 1, -beta, -alpha, -side, pv);
+        }
+        let probe_beta = if self.features.pvs { alpha + 1 } else { beta };
+        let mut score = -self.alpha_beta(pos, depth - 1 - reduction, ply + 1, -probe_beta, -alpha, -side, pv);
+        if reduction > 0 && score > alpha {
+            score = -self.alpha_beta(pos, depth - 1, ply + 1, -probe_beta, -alpha, -side, pv);
+        }
+        if probe_beta < beta && score > alpha && score < beta {
+            score = -self.alpha_beta(pos, depth - 1, ply + 1, -beta, -alpha, -side, pv);
+        }
+        score
+    }
+
+    // Alpha-Beta（negamax形式），分数以 side 视角计算；pv 返回分数落在窗口内时的主要变化
+    #[allow(clippy::too_many_arguments)]
+    fn alpha_beta(&mut self, pos: &mut Evaluator, depth: i32, ply: i32, mut alpha: i32, beta: i32, side: i32, pv: &mut Vec<(usize, usize)>) -> i32 {
+        pv.clear();
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.check_limits();
+        }
+        if self.stopped {
+            return 0;
+        }
+
+        let key = zobrist::position_key(pos.board().hash(), side);
+        let mut tt_move = None;
+        if let Some(mut entry) = self.tt.probe(key) {
+            entry.score = score_from_tt(entry.score, ply);
+            if let Some(score) = entry.cutoff(depth, alpha, beta) {
+                self.tt_hits += 1;
+                return score;
+            }
+            tt_move = entry.best_move;
+        }
+
+        if depth <= 0 {
+            return self.quiescence(pos, ply, 0, alpha, beta, side, pv);
+        }
+
+        let candidates = candidates::generate(pos, side);
+        if candidates.moves.is_empty() {
+            return 0; // 棋盘下满，和棋
+        }
+        let moves = self.ordering.order(&candidates.moves, side, ply as usize, tt_move);
+        // 只有需要判断禁手时才复制棋盘
+        let mut scratch = self.rule.has_forbidden_moves(side).then(|| *pos.board());
+
+        let alpha_orig = alpha;
+        let mut best_score = -WIN_SCORE;
+        let mut best_move = None;
+        let mut child_pv = Vec::new();
+        let mut searched = 0;
+        // 只有普通着法计入宽度，战术着法和杀手着法不占名额
+        let mut quiet = 0;
+
+        for scored in moves {
+            let (row, col) = scored.mv;
+            if scored.class == MoveClass::Quiet && quiet >= self.limits.width.max(1) {
+                break;
+            }
+            if let Some(board) = scratch.as_mut() {
+                if self.rule.is_forbidden(board, row, col, side) {
+                    continue;
+                }
+            }
+            searched += 1;
+            quiet += usize::from(scored.class == MoveClass::Quiet);
+            pos.make_move(row, col, side);
+
+            // 快速获胜检查
+            if check_win_with_rule(pos.board(), row, col, self.rule) {
+                pos.unmake_move(row, col);
+                let score = WIN_SCORE - ply - 1;
+                self.tt.store(key, depth, score_to_tt(score, ply), Bound::Exact, Some((row, col)));
+                pv.push((row, col));
+                return score;
+            }
+
+            let reduction = if self.features.lmr && depth >= LMR_MIN_DEPTH && searched > LMR_FULL_MOVES && scored.class == MoveClass::Quiet {
+                LMR_REDUCTION
+            } else {
+                0
+            };
+            let score = self.search_child(pos, depth, ply, alpha, beta, side, searched == 1, reduction, &mut child_pv);
+            pos.unmake_move(row, col);
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some((row, col));
+                if score > alpha {
+                    pv.clear();
+                    pv.push((row, col));
+                    pv.extend_from_slice(&child_pv);
+                }
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                self.cutoffs += 1;
+                if scored.class <= MoveClass::Killer {
+                    self.ordering.record_cutoff(side, ply as usize, depth, (row, col));
+                }
+                break; // Alpha-Beta剪枝
+            }
+        }
+        if best_move.is_none() {
+            // 应对点全是禁手就输了；平静局面里附近全是禁手则按和棋处理
+            return if candidates.is_forced() { -WIN_SCORE + ply } else { 0 };
+        }
+
+        self.tt.store(key, depth, score_to_tt(best_score, ply), bound_for(best_score, alpha_orig, beta), best_move);
+        best_score
+    }
+
+    // 静态搜索，qply 为越过名义深度的层数；不读写置换表
+    // 局面平静时行棋方可以不走（取静态评估），也可以冲四（开启活三延伸时还有成活三）；对方有四（或活三）时只能应对
+    #[allow(clippy::too_many_arguments)]
+    fn quiescence(&mut self, pos: &mut Evaluator, ply: i32, qply: i32, mut alpha: i32, beta: i32, side: i32, pv: &mut Vec<(usize, usize)>) -> i32 {
+        pv.clear();
+        if qply >= self.features.quiescence_plies {
+            return static_eval(pos, side);
+        }
+        if qply > 0 {
+            self.nodes += 1;
+            if self.nodes.is_multiple_of(CHECK_INTERVAL) {
+                self.check_limits();
+            }
+            if self.stopped {
+                return 0;
+            }
+        }
+
+        let candidates = candidates::generate(pos, side);
+        if candidates.moves.is_empty() {
+            return 0; // 棋盘下满，和棋
+        }
+        let threes = self.features.quiescence_threes;
+        let forced = match candidates.kind {
+            CandidateKind::Win => {
+                pv.push(candidates.moves[0].pos);
+                return WIN_SCORE - ply - 1;
+            }
+            CandidateKind::BlockFour => true,
+            CandidateKind::AnswerThree => threes,
+            CandidateKind::Neighborhood => false,
+        };
+        let strongest = if threes { LineShape::LiveThree } else { LineShape::RushFour };
+
+        let mut best_score = -WIN_SCORE;
+        if !forced {
+            best_score = static_eval(pos, side);
+            if best_score >= beta {
+                return best_score;
+            }
+            alpha = alpha.max(best_score);
+        }
+
+        let mut scratch = self.rule.has_forbidden_moves(side).then(|| *pos.board());
+        let mut searched = false;
+        let mut child_pv = Vec::new();
+        for candidate in &candidates.moves {
+            if !forced && candidate.attack > strongest {
+                continue;
+            }
+            let (row, col) = candidate.pos;
+            if let Some(board) = scratch.as_mut() {
+                if self.rule.is_forbidden(board, row, col, side) {
+                    continue;
+                }
+            }
+            searched = true;
+            pos.make_move(row, col, side);
+            let score = if check_win_with_rule(pos.board(), row, col, self.rule) {
+                child_pv.clear();
+                WIN_SCORE - ply - 1
+            } else {
+                -self.quiescence(pos, ply + 1, qply + 1, -beta, -alpha, -side, &mut child_pv)
+            };
+            pos.unmake_move(row, col);
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    pv.clear();
+                    pv.push((row, col));
+                    pv.extend_from_slice(&child_pv);
+                }
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        if forced && !searched {
+            return -WIN_SCORE + ply; // 应对点全是禁手
+        }
+        best_score
+    }
+}
+
+// 先走完 prefix，再沿置换表里的最佳着法补全主要变化，遇到空缺、非法着法或胜局时停止
+pub fn principal_variation(tt: &TranspositionTable, board: &Board, side: i32, rule: RuleSet, max_len: usize, prefix: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut board = *board;
+    let mut side = side;
+    let mut pv = Vec::new();
+    let mut prefix = prefix.iter().copied();
+    let mut next = prefix.next();
+    while let Some((row, col)) = next {
+        if pv.len() >= max_len.max(1) || !board.is_empty_at(row, col) {
+            break;
+        }
+        board.make_move(row, col, side);
+        pv.push((row, col));
+        if check_win_with_rule(&board, row, col, rule) {
+            break;
+        }
+        side = -side;
+        next = prefix
+            .next()
+            .or_else(|| tt.probe(zobrist::position_key(board.hash(), side)).and_then(|entry| entry.best_move));
+    }
+    pv
+}
+
+// 便捷入口：用新建的置换表按预算搜索 side 的最佳着法
+pub fn search_best_move(board: &Board, side: i32, rule: RuleSet, limits: SearchLimits, tt_size_mb: usize) -> SearchResult {
+    let tt = TranspositionTable::new(tt_size_mb);
+    Searcher::new(&tt, limits, rule).iterative_deepening(board, side)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::test_util::board_with;
+    use crate::vcf::{find_vcf, VcfLimits, VcfOutcome};
+    use crate::{BLACK, WHITE};
+
+    #[test]
+    fn forbidden_forced_block_still_returns_a_move() {
+        // 白四唯一的挡点 (7,7) 是黑棋的三三禁手
+        let board = board_with(&[(7, 2), (5, 7), (6, 7), (8, 8), (9, 9)], &[(7, 3), (7, 4), (7, 5), (7, 6)]);
+        let result = search_best_move(&board, BLACK, RuleSet::Renju, SearchLimits::depth(2), 1);
+        let (row, col) = result.best_move.expect("棋盘未满时应当有着法");
+        assert_ne!((row, col), (7, 7));
+        let mut scratch = board;
+        assert!(board.is_empty_at(row, col) && !RuleSet::Renju.is_forbidden(&mut scratch, row, col, BLACK));
+    }
+
+    #[test]
+    fn excluding_every_forced_move_falls_back() {
+        let board = board_with(&[(7, 7)], &[(5, 3), (5, 4), (5, 5), (5, 6)]);
+        let tt = TranspositionTable::new(1);
+        let excluded = [(5, 2), (5, 7)];
+        let result = Searcher::new(&tt, SearchLimits::depth(2), RuleSet::Freestyle)
+            .exclude_root_moves(&excluded)
+            .iterative_deepening(&board, BLACK);
+        let best = result.best_move.expect("应当退回到其它着法");
+        assert!(!excluded.contains(&best));
+    }
+
+    #[test]
+    fn takes_win_and_blocks_four() {
+        let board = board_with(&[(7, 3), (7, 4), (7, 5), (7, 6)], &[(8, 3), (8, 4), (8, 5)]);
+        let result = search_best_move(&board, BLACK, RuleSet::Freestyle, SearchLimits::depth(3), 1);
+        assert!(matches!(result.best_move, Some((7, 2)) | Some((7, 7))));
+        assert!(result.score > MATE_BOUND);
+
+        let board = board_with(&[(7, 2), (8, 3), (8, 4), (8, 9)], &[(7, 3), (7, 4), (7, 5), (7, 6), (9, 9)]);
+        let result = search_best_move(&board, BLACK, RuleSet::Freestyle, SearchLimits::depth(3), 1);
+        assert_eq!(result.best_move, Some((7, 7)));
+    }
+
+    #[test]
+    fn width_counts_only_quiet_root_moves() {
+        // 黑棋两处活二，能连成活三的点都是战术着法
+        let board = board_with(&[(7, 7), (7, 8), (4, 4), (5, 4)], &[(8, 8), (9, 9), (10, 3)]);
+        let pos = Evaluator::new(&board, RuleSet::Freestyle);
+        let tactical = candidates::generate(&pos, BLACK).moves.iter().filter(|m| m.tactical).count();
+        assert!(tactical >= 2);
+        let tt = TranspositionTable::new(1);
+        let limits = SearchLimits { width: 1, ..SearchLimits::depth(2) };
+        let searcher = Searcher::new(&tt, limits, RuleSet::Freestyle);
+        assert_eq!(searcher.root_moves(&pos, BLACK).len(), tactical + 1);
+    }
+
+    #[test]
+    fn quiescence_sees_the_four_sequence_past_the_horizon() {
+        // 白棋 (7,6) 冲四、(6,6) 冲四同时成活三，黑棋只看两层时注意不到，去下自己的棋
+        let board = board_with(
+            &[(7, 2), (10, 6), (11, 10), (11, 11), (12, 9), (2, 12)],
+            &[(7, 3), (7, 4), (7, 5), (8, 6), (9, 6), (5, 7)],
+        );
+        let white_vcf_after = |features: SearchFeatures| {
+            let tt = TranspositionTable::new(1);
+            let result = Searcher::new(&tt, SearchLimits::depth(2), RuleSet::Freestyle)
+                .features(features)
+                .iterative_deepening(&board, BLACK);
+            let (row, col) = result.best_move.unwrap();
+            let mut after = board;
+            after.make_move(row, col, BLACK);
+            matches!(find_vcf(&after, WHITE, RuleSet::Freestyle, VcfLimits::default()), VcfOutcome::Win(_))
+        };
+        assert!(white_vcf_after(SearchFeatures::none()));
+        assert!(!white_vcf_after(SearchFeatures { quiescence_plies: 8, ..SearchFeatures::none() }));
+        assert!(!white_vcf_after(SearchFeatures::default()));
+    }
+
+    #[test]
+    fn quiescence_threes_cost_many_more_nodes() {
+        // 平静的中局，同样搜3层；活三延伸默认关闭的依据
+        let board = board_with(&[(7, 7), (8, 8), (6, 8), (9, 6)], &[(7, 8), (8, 7), (6, 6), (9, 9)]);
+        let nodes = |features: SearchFeatures| {
+            let tt = TranspositionTable::new(1);
+            Searcher::new(&tt, SearchLimits::depth(3), RuleSet::Freestyle)
+                .features(features)
+                .iterative_deepening(&board, BLACK)
+                .nodes
+        };
+        let fours = nodes(SearchFeatures::default());
+        let threes = nodes(SearchFeatures { quiescence_threes: true, ..SearchFeatures::default() });
+        assert!(threes > fours * 10, "只延伸四 {} 个节点，延伸活三 {} 个节点", fours, threes);
+    }
+
+    #[test]
+    fn pruning_features_keep_the_result_and_save_nodes() {
+        // 静态搜索两边都开，只比较 PVS、渴望窗口和 LMR：结果与全窗口 Alpha-Beta 相同，节点更少
+        let positions = [
+            board_with(&[(7, 7), (8, 8), (6, 8), (9, 6)], &[(7, 8), (8, 7), (6, 6), (9, 9)]),
+            board_with(&[(7, 7), (7, 9), (8, 8), (5, 6), (10, 8)], &[(6, 8), (8, 7), (9, 9), (6, 6), (8, 10)]),
+            board_with(&[(7, 2), (10, 6), (11, 10), (11, 11), (12, 9), (2, 12)], &[(7, 3), (7, 4), (7, 5), (8, 6), (9, 6), (5, 7)]),
+            board_with(&[(7, 7), (7, 8), (8, 7), (9, 9)], &[(6, 6), (6, 9), (10, 10), (5, 5)]),
+        ];
+        let plain = SearchFeatures { quiescence_plies: SearchFeatures::default().quiescence_plies, ..SearchFeatures::none() };
+        let search = |board: &Board, features: SearchFeatures| {
+            let tt = TranspositionTable::new(1);
+            Searcher::new(&tt, SearchLimits::depth(4), RuleSet::Freestyle)
+                .features(features)
+                .iterative_deepening(board, BLACK)
+        };
+        for board in &positions {
+            let expected = search(board, plain);
+            for features in [
+                SearchFeatures { pvs: true, ..plain },
+                SearchFeatures { aspiration_window: SearchFeatures::default().aspiration_window, ..plain },
+                SearchFeatures { lmr: true, ..plain },
+                SearchFeatures::default(),
+            ] {
+                let result = search(board, features);
+                assert_eq!((result.best_move, result.score), (expected.best_move, expected.score), "{:?}", features);
+            }
+            assert!(search(board, SearchFeatures::default()).nodes < expected.nodes);
+        }
+    }
+
+    #[test]
+    fn helper_threads_agree_on_forced_positions() {
+        let positions = [
+            // 白四只有 (5,7) 一个挡点
+            board_with(&[(7, 7), (8, 8), (6, 8), (5, 2), (9, 6)], &[(5, 3), (5, 4), (5, 5), (5, 6), (8, 7)]),
+            // 黑棋 (7,8) 成四三，四步内取胜
+            board_with(&[(7, 7), (7, 9), (8, 8), (5, 6), (10, 8)], &[(6, 8), (8, 7), (9, 9), (6, 6), (8, 10)]),
+        ];
+        for board in &positions {
+            let search = |threads: usize| {
+                let tt = TranspositionTable::new(1);
+                Searcher::new(&tt, SearchLimits::depth(4), RuleSet::Freestyle)
+                    .threads(threads)
+                    .iterative_deepening(board, BLACK)
+            };
+            let single = search(1);
+            for threads in [2, 4] {
+                let result = search(threads);
+                let (row, col) = result.best_move.expect("应当有着法");
+                assert!(board.is_empty_at(row, col));
+                assert_eq!(result.best_move, single.best_move, "{} 个线程", threads);
+                assert_eq!(result.score > MATE_BOUND, single.score > MATE_BOUND);
+            }
+        }
+    }
+
+    #[test]
+    fn stop_signal_ends_a_helper_search() {
+        // 不限深度、时间和节点，只有停止信号能让辅助线程结束
+        let board = board_with(&[(7, 7), (8, 8), (6, 8), (9, 6)], &[(7, 8), (8, 7), (6, 6), (9, 9)]);
+        let tt = TranspositionTable::new(1);
+        let stop = AtomicBool::new(true);
+        let mut helper = Searcher::new(&tt, SearchLimits::default(), RuleSet::Freestyle);
+        helper.stop_signal = Some(&stop);
+        let result = helper.deepen(&board, BLACK);
+        assert!(result.nodes <= CHECK_INTERVAL, "停止后仍展开了 {} 个节点", result.nodes);
+        assert!(result.depth < MAX_DEPTH);
+
+        // 多线程时各线程到节点预算就停，主线程结束后辅助线程随之停下，总节点数有上限
+        let limits = SearchLimits { max_nodes: 4 * CHECK_INTERVAL, ..SearchLimits::default() };
+        let result = Searcher::new(&tt, limits, RuleSet::Freestyle).threads(4).iterative_deepening(&board, BLACK);
+        assert!(result.best_move.is_some());
+        assert!(result.nodes <= 4 * (limits.max_nodes + CHECK_INTERVAL), "4 个线程共展开 {} 个节点", result.nodes);
+    }
+
+    // 从 board 起双方轮流走 pv，每一步都落在空位上，成五之后不再有着法
+    fn assert_legal_line(board: &Board, side: i32, pv: &[(usize, usize)]) {
+        let mut board = *board;
+        let mut side = side;
+        for (i, &(row, col)) in pv.iter().enumerate() {
+            assert!(board.is_empty_at(row, col), "第 {} 步 {:?} 已有棋子: {:?}", i, (row, col), pv);
+            board.make_move(row, col, side);
+            if check_win_with_rule(&board, row, col, RuleSet::Freestyle) {
+                assert_eq!(i + 1, pv.len(), "成五之后还有着法: {:?}", pv);
+            }
+            side = -side;
+        }
+    }
+
+    #[test]
+    fn multi_pv_lines_are_legal_distinct_and_sorted() {
+        let positions = [
+            board_with(&[(7, 7), (8, 8), (6, 8), (9, 6)], &[(7, 8), (8, 7), (6, 6), (9, 9)]),
+            board_with(&[(7, 7), (7, 9), (8, 8), (5, 6), (10, 8)], &[(6, 8), (8, 7), (9, 9), (6, 6), (8, 10)]),
+        ];
+        for board in &positions {
+            let tt = TranspositionTable::new(1);
+            let result = Searcher::new(&tt, SearchLimits::depth(4), RuleSet::Freestyle)
+                .multi_pv(3)
+                .iterative_deepening(board, BLACK);
+            assert_eq!(result.lines.len(), 3);
+            let best = &result.lines[0];
+            assert_eq!((Some((best.row, best.col)), best.score, &best.pv), (result.best_move, result.score, &result.pv));
+            assert!(result.lines.windows(2).all(|w| w[0].score >= w[1].score));
+            for (i, line) in result.lines.iter().enumerate() {
+                assert_eq!(line.pv.first(), Some(&(line.row, line.col)));
+                assert!(result.lines[..i].iter().all(|other| (other.row, other.col) != (line.row, line.col)));
+                assert_legal_line(board, BLACK, &line.pv);
+            }
+            // 从置换表补全的主要变化同样合法
+            assert_legal_line(board, BLACK, &principal_variation(&tt, board, BLACK, RuleSet::Freestyle, 20, &result.pv[..1]));
+        }
+    }
+
+    #[test]
+    fn searches_a_20x20_board() {
+        // 白四的挡点在 15x15 范围之外
+        let mut board = Board::with_size(20).unwrap();
+        for (col, black) in (14..18).zip([(18, 13), (5, 5), (9, 3), (3, 12)]) {
+            board.make_move(18, col, WHITE);
+            board.make_move(black.0, black.1, BLACK);
+        }
+        let result = search_best_move(&board, BLACK, RuleSet::Freestyle, SearchLimits::depth(3), 1);
+        assert_eq!(result.best_move, Some((18, 18)));
+
+        let mut board = Board::with_size(20).unwrap();
+        board.make_move(19, 19, BLACK);
+        board.make_move(18, 18, WHITE);
+        let result = search_best_move(&board, BLACK, RuleSet::Freestyle, SearchLimits::depth(3), 1);
+        let (row, col) = result.best_move.unwrap();
+        assert!(row < 20 && col < 20 && board.is_empty_at(row, col));
+        assert_legal_line(&board, BLACK, &result.pv);
+    }
+
+    #[test]
+    fn mate_scores_are_stored_relative_to_the_node() {
+        // 在第3层发现“2步后成五”，分数按根节点计；存表后在第5层读出时要换算成按该层计
+        let mate_in = WIN_SCORE - 5;
+        let stored = score_to_tt(mate_in, 3);
+        assert_eq!(stored, WIN_SCORE - 2);
+        assert_eq!(score_from_tt(stored, 5), WIN_SCORE - 7);
+        assert_eq!(score_from_tt(score_to_tt(-mate_in, 3), 5), -(WIN_SCORE - 7));
+        for score in [0, 500, -500, MATE_BOUND, -MATE_BOUND] {
+            assert_eq!(score_to_tt(score, 7), score);
+            assert_eq!(score_from_tt(score, 7), score);
+        }
+        for ply in 0..20 {
+            assert_eq!(score_from_tt(score_to_tt(mate_in, ply), ply), mate_in);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+// 威胁判定 - 成五点、冲四点等基本战术元素，供VCF/VCT等战术搜索使用
+// 判定都基于“试下一子看结果”，因此天然覆盖跳四（X_XXX、XX_XX）等非连续棋型
+
+use crate::{check_win_with_rule, Board, RuleSet, DIRECTIONS};
+
+// 在空位 (row, col) 落 player 是否直接成五
+pub fn is_five_move(board: &mut Board, row: usize, col: usize, player: i32, rule: RuleSet) -> bool {
+    board.make_move(row, col, player);
+    let won = check_win_with_rule(board, row, col, rule);
+    board.unmake_move(row, col);
+    won
+}
+
+// player 当前所有的成五点（下一手即可获胜的空位）
+pub fn five_points(board: &mut Board, player: i32, rule: RuleSet) -> Vec<(usize, usize)> {
+    // 成五点所在的五格窗口里有四颗己方棋子，其中至少一颗与它相邻
+    let candidates = board.stones(player).dilate().and(&board.empty_cells());
+    candidates
+        .iter()
+        .map(Board::coords)
+        .filter(|&(row, col)| is_five_move(board, row, col, player, rule))
+        .collect()
+}
+
+// (row, col) 上已有 player 的棋子，经过它的四条线上的成五点
+pub fn five_points_through(board: &mut Board, row: usize, col: usize, player: i32, rule: RuleSet) -> Vec<(usize, usize)> {
+    let mut points = Vec::new();
+    for dir in 0..DIRECTIONS.len() {
+        line_five_points(board, row, col, dir, player, rule, &mut points);
+    }
+    points
+}
+
+// 经过 (row, col) 的 DIRECTIONS[dir] 方向上，距离 radius 格以内的棋盘内格子（不含自身）
+fn line_cells(board: &Board, row: usize, col: usize, dir: usize, radius: i32) -> impl Iterator<Item = (usize, usize)> {
+    let (dx, dy) = DIRECTIONS[dir];
+    let size = board.size() as i32;
+    (-radius..=radius).filter(|&k| k != 0).filter_map(move |k| {
+        let r = row as i32 + dx * k;
+        let c = col as i32 + dy * k;
+        (r >= 0 && r < size && c >= 0 && c < size).then_some((r as usize, c as usize))
+    })
+}
+
+// 经过 (row, col) 的 DIRECTIONS[dir] 方向上，距离4格以内的成五点
+fn line_five_points(board: &mut Board, row: usize, col: usize, dir: usize, player: i32, rule: RuleSet, points: &mut Vec<(usize, usize)>) {
+    // 成五点所在的五格窗口里除 (row, col) 外还要有三颗己方棋子
+    if line_stone_count(board, row, col, dir, player) < 3 {
+        return;
+    }
+    for (r, c) in line_cells(board, row, col, dir, 4) {
+        if !board.is_empty_at(r, c) || points.contains(&(r, c)) {
+            continue;
+        }
+        board.make_move(r, c, player);
+        let five = rule.is_five_in_direction(board, r, c, dir, player);
+        board.unmake_move(r, c);
+        if five {
+            points.push((r, c));
+        }
+    }
+}
+
+// 在空位 (row, col) 落子后形成的成五点；为空说明这一手不是四
+pub fn four_points_of_move(board: &mut Board, row: usize, col: usize, player: i32, rule: RuleSet) -> Vec<(usize, usize)> {
+    board.make_move(row, col, player);
+    let points = five_points_through(board, row, col, player, rule);
+    board.unmake_move(row, col);
+    points
+}
+
+// 能形成四的落点，以及落子后的成五点（两个及以上即活四或双四）
+#[derive(Debug, Clone)]
+pub struct FourMove {
+    pub pos: (usize, usize),
+    pub five_points: Vec<(usize, usize)>,
+}
+
+// player 所有能形成四（冲四或活四）的落点，成五点多的排前面；不含禁手点
+pub fn four_moves(board: &mut Board, player: i32, rule: RuleSet) -> Vec<FourMove> {
+    // 形成四的落点，其五格窗口内另有三颗己方棋子，至少一颗在两格以内
+    let candidates = board.stones(player).dilate().dilate().and(&board.empty_cells());
+    let mut moves: Vec<_> = candidates
+        .iter()
+        .map(Board::coords)
+        .filter_map(|(row, col)| {
+            let five_points = four_points_of_move(board, row, col, player, rule);
+            if five_points.is_empty() || rule.is_forbidden(board, row, col, player) {
+                None
+            } else {
+                Some(FourMove { pos: (row, col), five_points })
+            }
+        })
+        .collect();
+    moves.sort_by_key(|m| std::cmp::Reverse(m.five_points.len()));
+    moves
+}
+
+// player 下一手能形成两个及以上成五点（活四、双四）的落点，即两步内必胜的点
+pub fn winning_four_points(board: &mut Board, player: i32, rule: RuleSet) -> Vec<(usize, usize)> {
+    four_moves(board, player, rule)
+        .into_iter()
+        .filter(|m| m.five_points.len() >= 2)
+        .map(|m| m.pos)
+        .collect()
+}
+
+// 在空位 (row, col) 落子后是否形成三：同一条线上出现了能走成活四（该线两个成五点）的点
+pub fn is_three_move(board: &mut Board, row: usize, col: usize, player: i32, rule: RuleSet) -> bool {
+    (0..DIRECTIONS.len()).any(|dir| is_three_in_direction(board, row, col, dir, player, rule))
+}
+
+fn is_three_in_direction(board: &mut Board, row: usize, col: usize, dir: usize, player: i32, rule: RuleSet) -> bool {
+    if line_stone_count(board, row, col, dir, player) < 2 {
+        return false;
+    }
+    board.make_move(row, col, player);
+    let mut found = false;
+    for (r, c) in line_cells(board, row, col, dir, 4) {
+        if !board.is_empty_at(r, c) {
+            continue;
+        }
+        board.make_move(r, c, player);
+        let mut points = Vec::new();
+        line_five_points(board, r, c, dir, player, rule, &mut points);
+        board.unmake_move(r, c);
+        if points.len() >= 2 {
+            found = true;
+            break;
+        }
+    }
+    board.unmake_move(row, col);
+    found
+}
+
+// 同一条线上4格以内 player 的棋子数，用于快速排除不可能成三的点
+fn line_stone_count(board: &Board, row: usize, col: usize, dir: usize, player: i32) -> usize {
+    line_cells(board, row, col, dir, 4).filter(|&(r, c)| board.get(r, c) == player).count()
+}
+
+// 经过 (row, col) 的连续三子，两端都是空位
+fn is_open_solid_three(board: &Board, row: usize, col: usize, dir: usize, player: i32) -> bool {
+    let (dx, dy) = DIRECTIONS[dir];
+    let mut count = 1;
+    let mut open_ends = 0;
+    for sign in [1, -1] {
+        let mut step = 1;
+        loop {
+            let (r, c) = (row as i32 + sign * dx * step, col as i32 + sign * dy * step);
+            if !board.contains(r, c) {
+                break;
+            }
+            match board.get(r as usize, c as usize) {
+                cell if cell == player => {
+                    count += 1;
+                    step += 1;
+                }
+                0 => {
+                    open_ends += 1;
+                    break;
+                }
+                _ => break,
+            }
+        }
+    }
+    count == 3 && open_ends == 2
+}
+
+// player 所有能形成三（且不构成四、不是禁手）的落点；按两端都空的连续三的数量排序，真活三优先
+pub fn three_moves(board: &mut Board, player: i32, rule: RuleSet) -> Vec<(usize, usize)> {
+    let candidates = board.stones(player).dilate().dilate().and(&board.empty_cells());
+    let mut moves: Vec<((usize, usize), usize)> = Vec::new();
+    for (row, col) in candidates.iter().map(Board::coords) {
+        if !is_three_move(board, row, col, player, rule)
+            || !four_points_of_move(board, row, col, player, rule).is_empty()
+            || rule.is_forbidden(board, row, col, player)
+        {
+            continue;
+        }
+        board.make_move(row, col, player);
+        let live_threes = (0..DIRECTIONS.len()).filter(|&dir| is_open_solid_three(board, row, col, dir, player)).count();
+        board.unmake_move(row, col);
+        moves.push(((row, col), live_threes));
+    }
+    moves.sort_by_key(|&(_, live_threes)| std::cmp::Reverse(live_threes));
+    moves.into_iter().map(|(pos, _)| pos).collect()
+}
+
+// 对手（-player）能让 player 的全部两步必胜点同时失效的防守点（不含对手的禁手点）
+// 防守方落子只会减少攻方的必胜点，所以只需复查原有的必胜点
+pub fn defenses_against(board: &mut Board, player: i32, threats: &[(usize, usize)], rule: RuleSet) -> Vec<(usize, usize)> {
+    let mut candidates: Vec<(usize, usize)> = Vec::new();
+    for &(row, col) in threats {
+        let cells = std::iter::once((row, col)).chain((0..DIRECTIONS.len()).flat_map(|dir| line_cells(board, row, col, dir, 4)));
+        for (r, c) in cells {
+            if board.is_empty_at(r, c) && !candidates.contains(&(r, c)) {
+                candidates.push((r, c));
+            }
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|&(r, c)| {
+            if rule.is_forbidden(board, r, c, -player) {
+                return false;
+            }
+            board.make_move(r, c, -player);
+            let refuted = threats
+                .iter()
+                .filter(|&&t| t != (r, c))
+                .all(|&(tr, tc)| four_points_of_move(board, tr, tc, player, rule).len() < 2);
+            board.unmake_move(r, c);
+            refuted
+        })
+        .collect()
+}
@@ -0,0 +1,65 @@
+// 搜索统计与追踪 - 记录选点走了哪个阶段、搜索了多少节点、用了多少时间
+// SearchInfo 随着法一起返回；TraceEvent 在选点过程中按发生顺序回调，供调试界面展示
+
+use serde::{Deserialize, Serialize};
+
+use crate::mcts::MoveVisits;
+use crate::MoveResult;
+
+// 最终着法来自选点流程的哪个阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionStage {
+    InstantWin,     // AI一步成五
+    ForcedBlock,    // 挡住对手的一步成五
+    Vcf,            // AI连续冲四必胜
+    Vct,            // AI连续威胁必胜
+    VctDefense,     // 化解对手的VCT
+    Search,         // 迭代加深搜索
+    Mcts,           // 蒙特卡洛树搜索
+    Handicap,       // 低难度故意走了搜索中分数稍差的一条变化
+}
+
+// 随着法返回的搜索统计；没有走到搜索阶段时剪枝和置换表字段为0
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchInfo {
+    pub stage: Option<DecisionStage>,
+    pub score: i32,
+    // 迭代加深完成的深度；战术阶段和 MCTS 为着法序列长度
+    pub depth: i32,
+    // VCF/VCT 求解器展开的节点数加上搜索的节点数（MCTS 为模拟次数）
+    pub nodes: u64,
+    // Alpha-Beta 剪枝次数
+    pub cutoffs: u64,
+    // 置换表命中可直接返回的次数
+    pub tt_hits: u64,
+    // 整个选点流程的用时
+    pub elapsed_ms: u64,
+    pub pv: Vec<(usize, usize)>,
+    // MCTS 根节点各着法的访问次数和胜率，按访问次数从多到少；Alpha-Beta 搜索时为空
+    pub visits: Vec<MoveVisits>,
+}
+
+// 选点过程中的追踪事件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TraceEvent {
+    // 一次VCF/VCT/VCT防守求解的结论和展开的节点数
+    Solver { stage: DecisionStage, found: bool, nodes: u64, elapsed_ms: u64 },
+    // 迭代加深完成一轮
+    Iteration { depth: i32, score: i32, nodes: u64, elapsed_ms: u64, pv: Vec<(usize, usize)> },
+    // 最终决定
+    Decision { stage: DecisionStage, row: usize, col: usize, score: i32, elapsed_ms: u64 },
+}
+
+// 追踪回调
+pub type TraceHook<'a> = &'a mut dyn FnMut(&TraceEvent);
+
+// 带统计和完整追踪记录的选点结果，供调试界面使用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveReport {
+    #[serde(rename = "move")]
+    pub best: Option<MoveResult>,
+    pub info: SearchInfo,
+    pub trace: Vec<TraceEvent>,
+}
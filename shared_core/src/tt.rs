@@ -0,0 +1,233 @@
+// 置换表 - 固定大小、按深度替换，保存分数界、深度和最佳着法
+// 每个槽位是两个原子 u64（key ^ data, data），无锁读写，多线程共享时能检测撕裂的条目
+
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+// 默认内存预算（MB）
+pub const DEFAULT_TT_SIZE_MB: usize = 16;
+
+// 分数界类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact, // 精确值
+    Lower, // 下界（发生 beta 截断）
+    Upper, // 上界（没有着法超过 alpha）
+}
+
+// 从置换表读出的条目
+#[derive(Debug, Clone, Copy)]
+pub struct TTEntry {
+    pub score: i32,
+    pub depth: i32,
+    pub bound: Bound,
+    pub best_move: Option<(usize, usize)>,
+}
+
+impl TTEntry {
+    // 在 [alpha, beta] 窗口下这个条目能否直接作为结果
+    pub fn cutoff(&self, depth: i32, alpha: i32, beta: i32) -> Option<i32> {
+        if self.depth < depth {
+            return None;
+        }
+        match self.bound {
+            Bound::Exact => Some(self.score),
+            Bound::Lower if self.score >= beta => Some(self.score),
+            Bound::Upper if self.score <= alpha => Some(self.score),
+            _ => None,
+        }
+    }
+}
+
+// data 的位布局：
+// [0, 32) 分数  [32, 40) 深度 + 1（保证有效条目非零）  [40, 42) 界类型  [42, 48) 代数  [48, 64) 最佳着法（格子编号 + 1，0 表示无）
+fn pack(score: i32, depth: i32, bound: Bound, generation: u8, best_move: Option<(usize, usize)>) -> u64 {
+    let bound_bits = match bound {
+        Bound::Exact => 0u64,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    let move_bits = best_move
+        .map(|(row, col)| crate::Board::index(row, col) as u64 + 1)
+        .unwrap_or(0);
+    (score as u32 as u64)
+        | (((depth.clamp(0, 254) + 1) as u64) << 32)
+        | (bound_bits << 40)
+        | (((generation & 0x3F) as u64) << 42)
+        | (move_bits << 48)
+}
+
+fn unpack(data: u64) -> (TTEntry, u8) {
+    let bound = match (data >> 40) & 0x3 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    let move_bits = (data >> 48) as usize;
+    let entry = TTEntry {
+        score: data as u32 as i32,
+        depth: ((data >> 32) & 0xFF) as i32 - 1,
+        bound,
+        best_move: if move_bits == 0 { None } else { Some(crate::Board::coords(move_bits - 1)) },
+    };
+    (entry, ((data >> 42) & 0x3F) as u8)
+}
+
+struct Slot {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    mask: usize,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    // 按内存预算创建，槽位数取不超过预算的2的幂
+    pub fn new(size_mb: usize) -> Self {
+        let bytes = size_mb.max(1) * 1024 * 1024;
+        let wanted = bytes / std::mem::size_of::<Slot>();
+        let count = if wanted.is_power_of_two() { wanted } else { wanted.next_power_of_two() / 2 };
+        let slots = (0..count)
+            .map(|_| Slot { check: AtomicU64::new(0), data: AtomicU64::new(0) })
+            .collect();
+        TranspositionTable { slots, mask: count - 1, generation: AtomicU8::new(0) }
+    }
+
+    pub fn size_mb(&self) -> usize {
+        self.slots.len() * std::mem::size_of::<Slot>() / (1024 * 1024)
+    }
+
+    // 清空所有条目
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.check.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    // 每次新搜索前调用，旧代条目可被优先替换
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn current_generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed) & 0x3F
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TTEntry> {
+        let slot = &self.slots[key as usize & self.mask];
+        let data = slot.data.load(Ordering::Relaxed);
+        let check = slot.check.load(Ordering::Relaxed);
+        if data == 0 || check ^ data != key {
+            return None;
+        }
+        Some(unpack(data).0)
+    }
+
+    // 替换策略：空槽、旧代条目、或新深度不低于原深度时写入
+    pub fn store(&self, key: u64, depth: i32, score: i32, bound: Bound, best_move: Option<(usize, usize)>) {
+        let slot = &self.slots[key as usize & self.mask];
+        let generation = self.current_generation();
+        let old_data = slot.data.load(Ordering::Relaxed);
+        if old_data != 0 {
+            let (old, old_generation) = unpack(old_data);
+            let same_key = slot.check.load(Ordering::Relaxed) ^ old_data == key;
+            if old_generation == generation && depth < old.depth && !(same_key && bound == Bound::Exact) {
+                return;
+            }
+        }
+        let data = pack(score, depth, bound, generation, best_move);
+        slot.data.store(data, Ordering::Relaxed);
+        slot.check.store(key ^ data, Ordering::Relaxed);
+    }
+
+    // 已使用的槽位比例（千分比，取前1000个槽位估算）
+    pub fn hashfull(&self) -> usize {
+        let sample = self.slots.len().min(1000);
+        let generation = self.current_generation();
+        self.slots[..sample]
+            .iter()
+            .filter(|slot| {
+                let data = slot.data.load(Ordering::Relaxed);
+                data != 0 && unpack(data).1 == generation
+            })
+            .count()
+            * 1000
+            / sample
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_TT_SIZE_MB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BOARD_SIZE;
+
+    #[test]
+    fn pack_round_trips() {
+        let last = BOARD_SIZE - 1;
+        for score in [0, 1, -1, 123_456, -123_456, i32::MAX, i32::MIN] {
+            for (depth, bound, generation, best_move) in [
+                (0, Bound::Exact, 0, None),
+                (1, Bound::Lower, 5, Some((0, 0))),
+                (254, Bound::Upper, 63, Some((last, last))),
+            ] {
+                let (entry, gen) = unpack(pack(score, depth, bound, generation, best_move));
+                assert_eq!((entry.score, entry.depth, entry.bound, entry.best_move, gen), (score, depth, bound, best_move, generation));
+            }
+        }
+        // 深度超出8位时截断，代数只保留低6位
+        let (entry, gen) = unpack(pack(7, 300, Bound::Exact, 64 + 3, None));
+        assert_eq!((entry.depth, gen), (254, 3));
+        assert_ne!(pack(0, 0, Bound::Exact, 0, None), 0);
+    }
+
+    #[test]
+    fn cutoff_respects_bound_and_depth() {
+        let entry = |bound| TTEntry { score: 50, depth: 4, bound, best_move: None };
+        assert_eq!(entry(Bound::Exact).cutoff(4, 0, 100), Some(50));
+        assert_eq!(entry(Bound::Exact).cutoff(5, 0, 100), None);
+        assert_eq!(entry(Bound::Lower).cutoff(3, 0, 40), Some(50));
+        assert_eq!(entry(Bound::Lower).cutoff(3, 0, 100), None);
+        assert_eq!(entry(Bound::Upper).cutoff(3, 60, 100), Some(50));
+        assert_eq!(entry(Bound::Upper).cutoff(3, 0, 100), None);
+    }
+
+    #[test]
+    fn store_probe_and_replacement() {
+        let tt = TranspositionTable::new(1);
+        let key = 0x1234_5678_9abc_def0;
+        assert!(tt.probe(key).is_none());
+        tt.store(key, 6, -300, Bound::Upper, Some((7, 7)));
+        let entry = tt.probe(key).unwrap();
+        assert_eq!((entry.depth, entry.score, entry.bound, entry.best_move), (6, -300, Bound::Upper, Some((7, 7))));
+
+        // 同一槽位上的其它局面读不到这个条目
+        let other = key ^ ((tt.mask as u64 + 1) << 4);
+        assert!(tt.probe(other).is_none());
+
+        // 同一代里较浅的其它局面不替换，新一代里可以替换
+        tt.store(other, 2, 10, Bound::Exact, None);
+        assert!(tt.probe(key).is_some());
+        tt.new_search();
+        tt.store(other, 2, 10, Bound::Exact, None);
+        assert!(tt.probe(key).is_none());
+        assert_eq!(tt.probe(other).unwrap().score, 10);
+
+        // 同一局面的精确值总会写入
+        tt.store(other, 1, 20, Bound::Exact, None);
+        assert_eq!(tt.probe(other).unwrap().score, 20);
+
+        tt.clear();
+        assert!(tt.probe(other).is_none());
+        assert_eq!(tt.hashfull(), 0);
+    }
+}
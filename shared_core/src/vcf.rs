@@ -0,0 +1,227 @@
+// VCF（连续冲四胜）求解 - 攻方每一手都形成四，守方只能挡在成五点上
+// 既供选点逻辑调用，也可以单独作为分析接口使用
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::search::Timer;
+use crate::threat::{five_points, four_moves};
+use crate::{check_win_with_rule, Board, RuleSet};
+
+// VCF搜索预算
+#[derive(Debug, Clone, Copy)]
+pub struct VcfLimits {
+    // 攻方最多连续冲四的次数
+    pub max_depth: usize,
+    // 最多展开的节点数
+    pub max_nodes: u64,
+    // 时间预算（毫秒），0 表示不限时
+    pub time_limit_ms: u64,
+}
+
+impl Default for VcfLimits {
+    fn default() -> Self {
+        VcfLimits {
+            max_depth: 12,
+            max_nodes: 20_000,
+            time_limit_ms: 0,
+        }
+    }
+}
+
+// 每展开这么多个节点检查一次是否超时
+const TIME_CHECK_INTERVAL: u64 = 64;
+
+// VCF求解结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VcfOutcome {
+    // 攻守交替的着法序列，第一手为攻方，最后一手攻方成五
+    // 守方挡点为禁手时省略守方这一手
+    Win(Vec<(usize, usize)>),
+    // 在深度限制内证明不存在VCF
+    NoWin,
+    // 节点或时间预算用完，未能得出结论
+    Unknown,
+}
+
+struct VcfSolver {
+    limits: VcfLimits,
+    rule: RuleSet,
+    nodes: u64,
+    timer: Timer,
+    aborted: bool,
+    // 已证明失败的局面：哈希 -> 证明时的剩余深度
+    failed: HashMap<u64, usize>,
+}
+
+impl VcfSolver {
+    fn out_of_budget(&self) -> bool {
+        let time_up = self.limits.time_limit_ms > 0
+            && self.nodes.is_multiple_of(TIME_CHECK_INTERVAL)
+            && self.timer.elapsed_ms() >= self.limits.time_limit_ms;
+        self.nodes > self.limits.max_nodes || time_up
+    }
+
+    fn search(&mut self, board: &mut Board, attacker: i32, depth: usize, sequence: &mut Vec<(usize, usize)>) -> bool {
+        self.nodes += 1;
+        if self.out_of_budget() {
+            self.aborted = true;
+            return false;
+        }
+
+        // 攻方已经有成五点
+        if let Some(&point) = five_points(board, attacker, self.rule).first() {
+            sequence.push(point);
+            return true;
+        }
+        if depth == 0 {
+            return false;
+        }
+        if self.failed.get(&board.hash()).is_some_and(|&d| d >= depth) {
+            return false;
+        }
+
+        // 守方有成五点时，攻方只能在该点冲四；守方有两个以上成五点则失败
+        let defender = -attacker;
+        let defender_fives = five_points(board, defender, self.rule);
+        if defender_fives.len() > 1 {
+            self.failed.insert(board.hash(), depth);
+            return false;
+        }
+
+        for four in four_moves(board, attacker, self.rule) {
+            let (row, col) = four.pos;
+            let points = four.five_points;
+            if defender_fives.first().is_some_and(|&p| p != (row, col)) {
+                continue;
+            }
+
+            board.make_move(row, col, attacker);
+            if points.len() >= 2 {
+                // 活四或双四，守方只能挡住一个
+                board.unmake_move(row, col);
+                sequence.extend([(row, col), points[0], points[1]]);
+                return true;
+            }
+
+            let (block_row, block_col) = points[0];
+            if self.rule.is_forbidden(board, block_row, block_col, defender) {
+                // 守方挡点是禁手，无法防守，攻方下一手直接成五
+                board.unmake_move(row, col);
+                sequence.extend([(row, col), (block_row, block_col)]);
+                return true;
+            }
+            board.make_move(block_row, block_col, defender);
+            // 守方挡的这一手本身成五则此路不通
+            let found = !check_win_with_rule(board, block_row, block_col, self.rule) && {
+                let len = sequence.len();
+                sequence.extend([(row, col), (block_row, block_col)]);
+                let found = self.search(board, attacker, depth - 1, sequence);
+                if !found {
+                    sequence.truncate(len);
+                }
+                found
+            };
+            board.unmake_move(block_row, block_col);
+            board.unmake_move(row, col);
+
+            if found {
+                return true;
+            }
+            if self.aborted {
+                return false;
+            }
+        }
+
+        self.failed.insert(board.hash(), depth);
+        false
+    }
+}
+
+// 为 attacker 寻找VCF，按深度逐步加深以得到最短的取胜序列
+pub fn find_vcf(board: &Board, attacker: i32, rule: RuleSet, limits: VcfLimits) -> VcfOutcome {
+    solve_vcf(board, attacker, rule, limits).0
+}
+
+// 同 find_vcf，另外返回展开的节点数
+pub(crate) fn solve_vcf(board: &Board, attacker: i32, rule: RuleSet, limits: VcfLimits) -> (VcfOutcome, u64) {
+    let mut board = *board;
+    let mut solver = VcfSolver {
+        limits,
+        rule,
+        nodes: 0,
+        timer: Timer::start(),
+        aborted: false,
+        failed: HashMap::new(),
+    };
+
+    for depth in 1..=limits.max_depth {
+        let mut sequence = Vec::new();
+        if solver.search(&mut board, attacker, depth, &mut sequence) {
+            return (VcfOutcome::Win(sequence), solver.nodes);
+        }
+        if solver.aborted {
+            return (VcfOutcome::Unknown, solver.nodes);
+        }
+    }
+    (VcfOutcome::NoWin, solver.nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::test_util::board_with;
+    use crate::threat::four_points_of_move;
+    use crate::{BLACK, WHITE};
+
+    // 按攻守交替走完序列：攻方每一手都成四（守方只能挡），最后一手成五
+    fn replays_as_vcf(board: &Board, attacker: i32, rule: RuleSet, sequence: &[(usize, usize)]) -> bool {
+        let mut board = *board;
+        for (i, &(row, col)) in sequence.iter().enumerate() {
+            let player = if i % 2 == 0 { attacker } else { -attacker };
+            if player == attacker && i + 1 < sequence.len() && four_points_of_move(&mut board, row, col, attacker, rule).is_empty() {
+                return false;
+            }
+            board.make_move(row, col, player);
+        }
+        let &(row, col) = sequence.last().unwrap();
+        check_win_with_rule(&board, row, col, rule)
+    }
+
+    #[test]
+    fn finds_double_four() {
+        // (7,7) 同时形成横竖两个冲四
+        let board = board_with(&[(7, 4), (7, 5), (7, 6), (4, 7), (5, 7), (6, 7)], &[(7, 3), (3, 7)]);
+        let VcfOutcome::Win(sequence) = find_vcf(&board, BLACK, RuleSet::Freestyle, VcfLimits::default()) else {
+            panic!("应当找到VCF");
+        };
+        assert_eq!(sequence[0], (7, 7));
+        assert!(replays_as_vcf(&board, BLACK, RuleSet::Freestyle, &sequence));
+    }
+
+    #[test]
+    fn finds_chained_fours() {
+        // 横线冲四逼白挡住后，(7,8) 上的新子让竖线能走成活四
+        let board = board_with(&[(7, 5), (7, 6), (7, 7), (5, 8), (6, 8)], &[(7, 4)]);
+        let VcfOutcome::Win(sequence) = find_vcf(&board, BLACK, RuleSet::Freestyle, VcfLimits::default()) else {
+            panic!("应当找到VCF");
+        };
+        assert!(sequence.len() > 3);
+        assert!(replays_as_vcf(&board, BLACK, RuleSet::Freestyle, &sequence));
+    }
+
+    #[test]
+    fn double_four_is_no_vcf_for_renju_black() {
+        let board = board_with(&[(7, 4), (7, 5), (7, 6), (4, 7), (5, 7), (6, 7)], &[(7, 3), (3, 7)]);
+        assert_eq!(find_vcf(&board, BLACK, RuleSet::Renju, VcfLimits::default()), VcfOutcome::NoWin);
+        // 白棋没有禁手
+        let board = board_with(&[(7, 3), (3, 7)], &[(7, 4), (7, 5), (7, 6), (4, 7), (5, 7), (6, 7)]);
+        assert!(matches!(find_vcf(&board, WHITE, RuleSet::Renju, VcfLimits::default()), VcfOutcome::Win(_)));
+    }
+
+    #[test]
+    fn quiet_position_has_no_vcf() {
+        let board = board_with(&[(7, 7), (7, 8)], &[(8, 7)]);
+        assert_eq!(find_vcf(&board, BLACK, RuleSet::Freestyle, VcfLimits::default()), VcfOutcome::NoWin);
+    }
+}
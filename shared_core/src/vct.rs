@@ -0,0 +1,336 @@
+// VCT（连续威胁胜）求解 - 攻方每一手都必须是四或三，守方只考虑真正能化解威胁的应手
+// 威胁空间剪枝：对四只能挡成五点；对三只能走使全部活四点失效的防守点，或者用自己的四反击
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::candidates::candidate_moves;
+use crate::search::Timer;
+use crate::threat::{defenses_against, five_points, four_moves, three_moves, winning_four_points};
+use crate::{check_win_with_rule, Board, RuleSet};
+
+// VCT搜索预算
+#[derive(Debug, Clone, Copy)]
+pub struct VctLimits {
+    // 攻方最多连续威胁的次数
+    pub max_depth: usize,
+    // 最多展开的节点数
+    pub max_nodes: u64,
+    // 时间预算（毫秒），0 表示不限时；化解VCT时是所有复查共用的总预算
+    pub time_limit_ms: u64,
+}
+
+impl Default for VctLimits {
+    fn default() -> Self {
+        VctLimits {
+            max_depth: 6,
+            max_nodes: 3_000,
+            time_limit_ms: 0,
+        }
+    }
+}
+
+// 每展开这么多个节点检查一次是否超时
+const TIME_CHECK_INTERVAL: u64 = 16;
+
+// VCT求解结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VctOutcome {
+    // 攻守交替的主变化，第一手为攻方，最后一手攻方成五；守方取第一种应手
+    // 守方挡点为禁手时省略守方这一手
+    Win(Vec<(usize, usize)>),
+    // 在深度限制内证明不存在VCT
+    NoWin,
+    // 节点或时间预算用完，未能得出结论
+    Unknown,
+}
+
+struct VctSolver {
+    limits: VctLimits,
+    rule: RuleSet,
+    nodes: u64,
+    timer: Timer,
+    aborted: bool,
+    // 已证明失败的局面（攻方行棋）：哈希 -> 证明时的剩余深度
+    failed: HashMap<u64, usize>,
+}
+
+impl VctSolver {
+    fn out_of_budget(&self) -> bool {
+        let time_up = self.limits.time_limit_ms > 0
+            && self.nodes.is_multiple_of(TIME_CHECK_INTERVAL)
+            && self.timer.elapsed_ms() >= self.limits.time_limit_ms;
+        self.nodes > self.limits.max_nodes || time_up
+    }
+
+    // 攻方行棋，返回取胜的主变化
+    fn attack(&mut self, board: &mut Board, attacker: i32, depth: usize) -> Option<Vec<(usize, usize)>> {
+        self.nodes += 1;
+        if self.out_of_budget() {
+            self.aborted = true;
+            return None;
+        }
+
+        if let Some(&point) = five_points(board, attacker, self.rule).first() {
+            return Some(vec![point]);
+        }
+        let defender_fives = five_points(board, -attacker, self.rule);
+        if defender_fives.len() > 1 || depth == 0 {
+            return None;
+        }
+        if self.failed.get(&board.hash()).is_some_and(|&d| d >= depth) {
+            return None;
+        }
+
+        // 守方有四时只能先挡，挡点是攻方禁手则挡不住；否则先试四再试三
+        let candidates: Vec<(usize, usize)> = if let Some(&point) = defender_fives.first() {
+            if self.rule.is_forbidden(board, point.0, point.1, attacker) {
+                return None;
+            }
+            vec![point]
+        } else {
+            let mut moves: Vec<_> = four_moves(board, attacker, self.rule).into_iter().map(|m| m.pos).collect();
+            moves.extend(three_moves(board, attacker, self.rule));
+            moves
+        };
+
+        for (row, col) in candidates {
+            board.make_move(row, col, attacker);
+            let line = self.defend(board, attacker, depth - 1);
+            board.unmake_move(row, col);
+
+            if let Some(mut line) = line {
+                line.insert(0, (row, col));
+                return Some(line);
+            }
+            if self.aborted {
+                return None;
+            }
+        }
+
+        self.failed.insert(board.hash(), depth);
+        None
+    }
+
+    // 守方行棋：所有应手都被攻破时返回第一种应手下的主变化
+    fn defend(&mut self, board: &mut Board, attacker: i32, depth: usize) -> Option<Vec<(usize, usize)>> {
+        let defender = -attacker;
+        let attacker_fives = five_points(board, attacker, self.rule);
+        if attacker_fives.len() >= 2 {
+            return Some(vec![attacker_fives[0], attacker_fives[1]]);
+        }
+
+        let replies = if let Some(&point) = attacker_fives.first() {
+            // 唯一的挡点是守方禁手，无法防守，攻方下一手直接成五
+            if self.rule.is_forbidden(board, point.0, point.1, defender) {
+                return Some(vec![point]);
+            }
+            vec![point]
+        } else {
+            let threats = winning_four_points(board, attacker, self.rule);
+            if threats.is_empty() {
+                return None; // 攻方这一手没有形成威胁
+            }
+            let mut replies = defenses_against(board, attacker, &threats, self.rule);
+            for four in four_moves(board, defender, self.rule) {
+                if !replies.contains(&four.pos) {
+                    replies.push(four.pos);
+                }
+            }
+            // 无法化解（如双活三），任取一个点代表守方应手，以便给出后续主变化
+            if replies.is_empty() {
+                replies.push(threats[0]);
+            }
+            replies
+        };
+
+        let mut main_line = None;
+        for (row, col) in replies {
+            board.make_move(row, col, defender);
+            let line = if check_win_with_rule(board, row, col, self.rule) {
+                None
+            } else {
+                self.attack(board, attacker, depth)
+            };
+            board.unmake_move(row, col);
+
+            match line {
+                Some(mut line) => {
+                    if main_line.is_none() {
+                        line.insert(0, (row, col));
+                        main_line = Some(line);
+                    }
+                }
+                None => return None,
+            }
+        }
+        main_line
+    }
+}
+
+// 为 attacker 寻找VCT，按深度逐步加深以得到最短的取胜序列
+pub fn find_vct(board: &Board, attacker: i32, rule: RuleSet, limits: VctLimits) -> VctOutcome {
+    solve_vct(board, attacker, rule, limits).0
+}
+
+// 同 find_vct，另外返回展开的节点数
+pub(crate) fn solve_vct(board: &Board, attacker: i32, rule: RuleSet, limits: VctLimits) -> (VctOutcome, u64) {
+    let mut board = *board;
+    let mut solver = VctSolver {
+        limits,
+        rule,
+        nodes: 0,
+        timer: Timer::start(),
+        aborted: false,
+        failed: HashMap::new(),
+    };
+
+    for depth in 1..=limits.max_depth {
+        if let Some(line) = solver.attack(&mut board, attacker, depth) {
+            return (VctOutcome::Win(line), solver.nodes);
+        }
+        if solver.aborted {
+            return (VctOutcome::Unknown, solver.nodes);
+        }
+    }
+    (VctOutcome::NoWin, solver.nodes)
+}
+
+// 化解对方VCT时最多尝试的防守点数量
+const MAX_DEFENSE_CANDIDATES: usize = 10;
+
+// 对手（-defender）存在VCT时，寻找能化解它的防守点
+// 候选依次为：对方主变化中攻方的落点、守方自己的冲四点、常规候选位置
+// 只返回证明过对方再无VCT的点；复查时预算用完（Unknown）不算化解，总时间用完时放弃
+pub fn find_vct_defense(board: &Board, defender: i32, rule: RuleSet, limits: VctLimits) -> Option<(usize, usize)> {
+    solve_vct_defense(board, defender, rule, limits).0
+}
+
+// 同 find_vct_defense，另外返回各次求解共展开的节点数
+pub(crate) fn solve_vct_defense(board: &Board, defender: i32, rule: RuleSet, limits: VctLimits) -> (Option<(usize, usize)>, u64) {
+    let attacker = -defender;
+    let timer = Timer::start();
+    // 每次求解只拿总预算剩下的时间
+    let remaining = || match limits.time_limit_ms {
+        0 => Some(limits),
+        total => {
+            let left = total.saturating_sub(timer.elapsed_ms());
+            (left > 0).then_some(VctLimits { time_limit_ms: left, ..limits })
+        }
+    };
+    let (outcome, mut nodes) = solve_vct(board, attacker, rule, limits);
+    let VctOutcome::Win(line) = outcome else {
+        return (None, nodes);
+    };
+
+    let mut board = *board;
+    let mut candidates: Vec<(usize, usize)> = line.iter().step_by(2).copied().collect();
+    candidates.extend(four_moves(&mut board, defender, rule).into_iter().map(|m| m.pos));
+    candidates.extend(candidate_moves(&board, defender, rule));
+
+    let mut tried = Vec::new();
+    for (row, col) in candidates {
+        if tried.contains(&(row, col)) || !board.is_empty_at(row, col) || rule.is_forbidden(&mut board, row, col, defender) {
+            continue;
+        }
+        tried.push((row, col));
+        if tried.len() > MAX_DEFENSE_CANDIDATES {
+            break;
+        }
+
+        let Some(limits) = remaining() else {
+            break;
+        };
+        board.make_move(row, col, defender);
+        let refuted = check_win_with_rule(&board, row, col, rule) || {
+            let (outcome, spent) = solve_vct(&board, attacker, rule, limits);
+            nodes += spent;
+            outcome == VctOutcome::NoWin
+        };
+        board.unmake_move(row, col);
+        if refuted {
+            return (Some((row, col)), nodes);
+        }
+    }
+    (None, nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::test_util::board_with;
+    use crate::{BLACK, WHITE};
+
+    // 按攻守交替走完主变化，最后一手应当成五
+    fn ends_in_five(board: &Board, attacker: i32, rule: RuleSet, line: &[(usize, usize)]) -> bool {
+        let mut board = *board;
+        let mut player = attacker;
+        for &(row, col) in line {
+            board.make_move(row, col, player);
+            player = -player;
+        }
+        let &(row, col) = line.last().unwrap();
+        board.get(row, col) == attacker && check_win_with_rule(&board, row, col, rule)
+    }
+
+    #[test]
+    fn finds_double_three() {
+        let board = board_with(&[(7, 5), (7, 6), (5, 8), (6, 8)], &[]);
+        let VctOutcome::Win(line) = find_vct(&board, BLACK, RuleSet::Freestyle, VctLimits::default()) else {
+            panic!("应当找到VCT");
+        };
+        assert!(ends_in_five(&board, BLACK, RuleSet::Freestyle, &line));
+    }
+
+    #[test]
+    fn no_threats_no_win() {
+        let board = board_with(&[(7, 7)], &[(7, 8)]);
+        assert_eq!(find_vct(&board, BLACK, RuleSet::Freestyle, VctLimits::default()), VctOutcome::NoWin);
+    }
+
+    #[test]
+    fn forced_block_on_forbidden_point_loses() {
+        // 白四的挡点 (7,7) 是黑棋的三三禁手
+        let mut board = board_with(&[(7, 2), (5, 7), (6, 7), (8, 8), (9, 9)], &[(7, 3), (7, 4), (7, 5), (7, 6)]);
+        assert!(RuleSet::Renju.is_forbidden(&mut board, 7, 7, BLACK));
+        assert!(!matches!(find_vct(&board, BLACK, RuleSet::Renju, VctLimits::default()), VctOutcome::Win(_)));
+        assert!(matches!(find_vct(&board, BLACK, RuleSet::Freestyle, VctLimits::default()), VctOutcome::Win(_)));
+    }
+
+    #[test]
+    fn defense_refutes_vct() {
+        let board = board_with(&[(7, 5), (7, 6), (7, 7)], &[(3, 3)]);
+        let (row, col) = find_vct_defense(&board, WHITE, RuleSet::Freestyle, VctLimits::default()).expect("应当找到防守点");
+        let mut after = board;
+        after.make_move(row, col, WHITE);
+        assert_eq!(find_vct(&after, BLACK, RuleSet::Freestyle, VctLimits::default()), VctOutcome::NoWin);
+    }
+
+    #[test]
+    fn unproven_defense_is_not_returned() {
+        // 预算只够找到对方的VCT，复查防守点时得不出结论，不能当作化解
+        let board = board_with(&[(7, 5), (7, 6), (7, 7)], &[(3, 3)]);
+        let limits = VctLimits { max_depth: 6, max_nodes: 5, ..Default::default() };
+        assert!(matches!(find_vct(&board, BLACK, RuleSet::Freestyle, limits), VctOutcome::Win(_)));
+        for (row, col) in [(7, 4), (7, 8)] {
+            let mut after = board;
+            after.make_move(row, col, WHITE);
+            assert_eq!(find_vct(&after, BLACK, RuleSet::Freestyle, limits), VctOutcome::Unknown);
+        }
+        assert_eq!(find_vct_defense(&board, WHITE, RuleSet::Freestyle, limits), None);
+    }
+
+    #[test]
+    fn time_budget_stops_the_search() {
+        // 威胁很多的中局，不限时要好几秒才能得出结论；节点不设上限，只有时间预算能让求解停下
+        let board = board_with(
+            &[(4, 6), (5, 8), (5, 9), (6, 6), (6, 10), (7, 4), (9, 8), (10, 7)],
+            &[(4, 9), (4, 10), (5, 6), (7, 5), (7, 6), (7, 9), (7, 10), (8, 6)],
+        );
+        let limits = VctLimits { max_depth: 12, max_nodes: u64::MAX, time_limit_ms: 30 };
+        let (outcome, nodes) = solve_vct(&board, BLACK, RuleSet::Freestyle, limits);
+        assert_eq!(outcome, VctOutcome::Unknown);
+        assert!(nodes > 0);
+        assert_eq!(find_vct_defense(&board, WHITE, RuleSet::Freestyle, limits), None);
+    }
+}
@@ -0,0 +1,171 @@
+// 评估权重 - 棋型分、组合加分和中心加分，可以从 JSON/TOML 文件载入，不用重新编译就能换一套权重
+// 文件里省略的字段取内置默认值，例如只调活三：
+//   live_three = 2500
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::eval::LineShape;
+use crate::WIN_SCORE;
+
+// 一条线上的一个棋型只计一次，分数由构成它的几颗棋子平分；成五固定为 WIN_SCORE，不可调
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvalWeights {
+    pub live_four: i32,
+    pub rush_four: i32,
+    pub live_three: i32,
+    pub sleep_three: i32,
+    pub live_two: i32,
+    pub sleep_two: i32,
+    pub live_one: i32,
+    // 同一颗棋子上的双四、四三
+    pub four_combo: i32,
+    // 同一颗棋子上的双活三
+    pub double_three: i32,
+    // 靠近中心（曼哈顿距离不超过2）的棋子
+    pub center: i32,
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        EvalWeights {
+            live_four: 20000,
+            rush_four: 2500,
+            live_three: 2000,
+            sleep_three: 300,
+            live_two: 200,
+            sleep_two: 30,
+            live_one: 10,
+            four_combo: 15000,
+            double_three: 6000,
+            center: 20,
+        }
+    }
+}
+
+impl EvalWeights {
+    pub fn shape_score(&self, shape: LineShape) -> i32 {
+        match shape {
+            LineShape::Five => WIN_SCORE,
+            LineShape::LiveFour => self.live_four,
+            LineShape::RushFour => self.rush_four,
+            LineShape::LiveThree => self.live_three,
+            LineShape::SleepThree => self.sleep_three,
+            LineShape::LiveTwo => self.live_two,
+            LineShape::SleepTwo => self.sleep_two,
+            LineShape::LiveOne => self.live_one,
+            LineShape::None => 0,
+        }
+    }
+
+    // 按名称列出全部可调字段，供调参工具逐项修改
+    pub fn fields_mut(&mut self) -> [(&'static str, &mut i32); 10] {
+        [
+            ("live_four", &mut self.live_four),
+            ("rush_four", &mut self.rush_four),
+            ("live_three", &mut self.live_three),
+            ("sleep_three", &mut self.sleep_three),
+            ("live_two", &mut self.live_two),
+            ("sleep_two", &mut self.sleep_two),
+            ("live_one", &mut self.live_one),
+            ("four_combo", &mut self.four_combo),
+            ("double_three", &mut self.double_three),
+            ("center", &mut self.center),
+        ]
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| format!("无效的权重 JSON: {}", e))
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| format!("无效的权重 TOML: {}", e))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("权重必然可以序列化")
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("权重必然可以序列化")
+    }
+
+    // 按扩展名（.json / .toml）读取权重文件
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| format!("无法读取权重文件 {}: {}", path.display(), e))?;
+        match path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("json") => Self::from_json(&text),
+            Some("toml") => Self::from_toml(&text),
+            _ => Err(format!("不支持的权重文件格式: {}", path.display())),
+        }
+    }
+
+    // 按扩展名写出权重文件
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let text = match path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("json") => self.to_json(),
+            Some("toml") => self.to_toml(),
+            _ => return Err(format!("不支持的权重文件格式: {}", path.display())),
+        };
+        std::fs::write(path, text).map_err(|e| format!("无法写入权重文件 {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 测试用的临时文件路径，带进程号避免并行测试互相覆盖
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("gobang-weights-{}-{}", std::process::id(), name))
+    }
+
+    fn tweaked() -> EvalWeights {
+        EvalWeights { live_three: 2500, center: 7, ..EvalWeights::default() }
+    }
+
+    #[test]
+    fn json_and_toml_round_trip() {
+        let weights = tweaked();
+        assert_eq!(EvalWeights::from_json(&weights.to_json()), Ok(weights));
+        assert_eq!(EvalWeights::from_toml(&weights.to_toml()), Ok(weights));
+    }
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let expected = EvalWeights { live_three: 2500, ..EvalWeights::default() };
+        assert_eq!(EvalWeights::from_toml("live_three = 2500"), Ok(expected));
+        assert_eq!(EvalWeights::from_json(r#"{"live_three": 2500}"#), Ok(expected));
+        assert_eq!(EvalWeights::from_json("{}"), Ok(EvalWeights::default()));
+        assert!(EvalWeights::from_json(r#"{"live_three": "high"}"#).is_err());
+    }
+
+    #[test]
+    fn load_and_save_by_extension() {
+        let weights = tweaked();
+        for name in ["round-trip.json", "round-trip.TOML"] {
+            let path = temp_path(name);
+            weights.save(&path).unwrap();
+            assert_eq!(EvalWeights::load(&path), Ok(weights));
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn unknown_extension_and_missing_file_are_errors() {
+        let path = temp_path("weights.yaml");
+        assert!(tweaked().save(&path).unwrap_err().contains("不支持的权重文件格式"));
+        assert!(!path.exists());
+
+        // 内容合法，扩展名不认识也不载入
+        std::fs::write(&path, tweaked().to_json()).unwrap();
+        assert!(EvalWeights::load(&path).unwrap_err().contains("不支持的权重文件格式"));
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(EvalWeights::load(temp_path("missing.json")).unwrap_err().contains("无法读取权重文件"));
+    }
+}
@@ -0,0 +1,50 @@
+// Zobrist哈希 - 每个格子、每种棋子一个随机键，落子/撤销时异或更新
+
+use crate::board::MAX_CELL_COUNT;
+
+// 编译期用固定种子的 splitmix64 生成键表，保证各平台哈希一致
+pub(crate) const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn build_keys() -> [[u64; MAX_CELL_COUNT]; 2] {
+    let mut keys = [[0u64; MAX_CELL_COUNT]; 2];
+    let mut state = 0x2545_F491_4F6C_DD1D;
+    let mut side = 0;
+    while side < 2 {
+        let mut idx = 0;
+        while idx < MAX_CELL_COUNT {
+            let (next, value) = splitmix64(state);
+            state = next;
+            keys[side][idx] = value;
+            idx += 1;
+        }
+        side += 1;
+    }
+    keys
+}
+
+// keys[0] 对应棋子 1，keys[1] 对应棋子 -1
+static PIECE_KEYS: [[u64; MAX_CELL_COUNT]; 2] = build_keys();
+
+// 轮到棋子 -1 行棋时额外异或的键，区分同一局面的不同行棋方
+pub const SIDE_KEY: u64 = 0x6A09_E667_F3BC_C909;
+
+#[inline]
+pub fn piece_key(player: i32, idx: usize) -> u64 {
+    PIECE_KEYS[if player == 1 { 0 } else { 1 }][idx]
+}
+
+// 搜索用的局面键：棋子哈希加上行棋方
+#[inline]
+pub fn position_key(board_hash: u64, side_to_move: i32) -> u64 {
+    if side_to_move == 1 {
+        board_hash
+    } else {
+        board_hash ^ SIDE_KEY
+    }
+}
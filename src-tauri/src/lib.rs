@@ -3,9 +3,24 @@
 
 use shared_core::*;
 
+// 解析可选的规则参数，未传时使用自由规则
+fn parse_rule(rule: Option<String>) -> Result<RuleSet, String> {
+    rule.map_or(Ok(RuleSet::Freestyle), |rule| rule.parse())
+}
+
+// 解析难度等级，未知值按简单处理
+fn parse_difficulty(difficulty: &str) -> AIDifficulty {
+    match difficulty {
+        "medium" => AIDifficulty::Medium,
+        "hard" => AIDifficulty::Hard,
+        _ => AIDifficulty::Easy,
+    }
+}
+
 // AI下棋命令
 #[tauri::command]
 pub fn ai_move(board: Vec<Vec<i32>>, ai_player: i32, human_player: i32) -> Result<(usize, usize), String> {
+    let board = Board::from_nested(&board)?;
     let best_move = get_best_move(&board, ai_player, human_player);
     match best_move {
         Some(move_result) => Ok((move_result.row, move_result.col)),
@@ -13,28 +28,59 @@ pub fn ai_move(board: Vec<Vec<i32>>, ai_player: i32, human_player: i32) -> Resul
     }
 }
 
+// 调试命令：AI移动及搜索统计（决定着法的阶段、深度、节点数、用时）和追踪事件
+#[tauri::command]
+pub fn ai_move_report(board: Vec<Vec<i32>>, ai_player: i32, human_player: i32, difficulty: String, rule: Option<String>) -> Result<MoveReport, String> {
+    let board = Board::from_nested(&board)?;
+    Ok(get_best_move_report(&board, ai_player, human_player, parse_difficulty(&difficulty), parse_rule(rule)?))
+}
+
 // 检查游戏是否结束
 #[tauri::command]
-pub fn check_win(board: Vec<Vec<i32>>, row: usize, col: usize) -> bool {
-    shared_core::check_win(&board, row, col)
+pub fn check_win(board: Vec<Vec<i32>>, row: usize, col: usize, rule: Option<String>) -> Result<bool, String> {
+    let board = Board::from_nested(&board)?;
+    Ok(shared_core::check_win_with_rule(&board, row, col, parse_rule(rule)?))
 }
 
 // 评估棋盘状态
 #[tauri::command]
-pub fn evaluate_board(board: Vec<Vec<i32>>, player: i32) -> i32 {
-    shared_core::evaluate_board(&board, player)
+pub fn evaluate_board(board: Vec<Vec<i32>>, player: i32, rule: Option<String>) -> Result<i32, String> {
+    let board = Board::from_nested(&board)?;
+    Ok(shared_core::evaluate_board_with_rule(&board, player, parse_rule(rule)?))
 }
 
 // 获取可能的移动位置
 #[tauri::command]
-pub fn get_possible_moves(board: Vec<Vec<i32>>) -> Vec<(usize, usize)> {
-    shared_core::get_possible_moves(&board)
+pub fn get_possible_moves(board: Vec<Vec<i32>>) -> Result<Vec<(usize, usize)>, String> {
+    let board = Board::from_nested(&board)?;
+    Ok(shared_core::get_possible_moves(&board))
+}
+
+// 分析接口：为 attacker 寻找连续冲四必胜（VCF）序列
+#[tauri::command]
+pub fn find_vcf(board: Vec<Vec<i32>>, attacker: i32, rule: Option<String>) -> Result<VcfOutcome, String> {
+    let board = Board::from_nested(&board)?;
+    Ok(shared_core::find_vcf(&board, attacker, parse_rule(rule)?, VcfLimits::default()))
+}
+
+// 分析接口：为 attacker 寻找连续威胁必胜（VCT）主变化
+#[tauri::command]
+pub fn find_vct(board: Vec<Vec<i32>>, attacker: i32, rule: Option<String>) -> Result<VctOutcome, String> {
+    let board = Board::from_nested(&board)?;
+    Ok(shared_core::find_vct(&board, attacker, parse_rule(rule)?, VctLimits::default()))
+}
+
+// 分析接口：player 的前 multi_pv 个候选着法（默认3个），各带分数和主要变化
+#[tauri::command]
+pub fn analyze_position(board: Vec<Vec<i32>>, player: i32, difficulty: String, rule: Option<String>, multi_pv: Option<usize>) -> Result<Vec<PvLine>, String> {
+    let board = Board::from_nested(&board)?;
+    Ok(shared_core::analyze_position(&board, player, parse_difficulty(&difficulty), parse_rule(rule)?, multi_pv.unwrap_or(3)))
 }
 
 // 创建新的游戏状态
 #[tauri::command]
-pub fn create_game_state(ai_player: i32, human_player: i32) -> GameState {
-    shared_core::create_game_state(ai_player, human_player)
+pub fn create_game_state(ai_player: i32, human_player: i32, rule: Option<String>, size: Option<usize>) -> Result<GameState, String> {
+    shared_core::create_game_state_with_size(ai_player, human_player, parse_rule(rule)?, size.unwrap_or(BOARD_SIZE))
 }
 
 // 执行移动
@@ -46,12 +92,16 @@ pub fn make_move(
     human_player: i32,
     row: usize,
     col: usize,
+    rule: Option<String>,
 ) -> Result<(bool, GameState), String> {
     let mut game_state = GameState {
-        board,
+        board: Board::from_nested(&board)?,
         current_player,
         ai_player,
         human_player,
+        rule: parse_rule(rule)?,
+        opening: OpeningRule::Free,
+        phase: GamePhase::Playing,
     };
     
     match shared_core::make_move(&mut game_state, row, col) {
@@ -60,16 +110,57 @@ pub fn make_move(
     }
 }
 
+// 按开局协议创建游戏状态
+#[tauri::command]
+pub fn create_opening_game(rule: Option<String>, opening: String, proposer: Participant, size: Option<usize>) -> Result<GameState, String> {
+    shared_core::create_game_state_with_opening(parse_rule(rule)?, opening.parse()?, proposer, size.unwrap_or(BOARD_SIZE))
+}
+
+// 在完整的游戏状态上落子（含开局阶段）
+#[tauri::command]
+pub fn play_move(mut game_state: GameState, row: usize, col: usize) -> Result<(bool, GameState), String> {
+    let won = shared_core::make_move(&mut game_state, row, col)?;
+    Ok((won, game_state))
+}
+
+// 选色阶段的决定
+#[tauri::command]
+pub fn choose_color(mut game_state: GameState, choice: ColorChoice) -> Result<GameState, String> {
+    shared_core::choose_color(&mut game_state, choice)?;
+    Ok(game_state)
+}
+
+// AI在当前阶段的落子（摆开局棋子、给出/选择第五手或正常对局）
+#[tauri::command]
+pub fn ai_opening_move(game_state: GameState, difficulty: String) -> Result<MoveResult, String> {
+    shared_core::get_ai_opening_move(&game_state, parse_difficulty(&difficulty)).ok_or_else(|| "AI无法找到合适的移动".to_string())
+}
+
+// AI作为选择方的选色决定
+#[tauri::command]
+pub fn ai_color_choice(game_state: GameState, difficulty: String) -> Result<ColorChoice, String> {
+    shared_core::get_ai_color_choice(&game_state, parse_difficulty(&difficulty)).ok_or_else(|| "当前不是选色阶段".to_string())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             ai_move, 
+            ai_move_report,
             check_win, 
             evaluate_board, 
             get_possible_moves, 
+            find_vcf,
+            find_vct,
+            analyze_position,
             create_game_state, 
-            make_move
+            make_move,
+            create_opening_game,
+            play_move,
+            choose_color,
+            ai_opening_move,
+            ai_color_choice
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
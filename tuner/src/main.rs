@@ -0,0 +1,91 @@
+// 评估权重调参工具入口 - 读取对局记录（JSONL，每行一盘），用 Texel 方法拟合评估权重并写出结果
+//
+// 用法：
+//   gobang-tuner <对局记录.jsonl> [--out <权重.toml|json>] [--init <权重文件>] [--rounds N] [--skip-opening N]
+//
+// 对局记录示例（黑先，winner: 1 黑胜，-1 白胜，0 或省略为和棋；rule 和 size 可省略）：
+//   {"moves":[[7,7],[7,8],[8,8]],"winner":1,"rule":"renju","size":15}
+//
+// 不带 --out 时只报告初始权重的预测误差，可以用来比较两套手调权重哪套更好
+
+mod texel;
+
+use std::process::ExitCode;
+
+use shared_core::EvalWeights;
+
+struct Args {
+    games: String,
+    out: Option<String>,
+    init: Option<String>,
+    rounds: usize,
+    skip_opening: usize,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = std::env::args().skip(1);
+    let mut parsed = Args { games: String::new(), out: None, init: None, rounds: 50, skip_opening: 4 };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{} 需要参数", name));
+        match arg.as_str() {
+            "--out" => parsed.out = Some(value("--out")?),
+            "--init" => parsed.init = Some(value("--init")?),
+            "--rounds" => parsed.rounds = value("--rounds")?.parse().map_err(|_| "--rounds 需要正整数")?,
+            "--skip-opening" => {
+                parsed.skip_opening = value("--skip-opening")?.parse().map_err(|_| "--skip-opening 需要非负整数")?
+            }
+            _ if arg.starts_with("--") => return Err(format!("未知参数 {}", arg)),
+            _ if parsed.games.is_empty() => parsed.games = arg,
+            _ => return Err(format!("多余的参数 {}", arg)),
+        }
+    }
+    if parsed.games.is_empty() {
+        return Err("缺少对局记录文件".to_string());
+    }
+    Ok(parsed)
+}
+
+fn run() -> Result<(), String> {
+    let args = parse_args()?;
+    let text = std::fs::read_to_string(&args.games).map_err(|e| format!("无法读取对局记录 {}: {}", args.games, e))?;
+    let records = texel::parse_records(&text)?;
+    let samples = texel::extract_samples(&records, args.skip_opening)?;
+    if samples.is_empty() {
+        return Err("对局记录中没有可用的局面".to_string());
+    }
+    let initial = match &args.init {
+        Some(path) => EvalWeights::load(path)?,
+        None => EvalWeights::default(),
+    };
+
+    let scale = texel::fit_scale(&samples, &initial);
+    let initial_error = texel::error(&samples, &initial, scale);
+    eprintln!("{} 盘对局，{} 个局面；scale = {:.1}，初始误差 {:.6}", records.len(), samples.len(), scale, initial_error);
+
+    let Some(out) = args.out else {
+        return Ok(());
+    };
+    let (tuned, tuned_error) = texel::tune(&samples, initial, scale, args.rounds, |round, error| {
+        eprintln!("第 {} 轮：误差 {:.6}", round, error);
+    });
+    eprintln!("误差 {:.6} -> {:.6}", initial_error, tuned_error);
+    let (mut before, mut after) = (initial, tuned);
+    for ((name, old), (_, new)) in before.fields_mut().into_iter().zip(after.fields_mut()) {
+        if *old != *new {
+            eprintln!("  {}: {} -> {}", name, old, new);
+        }
+    }
+    tuned.save(&out)?;
+    eprintln!("已写出 {}", out);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
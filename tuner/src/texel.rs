@@ -0,0 +1,246 @@
+// Texel 调参 - 把对局中的局面按终局结果打标签，用 sigmoid(评估分 / scale) 预测行棋方的得分，
+// 以均方误差为目标，逐项按比例微调权重，直到任何一项都不能再降低误差
+
+use serde::Deserialize;
+use shared_core::{evaluate_board_with_weights, threat, Board, EvalWeights, RuleSet, BLACK};
+
+// 每项权重每次上调/下调的比例
+const STEP_RATIO: f64 = 0.1;
+
+// 一盘对局记录：黑先，winner 为 1（黑胜）、-1（白胜）、0 或缺省（和棋）
+#[derive(Debug, Deserialize)]
+pub struct GameRecord {
+    pub moves: Vec<(usize, usize)>,
+    #[serde(default)]
+    pub winner: Option<i32>,
+    #[serde(default)]
+    pub rule: RuleSet,
+    #[serde(default = "default_size")]
+    pub size: usize,
+}
+
+fn default_size() -> usize {
+    shared_core::BOARD_SIZE
+}
+
+// 一个带标签的局面：result 是行棋方的实际得分（胜 1、和 0.5、负 0）
+pub struct Sample {
+    pub board: Board,
+    pub player: i32,
+    pub rule: RuleSet,
+    pub result: f64,
+}
+
+// 从 JSONL 文本读取对局记录，每行一盘，空行忽略
+pub fn parse_records(text: &str) -> Result<Vec<GameRecord>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("第 {} 行不是有效的对局记录: {}", i + 1, e)))
+        .collect()
+}
+
+// 复盘每盘棋，跳过开局的 skip_opening 手和一步即可成五的战术局面（这类局面交给搜索，不该由评估函数拟合）
+pub fn extract_samples(records: &[GameRecord], skip_opening: usize) -> Result<Vec<Sample>, String> {
+    let mut samples = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        let mut board = Board::with_size(record.size)?;
+        let mut player = BLACK;
+        for (ply, &(row, col)) in record.moves.iter().enumerate() {
+            if ply >= skip_opening && is_quiet(&mut board, player, record.rule) {
+                let result = match record.winner {
+                    Some(winner) if winner == player => 1.0,
+                    Some(winner) if winner == -player => 0.0,
+                    _ => 0.5,
+                };
+                samples.push(Sample { board, player, rule: record.rule, result });
+            }
+            board
+                .try_make_move(row, col, player)
+                .map_err(|e| format!("第 {} 盘第 {} 手不是合法落子: {}", i + 1, ply + 1, e))?;
+            player = -player;
+        }
+    }
+    Ok(samples)
+}
+
+fn is_quiet(board: &mut Board, player: i32, rule: RuleSet) -> bool {
+    threat::five_points(board, player, rule).is_empty() && threat::five_points(board, -player, rule).is_empty()
+}
+
+fn sigmoid(score: f64, scale: f64) -> f64 {
+    1.0 / (1.0 + (-score / scale).exp())
+}
+
+// 先算出每个局面的评估分，调 scale 时不必重复评估
+pub fn scores(samples: &[Sample], weights: &EvalWeights) -> Vec<i32> {
+    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let chunk = samples.len().div_ceil(threads).max(1);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = samples
+            .chunks(chunk)
+            .map(|part| {
+                scope.spawn(move || {
+                    part.iter()
+                        .map(|s| evaluate_board_with_weights(&s.board, s.player, s.rule, weights))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles.into_iter().flat_map(|h| h.join().expect("评估线程崩溃")).collect()
+    })
+}
+
+fn error_of_scores(samples: &[Sample], scores: &[i32], scale: f64) -> f64 {
+    let sum: f64 = samples
+        .iter()
+        .zip(scores)
+        .map(|(s, &score)| (s.result - sigmoid(score as f64, scale)).powi(2))
+        .sum();
+    sum / samples.len().max(1) as f64
+}
+
+// 给定权重在样本上的均方误差
+pub fn error(samples: &[Sample], weights: &EvalWeights, scale: f64) -> f64 {
+    error_of_scores(samples, &scores(samples, weights), scale)
+}
+
+// 在对数网格上找让误差最小的 scale，再在最优点附近细分一次
+pub fn fit_scale(samples: &[Sample], weights: &EvalWeights) -> f64 {
+    let scores = scores(samples, weights);
+    let best_on = |candidates: &mut dyn Iterator<Item = f64>| {
+        candidates
+            .map(|scale| (scale, error_of_scores(samples, &scores, scale)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(scale, _)| scale)
+            .unwrap_or(1.0)
+    };
+    let coarse = best_on(&mut (0..=40).map(|i| 10f64.powf(1.0 + i as f64 * 0.1)));
+    best_on(&mut (-10..=10).map(|i| coarse * 10f64.powf(i as f64 * 0.01)))
+}
+
+// 逐项按比例上调或下调权重，接受能降低误差的改动；一轮没有任何改进或达到 max_rounds 轮时停止
+// 每轮结束时调用 on_round(轮次, 当前误差)
+pub fn tune(
+    samples: &[Sample],
+    initial: EvalWeights,
+    scale: f64,
+    max_rounds: usize,
+    mut on_round: impl FnMut(usize, f64),
+) -> (EvalWeights, f64) {
+    let mut best = initial;
+    let mut best_error = error(samples, &best, scale);
+    let field_count = best.fields_mut().len();
+    for round in 1..=max_rounds {
+        let mut improved = false;
+        for field in 0..field_count {
+            for up in [true, false] {
+                let mut candidate = best;
+                let (_, value) = &mut candidate.fields_mut()[field];
+                let step = ((**value as f64 * STEP_RATIO).round() as i32).max(1);
+                **value = if up { **value + step } else { (**value - step).max(0) };
+                if candidate == best {
+                    continue;
+                }
+                let candidate_error = error(samples, &candidate, scale);
+                if candidate_error < best_error {
+                    best = candidate;
+                    best_error = candidate_error;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        on_round(round, best_error);
+        if !improved {
+            break;
+        }
+    }
+    (best, best_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_core::WHITE;
+
+    // 黑方沿第 7 行走出活三再冲成四，白方在第 0 行应付，黑胜
+    fn four_game() -> GameRecord {
+        GameRecord {
+            moves: vec![(7, 3), (0, 0), (7, 4), (0, 2), (7, 5), (0, 4), (7, 6), (14, 14), (7, 7)],
+            winner: Some(BLACK),
+            rule: RuleSet::Freestyle,
+            size: 15,
+        }
+    }
+
+    // 由种子决定的一串不重复落点，黑白交替
+    fn random_game(seed: u64, length: usize, winner: Option<i32>) -> GameRecord {
+        let mut state = seed;
+        let mut moves = Vec::new();
+        while moves.len() < length {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let cell = ((state >> 33) % 81) as usize;
+            let pos = (3 + cell / 9, 3 + cell % 9);
+            if !moves.contains(&pos) {
+                moves.push(pos);
+            }
+        }
+        GameRecord { moves, winner, rule: RuleSet::Freestyle, size: 15 }
+    }
+
+    fn random_samples() -> Vec<Sample> {
+        let records: Vec<GameRecord> = (0..12)
+            .map(|seed| random_game(seed, 14, [Some(BLACK), Some(WHITE), None][seed as usize % 3]))
+            .collect();
+        extract_samples(&records, 2).unwrap()
+    }
+
+    #[test]
+    fn samples_are_labelled_for_the_side_to_move_and_skip_tactics() {
+        let samples = extract_samples(&[four_game()], 0).unwrap();
+        // 黑冲四之后（第 7、8 手之前）有一步成五，不算平静局面
+        assert_eq!(samples.len(), 7);
+        for (ply, sample) in samples.iter().enumerate() {
+            assert_eq!(sample.board.stone_count(), ply);
+            let (player, result) = if ply % 2 == 0 { (BLACK, 1.0) } else { (WHITE, 0.0) };
+            assert_eq!((sample.player, sample.result), (player, result));
+        }
+
+        assert_eq!(extract_samples(&[four_game()], 2).unwrap().len(), 5);
+        let draw = GameRecord { winner: None, ..four_game() };
+        assert!(extract_samples(&[draw], 0).unwrap().iter().all(|s| s.result == 0.5));
+
+        let illegal = GameRecord { moves: vec![(7, 7), (7, 7)], ..four_game() };
+        assert!(extract_samples(&[illegal], 0).is_err());
+    }
+
+    #[test]
+    fn fit_scale_recovers_a_known_scale() {
+        let weights = EvalWeights::default();
+        let mut samples = random_samples();
+        let evaluated = scores(&samples, &weights);
+        assert!(evaluated.iter().any(|&score| score != 0));
+        // 标签恰好是 scale 为 400 时的预测值
+        for (sample, &score) in samples.iter_mut().zip(&evaluated) {
+            sample.result = sigmoid(score as f64, 400.0);
+        }
+        let scale = fit_scale(&samples, &weights);
+        assert!((scale - 400.0).abs() < 400.0 * 0.05, "scale {}", scale);
+        assert!(error(&samples, &weights, scale) < 1e-4);
+    }
+
+    #[test]
+    fn tune_never_increases_the_error() {
+        let samples = random_samples();
+        let initial = EvalWeights::default();
+        let scale = fit_scale(&samples, &initial);
+        let initial_error = error(&samples, &initial, scale);
+
+        let mut rounds = Vec::new();
+        let (tuned, tuned_error) = tune(&samples, initial, scale, 1, |round, err| rounds.push((round, err)));
+        assert!(tuned_error <= initial_error);
+        assert_eq!(rounds, [(1, tuned_error)]);
+        assert_eq!(error(&samples, &tuned, scale), tuned_error);
+    }
+}
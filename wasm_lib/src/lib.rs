@@ -8,18 +8,31 @@ pub fn main() {
     console_error_panic_hook::set_once();
 }
 
+// 解析可选的规则参数，未传时使用自由规则
+fn parse_rule(rule: Option<String>) -> Result<RuleSet, JsValue> {
+    match rule {
+        Some(rule) => rule.parse().map_err(|e: String| JsValue::from_str(&e)),
+        None => Ok(RuleSet::Freestyle),
+    }
+}
+
+// 解析难度等级，未知值按简单处理
+fn parse_difficulty(difficulty: &str) -> AIDifficulty {
+    match difficulty {
+        "easy" => AIDifficulty::Easy,
+        "medium" => AIDifficulty::Medium,
+        "hard" => AIDifficulty::Hard,
+        _ => AIDifficulty::Easy, // 默认简单
+    }
+}
+
 // 将共享核心的AI移动函数暴露给JavaScript
 #[wasm_bindgen]
 pub fn get_best_move_wasm(board: &[i32], ai_player: i32, human_player: i32) -> Result<JsValue, JsValue> {
-    // 将一维数组转换为二维数组
-    let mut board_2d = vec![vec![0; BOARD_SIZE]; BOARD_SIZE];
-    for i in 0..board.len() {
-        let row = i / BOARD_SIZE;
-        let col = i % BOARD_SIZE;
-        board_2d[row][col] = board[i];
-    }
+    // 将一维数组转换为位棋盘
+    let board = Board::from_flat(board).map_err(|e| JsValue::from_str(&e))?;
     
-    match get_best_move(&board_2d, ai_player, human_player) {
+    match get_best_move(&board, ai_player, human_player) {
         Some(move_result) => {
             let js_value = serde_wasm_bindgen::to_value(&move_result)
                 .map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e)))?;
@@ -31,24 +44,14 @@ pub fn get_best_move_wasm(board: &[i32], ai_player: i32, human_player: i32) -> R
 
 // 支持难度等级的AI移动函数
 #[wasm_bindgen]
-pub fn get_best_move_with_difficulty_wasm(board: &[i32], ai_player: i32, human_player: i32, difficulty: &str) -> Result<JsValue, JsValue> {
-    // 将一维数组转换为二维数组
-    let mut board_2d = vec![vec![0; BOARD_SIZE]; BOARD_SIZE];
-    for i in 0..board.len() {
-        let row = i / BOARD_SIZE;
-        let col = i % BOARD_SIZE;
-        board_2d[row][col] = board[i];
-    }
+pub fn get_best_move_with_difficulty_wasm(board: &[i32], ai_player: i32, human_player: i32, difficulty: &str, rule: Option<String>) -> Result<JsValue, JsValue> {
+    // 将一维数组转换为位棋盘
+    let board = Board::from_flat(board).map_err(|e| JsValue::from_str(&e))?;
+    let rule = parse_rule(rule)?;
     
-    // 解析难度等级
-    let ai_difficulty = match difficulty {
-        "easy" => AIDifficulty::Easy,
-        "medium" => AIDifficulty::Medium,
-        "hard" => AIDifficulty::Hard,
-        _ => AIDifficulty::Easy, // 默认简单
-    };
+    let ai_difficulty = parse_difficulty(difficulty);
     
-    match get_best_move_with_difficulty(&board_2d, ai_player, human_player, ai_difficulty) {
+    match get_best_move_with_rule(&board, ai_player, human_player, ai_difficulty, rule) {
         Some(move_result) => {
             let js_value = serde_wasm_bindgen::to_value(&move_result)
                 .map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e)))?;
@@ -58,52 +61,84 @@ pub fn get_best_move_with_difficulty_wasm(board: &[i32], ai_player: i32, human_p
     }
 }
 
-// 检查获胜状态
+// 调试接口：AI移动及搜索统计（决定着法的阶段、深度、节点数、用时）和追踪事件
 #[wasm_bindgen]
-pub fn check_win_wasm(board: &[i32], row: usize, col: usize) -> bool {
-    let mut board_2d = vec![vec![0; BOARD_SIZE]; BOARD_SIZE];
-    for i in 0..board.len() {
-        let r = i / BOARD_SIZE;
-        let c = i % BOARD_SIZE;
-        board_2d[r][c] = board[i];
-    }
+pub fn get_best_move_report_wasm(board: &[i32], ai_player: i32, human_player: i32, difficulty: &str, rule: Option<String>) -> Result<JsValue, JsValue> {
+    let board = Board::from_flat(board).map_err(|e| JsValue::from_str(&e))?;
+    let rule = parse_rule(rule)?;
     
-    check_win(&board_2d, row, col)
+    let report = get_best_move_report(&board, ai_player, human_player, parse_difficulty(difficulty), rule);
+    let js_value = serde_wasm_bindgen::to_value(&report)
+        .map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e)))?;
+    Ok(js_value)
+}
+
+// 检查获胜状态
+#[wasm_bindgen]
+pub fn check_win_wasm(board: &[i32], row: usize, col: usize, rule: Option<String>) -> Result<bool, JsValue> {
+    let board = Board::from_flat(board).map_err(|e| JsValue::from_str(&e))?;
+    Ok(check_win_with_rule(&board, row, col, parse_rule(rule)?))
 }
 
 // 评估棋盘状态
 #[wasm_bindgen]
-pub fn evaluate_board_wasm(board: &[i32], player: i32) -> i32 {
-    let mut board_2d = vec![vec![0; BOARD_SIZE]; BOARD_SIZE];
-    for i in 0..board.len() {
-        let r = i / BOARD_SIZE;
-        let c = i % BOARD_SIZE;
-        board_2d[r][c] = board[i];
-    }
-    
-    evaluate_board(&board_2d, player)
+pub fn evaluate_board_wasm(board: &[i32], player: i32, rule: Option<String>) -> Result<i32, JsValue> {
+    let board = Board::from_flat(board).map_err(|e| JsValue::from_str(&e))?;
+    Ok(evaluate_board_with_rule(&board, player, parse_rule(rule)?))
 }
 
 // 获取可能的移动位置
 #[wasm_bindgen]
 pub fn get_possible_moves_wasm(board: &[i32]) -> Result<JsValue, JsValue> {
-    let mut board_2d = vec![vec![0; BOARD_SIZE]; BOARD_SIZE];
-    for i in 0..board.len() {
-        let r = i / BOARD_SIZE;
-        let c = i % BOARD_SIZE;
-        board_2d[r][c] = board[i];
-    }
+    let board = Board::from_flat(board).map_err(|e| JsValue::from_str(&e))?;
     
-    let moves = get_possible_moves(&board_2d);
+    let moves = get_possible_moves(&board);
     let js_value = serde_wasm_bindgen::to_value(&moves)
         .map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e)))?;
     Ok(js_value)
 }
 
+// 分析接口：为 attacker 寻找连续冲四必胜（VCF）序列
+#[wasm_bindgen]
+pub fn find_vcf_wasm(board: &[i32], attacker: i32, rule: Option<String>) -> Result<JsValue, JsValue> {
+    let board = Board::from_flat(board).map_err(|e| JsValue::from_str(&e))?;
+    let rule = parse_rule(rule)?;
+    
+    let outcome = find_vcf(&board, attacker, rule, VcfLimits::default());
+    let js_value = serde_wasm_bindgen::to_value(&outcome)
+        .map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e)))?;
+    Ok(js_value)
+}
+
+// 分析接口：为 attacker 寻找连续威胁必胜（VCT）主变化
+#[wasm_bindgen]
+pub fn find_vct_wasm(board: &[i32], attacker: i32, rule: Option<String>) -> Result<JsValue, JsValue> {
+    let board = Board::from_flat(board).map_err(|e| JsValue::from_str(&e))?;
+    let rule = parse_rule(rule)?;
+    
+    let outcome = find_vct(&board, attacker, rule, VctLimits::default());
+    let js_value = serde_wasm_bindgen::to_value(&outcome)
+        .map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e)))?;
+    Ok(js_value)
+}
+
+// 分析接口：player 的前 multi_pv 个候选着法（默认3个），各带分数和主要变化
+#[wasm_bindgen]
+pub fn analyze_position_wasm(board: &[i32], player: i32, difficulty: &str, rule: Option<String>, multi_pv: Option<usize>) -> Result<JsValue, JsValue> {
+    let board = Board::from_flat(board).map_err(|e| JsValue::from_str(&e))?;
+    let rule = parse_rule(rule)?;
+    
+    let lines = analyze_position(&board, player, parse_difficulty(difficulty), rule, multi_pv.unwrap_or(3));
+    let js_value = serde_wasm_bindgen::to_value(&lines)
+        .map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e)))?;
+    Ok(js_value)
+}
+
 // 创建新的游戏状态
 #[wasm_bindgen]
-pub fn create_game_state_wasm(ai_player: i32, human_player: i32) -> Result<JsValue, JsValue> {
-    let game_state = create_game_state(ai_player, human_player);
+pub fn create_game_state_wasm(ai_player: i32, human_player: i32, rule: Option<String>, size: Option<usize>) -> Result<JsValue, JsValue> {
+    let size = size.unwrap_or(BOARD_SIZE);
+    let game_state = create_game_state_with_size(ai_player, human_player, parse_rule(rule)?, size).map_err(|e| JsValue::from_str(&e))?;
     let js_value = serde_wasm_bindgen::to_value(&game_state)
         .map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e)))?;
     Ok(js_value)
@@ -118,19 +153,18 @@ pub fn make_move_wasm(
     human_player: i32,
     row: usize,
     col: usize,
+    rule: Option<String>,
 ) -> Result<JsValue, JsValue> {
-    let mut board_2d = vec![vec![0; BOARD_SIZE]; BOARD_SIZE];
-    for i in 0..board.len() {
-        let r = i / BOARD_SIZE;
-        let c = i % BOARD_SIZE;
-        board_2d[r][c] = board[i];
-    }
+    let board = Board::from_flat(board).map_err(|e| JsValue::from_str(&e))?;
     
     let mut game_state = GameState {
-        board: board_2d,
+        board,
         current_player,
         ai_player,
         human_player,
+        rule: parse_rule(rule)?,
+        opening: OpeningRule::Free,
+        phase: GamePhase::Playing,
     };
     
     match make_move(&mut game_state, row, col) {
@@ -141,4 +175,59 @@ pub fn make_move_wasm(
         }
         Err(e) => Err(JsValue::from_str(&e)),
     }
-}
\ No newline at end of file
+}
+
+// 按开局协议创建游戏状态；proposer 为 "ai" 或 "human"，size 默认为15
+#[wasm_bindgen]
+pub fn create_opening_game_wasm(rule: Option<String>, opening: &str, proposer: &str, size: Option<usize>) -> Result<JsValue, JsValue> {
+    let opening: OpeningRule = opening.parse().map_err(|e: String| JsValue::from_str(&e))?;
+    let proposer = match proposer {
+        "ai" => Participant::Ai,
+        "human" => Participant::Human,
+        _ => return Err(JsValue::from_str("未知的提议方")),
+    };
+    let game_state = create_game_state_with_opening(parse_rule(rule)?, opening, proposer, size.unwrap_or(BOARD_SIZE))
+        .map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&game_state).map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e)))
+}
+
+// 在完整的游戏状态上落子（含开局阶段），返回 (是否获胜, 新状态)
+#[wasm_bindgen]
+pub fn play_move_wasm(game_state: JsValue, row: usize, col: usize) -> Result<JsValue, JsValue> {
+    let mut game_state: GameState = serde_wasm_bindgen::from_value(game_state)
+        .map_err(|e| JsValue::from_str(&format!("反序列化错误: {}", e)))?;
+    let won = make_move(&mut game_state, row, col).map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&(won, game_state)).map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e)))
+}
+
+// 选色阶段的决定："black"、"white" 或 "place_two"
+#[wasm_bindgen]
+pub fn choose_color_wasm(game_state: JsValue, choice: &str) -> Result<JsValue, JsValue> {
+    let mut game_state: GameState = serde_wasm_bindgen::from_value(game_state)
+        .map_err(|e| JsValue::from_str(&format!("反序列化错误: {}", e)))?;
+    let choice: ColorChoice = choice.parse().map_err(|e: String| JsValue::from_str(&e))?;
+    choose_color(&mut game_state, choice).map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&game_state).map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e)))
+}
+
+// AI在当前阶段的落子（摆开局棋子、给出/选择第五手或正常对局）
+#[wasm_bindgen]
+pub fn get_ai_opening_move_wasm(game_state: JsValue, difficulty: &str) -> Result<JsValue, JsValue> {
+    let game_state: GameState = serde_wasm_bindgen::from_value(game_state)
+        .map_err(|e| JsValue::from_str(&format!("反序列化错误: {}", e)))?;
+    match get_ai_opening_move(&game_state, parse_difficulty(difficulty)) {
+        Some(move_result) => serde_wasm_bindgen::to_value(&move_result).map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e))),
+        None => Err(JsValue::from_str("AI无法找到合适的移动")),
+    }
+}
+
+// AI作为选择方的选色决定
+#[wasm_bindgen]
+pub fn get_ai_color_choice_wasm(game_state: JsValue, difficulty: &str) -> Result<JsValue, JsValue> {
+    let game_state: GameState = serde_wasm_bindgen::from_value(game_state)
+        .map_err(|e| JsValue::from_str(&format!("反序列化错误: {}", e)))?;
+    match get_ai_color_choice(&game_state, parse_difficulty(difficulty)) {
+        Some(choice) => serde_wasm_bindgen::to_value(&choice).map_err(|e| JsValue::from_str(&format!("序列化错误: {}", e))),
+        None => Err(JsValue::from_str("当前不是选色阶段")),
+    }
+}